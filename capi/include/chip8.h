@@ -0,0 +1,83 @@
+// chip8.h is the C API of the chip8 interpreter. It is generated from
+// capi/src/lib.rs by chip8-header; do not edit it.
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// A machine, made by chip8_new and freed by chip8_free.
+typedef struct Chip8 Chip8;
+
+// The functions that can fail return CHIP8_OK when they succeed.
+#define CHIP8_OK 0
+
+// The machine faulted, or the call panicked, and the machine should not be
+// run any further.
+#define CHIP8_FAULT 1
+
+// An argument was NULL or invalid, and the machine was left unchanged.
+#define CHIP8_ERROR (-1)
+
+// The width and height of the display, in pixels.
+#define CHIP8_WIDTH 64
+#define CHIP8_HEIGHT 32
+
+// The size of a saved state, in bytes.
+#define CHIP8_STATE_SIZE 4425
+
+// Creates a machine, with nothing loaded, or returns NULL if it cannot.
+// Free it with chip8_free.
+Chip8 *chip8_new(void);
+
+// Frees a machine made by chip8_new. Freeing NULL does nothing.
+void chip8_free(Chip8 *c8);
+
+// Loads a ROM of len bytes at 0x200, and picks the quirks it was written
+// for. The speed and colors it plays best with are left to the caller, see
+// chip8_speed and chip8_palette. Fails when the ROM does not fit in memory.
+int chip8_load_rom(Chip8 *c8, const uint8_t *rom, size_t len);
+
+// Returns the instructions per frame the loaded ROM plays best at, to pass
+// to chip8_run_frame: its own for the games the interpreter knows, and 10
+// for the rest. Returns 0 for NULL.
+size_t chip8_speed(const Chip8 *c8);
+
+// Copies the colors the loaded ROM is best drawn with into colors, which is
+// len bytes long: the RGB of lit pixels, then of the background. len must
+// be at least 6.
+int chip8_palette(const Chip8 *c8, uint8_t *colors, size_t len);
+
+// Seeds the random number generator, so that runs can be repeated.
+int chip8_seed(Chip8 *c8, uint64_t seed);
+
+// Runs a 60 Hz frame: the given number of instructions, 10 being usual,
+// then a tick of the timers.
+int chip8_run_frame(Chip8 *c8, size_t instructions);
+
+// Presses or releases a key of the hex keypad, 0x0 to 0xF.
+int chip8_set_key(Chip8 *c8, uint8_t key, bool pressed);
+
+// Copies the display into pixels, one byte per pixel, row by row: 1 for a
+// lit pixel and 0 for an unlit one. len must be at least CHIP8_WIDTH *
+// CHIP8_HEIGHT.
+int chip8_framebuffer(const Chip8 *c8, uint8_t *pixels, size_t len);
+
+// Saves the state of the machine into the first CHIP8_STATE_SIZE bytes of
+// state, which is len bytes long.
+int chip8_save_state(const Chip8 *c8, uint8_t *state, size_t len);
+
+// Restores a state saved by chip8_save_state, of len bytes. Fails when it
+// is not a valid state.
+int chip8_load_state(Chip8 *c8, const uint8_t *state, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,6 @@
+// chip8-header prints the C header of the API, for include/chip8.h:
+//
+//     cargo run -p chip8-capi --bin chip8-header > capi/include/chip8.h
+fn main() {
+    print!("{}", chip8_capi::header());
+}
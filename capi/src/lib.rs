@@ -0,0 +1,360 @@
+// The C API embeds the interpreter in programs written in other languages,
+// which link against this shared library, libchip8_capi. Machines are made
+// by chip8_new and passed around as opaque pointers until chip8_free. Every
+// function taking a machine or a buffer accepts NULL, and fails on it.
+//
+// The header, include/chip8.h, is generated from this file by header and
+// written by chip8-header. The comments of the constants and the functions
+// are copied into it, so they are written for C programmers.
+//
+// The functions are unsafe as C is: pointers must be NULL or valid, and
+// buffers as long as their length says. Panics do not unwind into C: a
+// function that panics fails instead.
+#![allow(clippy::missing_safety_doc)]
+
+use chip8::interpreter::chip8::state::STATE_SIZE;
+use chip8::interpreter::chip8::{Chip8, HEIGHT, N_KEYS, WIDTH};
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+// The functions that can fail return CHIP8_OK when they succeed.
+pub const CHIP8_OK: c_int = 0;
+
+// The machine faulted, or the call panicked, and the machine should not be
+// run any further.
+pub const CHIP8_FAULT: c_int = 1;
+
+// An argument was NULL or invalid, and the machine was left unchanged.
+pub const CHIP8_ERROR: c_int = -1;
+
+// The width and height of the display, in pixels.
+pub const CHIP8_WIDTH: usize = 64;
+pub const CHIP8_HEIGHT: usize = 32;
+
+// The size of a saved state, in bytes.
+pub const CHIP8_STATE_SIZE: usize = 4425;
+
+// The constants are written out for the header, and must match the crate.
+const _: () = assert!(CHIP8_WIDTH == WIDTH && CHIP8_HEIGHT == HEIGHT);
+const _: () = assert!(CHIP8_STATE_SIZE == STATE_SIZE);
+
+// Creates a machine, with nothing loaded, or returns NULL if it cannot.
+// Free it with chip8_free.
+#[no_mangle]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    guard(ptr::null_mut(), || Box::into_raw(Box::new(Chip8::new())))
+}
+
+// Frees a machine made by chip8_new. Freeing NULL does nothing.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(c8: *mut Chip8) {
+    guard((), || {
+        if !c8.is_null() {
+            drop(Box::from_raw(c8));
+        }
+    })
+}
+
+// Loads a ROM of len bytes at 0x200, and picks the quirks it was written
+// for. The speed and colors it plays best with are left to the caller, see
+// chip8_speed and chip8_palette. Fails when the ROM does not fit in memory.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    c8: *mut Chip8,
+    rom: *const u8,
+    len: usize,
+) -> c_int {
+    guard(CHIP8_FAULT, || match (c8.as_mut(), bytes(rom, len)) {
+        (Some(c8), Some(rom)) => match c8.load_rom_bytes(rom) {
+            Ok(()) => CHIP8_OK,
+            Err(_) => CHIP8_ERROR,
+        },
+        _ => CHIP8_ERROR,
+    })
+}
+
+// Returns the instructions per frame the loaded ROM plays best at, to pass
+// to chip8_run_frame: its own for the games the interpreter knows, and 10
+// for the rest. Returns 0 for NULL.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_speed(c8: *const Chip8) -> usize {
+    guard(0, || c8.as_ref().map_or(0, Chip8::recommended_speed))
+}
+
+// Copies the colors the loaded ROM is best drawn with into colors, which is
+// len bytes long: the RGB of lit pixels, then of the background. len must
+// be at least 6.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_palette(
+    c8: *const Chip8,
+    colors: *mut u8,
+    len: usize,
+) -> c_int {
+    guard(CHIP8_FAULT, || {
+        match (c8.as_ref(), bytes_mut(colors, len)) {
+            (Some(c8), Some(colors)) if len >= 6 => {
+                let palette = c8.recommended_palette();
+                colors[..3].copy_from_slice(&palette.on);
+                colors[3..6].copy_from_slice(&palette.off);
+                CHIP8_OK
+            }
+            _ => CHIP8_ERROR,
+        }
+    })
+}
+
+// Seeds the random number generator, so that runs can be repeated.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_seed(c8: *mut Chip8, seed: u64) -> c_int {
+    guard(CHIP8_FAULT, || match c8.as_mut() {
+        Some(c8) => {
+            c8.seed(seed);
+            CHIP8_OK
+        }
+        None => CHIP8_ERROR,
+    })
+}
+
+// Runs a 60 Hz frame: the given number of instructions, 10 being usual,
+// then a tick of the timers.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(
+    c8: *mut Chip8,
+    instructions: usize,
+) -> c_int {
+    guard(CHIP8_FAULT, || match c8.as_mut() {
+        Some(c8) => match c8.run_frame(instructions) {
+            Ok(()) => CHIP8_OK,
+            Err(_) => CHIP8_FAULT,
+        },
+        None => CHIP8_ERROR,
+    })
+}
+
+// Presses or releases a key of the hex keypad, 0x0 to 0xF.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(
+    c8: *mut Chip8,
+    key: u8,
+    pressed: bool,
+) -> c_int {
+    guard(CHIP8_FAULT, || match c8.as_mut() {
+        Some(c8) if (key as usize) < N_KEYS => {
+            c8.set_key(key, pressed);
+            CHIP8_OK
+        }
+        _ => CHIP8_ERROR,
+    })
+}
+
+// Copies the display into pixels, one byte per pixel, row by row: 1 for a
+// lit pixel and 0 for an unlit one. len must be at least CHIP8_WIDTH *
+// CHIP8_HEIGHT.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(
+    c8: *const Chip8,
+    pixels: *mut u8,
+    len: usize,
+) -> c_int {
+    guard(CHIP8_FAULT, || {
+        match (c8.as_ref(), bytes_mut(pixels, len)) {
+            (Some(c8), Some(pixels)) if len >= WIDTH * HEIGHT => {
+                for (pixel, on) in pixels.iter_mut().zip(c8.display().pixels())
+                {
+                    *pixel = on as u8;
+                }
+                CHIP8_OK
+            }
+            _ => CHIP8_ERROR,
+        }
+    })
+}
+
+// Saves the state of the machine into the first CHIP8_STATE_SIZE bytes of
+// state, which is len bytes long.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(
+    c8: *const Chip8,
+    state: *mut u8,
+    len: usize,
+) -> c_int {
+    guard(CHIP8_FAULT, || match (c8.as_ref(), bytes_mut(state, len)) {
+        (Some(c8), Some(state)) => match c8.save_state(state) {
+            Ok(()) => CHIP8_OK,
+            Err(_) => CHIP8_ERROR,
+        },
+        _ => CHIP8_ERROR,
+    })
+}
+
+// Restores a state saved by chip8_save_state, of len bytes. Fails when it
+// is not a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    c8: *mut Chip8,
+    state: *const u8,
+    len: usize,
+) -> c_int {
+    guard(CHIP8_FAULT, || match (c8.as_mut(), bytes(state, len)) {
+        (Some(c8), Some(state)) => match c8.load_state(state) {
+            Ok(()) => CHIP8_OK,
+            Err(_) => CHIP8_ERROR,
+        },
+        _ => CHIP8_ERROR,
+    })
+}
+
+// guard runs the body of a function, returning the given result if it
+// panics, as unwinding into C is undefined behaviour.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+// bytes returns a buffer from C, unless it is NULL.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match data.is_null() {
+        true => None,
+        false => Some(slice::from_raw_parts(data, len)),
+    }
+}
+
+// bytes_mut returns a buffer from C to write to, unless it is NULL.
+unsafe fn bytes_mut<'a>(data: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    match data.is_null() {
+        true => None,
+        false => Some(slice::from_raw_parts_mut(data, len)),
+    }
+}
+
+// SOURCE is this file, which the header is generated from.
+const SOURCE: &str = include_str!("lib.rs");
+
+// header returns the C header of the API, made of the constants and the
+// functions of this file with their comments.
+pub fn header() -> String {
+    let mut out = String::from(
+        "\
+// chip8.h is the C API of the chip8 interpreter. It is generated from
+// capi/src/lib.rs by chip8-header; do not edit it.
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern \"C\" {
+#endif
+
+// A machine, made by chip8_new and freed by chip8_free.
+typedef struct Chip8 Chip8;
+",
+    );
+    let mut comment = Vec::new();
+    let mut lines = SOURCE.lines();
+    while let Some(line) = lines.next() {
+        if line.starts_with("#[") {
+            continue;
+        }
+        if let Some(text) = line.strip_prefix("// ") {
+            comment.push(text);
+            continue;
+        }
+        if let Some(constant) = line.strip_prefix("pub const ") {
+            if !comment.is_empty() {
+                out += "\n";
+                for text in comment.drain(..) {
+                    out += &format!("// {}\n", text);
+                }
+            }
+            let (name, rest) = constant.split_at(constant.find(':').unwrap());
+            let value = rest.split("= ").nth(1).unwrap().trim_end_matches(';');
+            match value.starts_with('-') {
+                true => out += &format!("#define {} ({})\n", name, value),
+                false => out += &format!("#define {} {}\n", name, value),
+            }
+            continue;
+        }
+        if line.starts_with("pub extern \"C\" fn")
+            || line.starts_with("pub unsafe extern \"C\" fn")
+        {
+            // The signature ends at the brace of the body
+            let mut signature = String::from(line);
+            while !signature.ends_with('{') {
+                signature += lines.next().unwrap().trim();
+            }
+            out += "\n";
+            for text in comment.drain(..) {
+                out += &format!("// {}\n", text);
+            }
+            out += &format!("{};\n", prototype(&signature));
+        }
+        comment.clear();
+    }
+    out += "
+#ifdef __cplusplus
+}
+#endif
+
+#endif
+";
+    out
+}
+
+// prototype returns the C prototype of a Rust function signature.
+fn prototype(signature: &str) -> String {
+    let name_start = signature.find("fn ").unwrap() + 3;
+    let params_start = signature.find('(').unwrap();
+    let params_end = signature.rfind(')').unwrap();
+    let name = &signature[name_start..params_start];
+    let params: Vec<String> = signature[params_start + 1..params_end]
+        .split(',')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, rust) = param.split_at(param.find(':').unwrap());
+            let c = c_type(rust[1..].trim());
+            match c.ends_with('*') {
+                true => format!("{}{}", c, name),
+                false => format!("{} {}", c, name),
+            }
+        })
+        .collect();
+    let result = signature[params_end + 1..]
+        .trim_end_matches('{')
+        .trim()
+        .strip_prefix("-> ")
+        .map_or("void", c_type);
+    let params = match params.is_empty() {
+        true => String::from("void"),
+        false => params.join(", "),
+    };
+    let c = match result.ends_with('*') {
+        true => format!("{}{}({})", result, name, params),
+        false => format!("{} {}({})", result, name, params),
+    };
+    // Prototypes too long for a line are broken after the parenthesis
+    match c.len() < 80 {
+        true => c,
+        false => c.replacen('(', "(\n    ", 1),
+    }
+}
+
+// c_type returns the C type of a Rust type of the API.
+fn c_type(rust: &str) -> &'static str {
+    match rust {
+        "*mut Chip8" => "Chip8 *",
+        "*const Chip8" => "const Chip8 *",
+        "*mut u8" => "uint8_t *",
+        "*const u8" => "const uint8_t *",
+        "u8" => "uint8_t",
+        "u64" => "uint64_t",
+        "usize" => "size_t",
+        "bool" => "bool",
+        "c_int" => "int",
+        _ => panic!("no C type for {}", rust),
+    }
+}
@@ -0,0 +1,121 @@
+// api.c exercises the C API: it runs a ROM that waits for a key and draws
+// the digit of the key, saves and restores the machine, and makes every
+// function fail once. It prints the checks that failed.
+#include <stdio.h>
+#include <string.h>
+
+#include "chip8.h"
+
+static const uint8_t ROM[] = {
+    0x60, 0x00, // 0x200  LD V0, 0x00
+    0xF1, 0x0A, // 0x202  LD V1, K
+    0xF1, 0x29, // 0x204  LD F, V1
+    0xD0, 0x05, // 0x206  DRW V0, V0, 5
+    0x12, 0x08, // 0x208  JP 0x208
+};
+
+static const uint8_t UNSUPPORTED[] = {0xFF, 0xFF};
+
+static const uint8_t RECURSIVE[] = {
+    0x22, 0x00, // 0x200  CALL 0x200, until the stack overflows
+};
+
+static const uint8_t RETURNING[] = {
+    0x00, 0xEE, // 0x200  RET, with nothing on the stack
+};
+
+static int failures = 0;
+
+// check reports a check that failed.
+#define check(condition)                                                  \
+    do {                                                                  \
+        if (!(condition)) {                                               \
+            fprintf(stderr, "api.c:%d: %s\n", __LINE__, #condition);      \
+            failures++;                                                   \
+        }                                                                 \
+    } while (0)
+
+// lit returns the number of lit pixels of a display.
+static int lit(const uint8_t *pixels) {
+    int n = 0;
+    for (int i = 0; i < CHIP8_WIDTH * CHIP8_HEIGHT; i++) {
+        n += pixels[i];
+    }
+    return n;
+}
+
+int main(void) {
+    static uint8_t pixels[CHIP8_WIDTH * CHIP8_HEIGHT];
+    static uint8_t copied[CHIP8_WIDTH * CHIP8_HEIGHT];
+    static uint8_t state[CHIP8_STATE_SIZE];
+    static uint8_t too_large[4096];
+
+    Chip8 *c8 = chip8_new();
+    check(c8 != NULL);
+    check(chip8_load_rom(c8, ROM, sizeof ROM) == CHIP8_OK);
+    check(chip8_seed(c8, 1) == CHIP8_OK);
+
+    // Nothing is drawn until a key is pressed
+    check(chip8_run_frame(c8, 10) == CHIP8_OK);
+    check(chip8_framebuffer(c8, pixels, sizeof pixels) == CHIP8_OK);
+    check(lit(pixels) == 0);
+
+    // A is F0 90 F0 90 90, 14 pixels
+    check(chip8_set_key(c8, 0xA, true) == CHIP8_OK);
+    check(chip8_run_frame(c8, 10) == CHIP8_OK);
+    check(chip8_framebuffer(c8, pixels, sizeof pixels) == CHIP8_OK);
+    check(lit(pixels) == 14);
+    check(pixels[0] == 1 && pixels[3] == 1 && pixels[4] == 0);
+    check(pixels[CHIP8_WIDTH] == 1 && pixels[CHIP8_WIDTH + 1] == 0);
+
+    // Another machine goes on from a saved state
+    check(chip8_save_state(c8, state, sizeof state) == CHIP8_OK);
+    Chip8 *copy = chip8_new();
+    check(chip8_load_state(copy, state, sizeof state) == CHIP8_OK);
+    check(chip8_run_frame(copy, 10) == CHIP8_OK);
+    check(chip8_framebuffer(copy, copied, sizeof copied) == CHIP8_OK);
+    check(memcmp(pixels, copied, sizeof pixels) == 0);
+    chip8_free(copy);
+
+    // Unknown ROMs run at the usual speed, black on white
+    uint8_t colors[6];
+    check(chip8_speed(c8) == 10);
+    check(chip8_palette(c8, colors, sizeof colors) == CHIP8_OK);
+    check(colors[0] == 0x00 && colors[3] == 0xFF);
+
+    // Errors leave the machine as it was
+    check(chip8_load_rom(NULL, ROM, sizeof ROM) == CHIP8_ERROR);
+    check(chip8_load_rom(c8, NULL, 0) == CHIP8_ERROR);
+    check(chip8_load_rom(c8, too_large, sizeof too_large) == CHIP8_ERROR);
+    check(chip8_seed(NULL, 1) == CHIP8_ERROR);
+    check(chip8_run_frame(NULL, 10) == CHIP8_ERROR);
+    check(chip8_set_key(c8, 16, true) == CHIP8_ERROR);
+    check(chip8_framebuffer(c8, pixels, 10) == CHIP8_ERROR);
+    check(chip8_save_state(c8, state, 10) == CHIP8_ERROR);
+    check(chip8_speed(NULL) == 0);
+    check(chip8_palette(c8, colors, 5) == CHIP8_ERROR);
+    state[0] = 'X';
+    check(chip8_load_state(c8, state, sizeof state) == CHIP8_ERROR);
+    check(chip8_load_state(c8, state, 10) == CHIP8_ERROR);
+    check(chip8_framebuffer(c8, copied, sizeof copied) == CHIP8_OK);
+    check(memcmp(pixels, copied, sizeof pixels) == 0);
+    chip8_free(c8);
+    chip8_free(NULL);
+
+    // A machine faults on an opcode it does not support, and on running
+    // out of stack either way, and goes on faulting
+    const uint8_t *faulting[] = {UNSUPPORTED, RECURSIVE, RETURNING};
+    for (size_t i = 0; i < sizeof faulting / sizeof faulting[0]; i++) {
+        Chip8 *faulty = chip8_new();
+        check(chip8_load_rom(faulty, faulting[i], 2) == CHIP8_OK);
+        check(chip8_run_frame(faulty, 20) == CHIP8_FAULT);
+        check(chip8_run_frame(faulty, 20) == CHIP8_FAULT);
+        chip8_free(faulty);
+    }
+
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    return 0;
+}
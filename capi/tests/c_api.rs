@@ -0,0 +1,49 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn header_is_current() {
+    let header = fs::read_to_string("include/chip8.h").unwrap();
+    assert!(
+        header == chip8_capi::header(),
+        "include/chip8.h is out of date, regenerate it with
+    cargo run -p chip8-capi --bin chip8-header > capi/include/chip8.h"
+    );
+}
+
+// tests/c/api.c passes, compiled against the header and the shared library,
+// which is next to the tests. It is skipped without a C compiler.
+#[cfg(unix)]
+#[test]
+fn c_program() {
+    let lib = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("api");
+    let cc = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+    let compiled = Command::new(cc.as_str())
+        .args(["-std=c99", "-Wall", "-Wextra", "-pedantic", "-Werror"])
+        .args(["-Iinclude", "tests/c/api.c", "-lchip8_capi", "-o"])
+        .arg(program.as_os_str())
+        .arg(format!("-L{}", lib.display()))
+        .arg(format!("-Wl,-rpath,{}", lib.display()))
+        .status();
+    match compiled {
+        Ok(status) => assert!(status.success(), "api.c does not compile"),
+        Err(e) => {
+            eprintln!("skipped: {}: {}", cc, e);
+            return;
+        }
+    }
+    // Cargo puts target/debug first on the library path, which may hold an
+    // older build of the library
+    let output = Command::new(program.as_os_str())
+        .env("LD_LIBRARY_PATH", &lib)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,254 @@
+use super::interpreter::chip8::{MEM_SIZE, PROGRAM_START};
+use super::interpreter::instruction::Instruction;
+use super::interpreter::metadata::Flow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+// Edge is the kind of a control flow edge between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Next, // Falls through into the next block
+    Skip, // Taken when a skip instruction skips
+    Jump, // An unconditional jump
+    Call, // A subroutine call
+}
+
+// Block is a basic block: a run of instructions with a single entry and a
+// single exit.
+#[derive(Debug)]
+pub struct Block {
+    pub start: u16,
+    pub instructions: Vec<(u16, Instruction)>,
+    pub successors: Vec<(u16, Edge)>,
+}
+
+impl Block {
+    // end returns the address right after the last instruction of the block.
+    pub fn end(&self) -> u16 {
+        match self.instructions.last() {
+            Some((addr, _)) => addr + 2,
+            None => self.start,
+        }
+    }
+}
+
+// Analysis is the result of statically analyzing a ROM.
+#[derive(Debug)]
+pub struct Analysis {
+    pub start: u16, // The address the ROM is loaded at
+    pub end: u16,   // The address right after the ROM
+    pub blocks: BTreeMap<u16, Block>,
+    pub subroutines: BTreeMap<u16, BTreeSet<u16>>, // Entry -> blocks
+    pub indirect: BTreeSet<u16>, // Addresses of BNNN jumps, never followed
+    pub invalid: BTreeSet<u16>,  // Reachable addresses that did not decode
+    code: BTreeSet<u16>,         // Addresses of reachable instructions
+}
+
+impl Analysis {
+    // new analyzes a ROM loaded at PROGRAM_START, starting from its entry.
+    // Bytes past the end of memory are left out, as they are never loaded.
+    pub fn new(rom: &[u8]) -> Self {
+        let start = PROGRAM_START;
+        let rom = &rom[..rom.len().min(MEM_SIZE - start as usize)];
+        let end = start + rom.len() as u16;
+        let fetch = |addr: u16| -> Option<u16> {
+            if addr < start || addr as usize + 1 >= end as usize {
+                return None;
+            }
+            let i = (addr - start) as usize;
+            Some((rom[i] as u16) << 8 | rom[i + 1] as u16)
+        };
+
+        // Find every reachable instruction and the leaders of the blocks
+        let mut code: BTreeMap<u16, Instruction> = BTreeMap::new();
+        let mut leaders: BTreeSet<u16> = BTreeSet::new();
+        let mut calls: BTreeSet<u16> = BTreeSet::new();
+        let mut indirect = BTreeSet::new();
+        let mut invalid = BTreeSet::new();
+        let mut work = vec![start];
+        leaders.insert(start);
+        while let Some(addr) = work.pop() {
+            if code.contains_key(&addr) || invalid.contains(&addr) {
+                continue;
+            }
+            let instr = match fetch(addr).map(Instruction::try_from) {
+                Some(Ok(instr)) => instr,
+                _ => {
+                    invalid.insert(addr);
+                    continue;
+                }
+            };
+            code.insert(addr, instr);
+
+            let targets = successors(addr, &instr);
+            if instr.metadata().flow.branches() {
+                for (target, _) in &targets {
+                    leaders.insert(*target);
+                }
+            }
+            match instr.metadata().flow {
+                Flow::Call => {
+                    calls.insert(instr.addr());
+                }
+                Flow::JumpIndirect => {
+                    indirect.insert(addr);
+                }
+                _ => (),
+            }
+            work.extend(targets.iter().map(|(target, _)| *target));
+        }
+
+        // Split the reachable instructions into blocks
+        let mut blocks = BTreeMap::new();
+        for &leader in leaders.iter().filter(|l| code.contains_key(l)) {
+            let mut block = Block {
+                start: leader,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+            };
+            let mut addr = leader;
+            while let Some(&instr) = code.get(&addr) {
+                block.instructions.push((addr, instr));
+                if instr.metadata().flow.branches() {
+                    block.successors = successors(addr, &instr);
+                    break;
+                }
+                addr += 2;
+                if leaders.contains(&addr) && code.contains_key(&addr) {
+                    block.successors.push((addr, Edge::Next));
+                    break;
+                }
+            }
+            blocks.insert(leader, block);
+        }
+
+        let mut analysis = Self {
+            start,
+            end,
+            blocks,
+            subroutines: BTreeMap::new(),
+            indirect,
+            invalid,
+            code: code.keys().cloned().collect(),
+        };
+        for entry in calls {
+            let body = analysis.body(entry);
+            analysis.subroutines.insert(entry, body);
+        }
+        analysis
+    }
+
+    // body returns the blocks reachable from a subroutine entry without
+    // following calls.
+    fn body(&self, entry: u16) -> BTreeSet<u16> {
+        let mut body = BTreeSet::new();
+        let mut work = vec![entry];
+        while let Some(addr) = work.pop() {
+            if let Some(block) = self.blocks.get(&addr) {
+                if body.insert(addr) {
+                    work.extend(
+                        block
+                            .successors
+                            .iter()
+                            .filter(|(_, edge)| *edge != Edge::Call)
+                            .map(|(target, _)| *target),
+                    );
+                }
+            }
+        }
+        body
+    }
+
+    // is_code returns whether an instruction starts at the given address.
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.code.contains(&addr)
+    }
+
+    // is_reachable returns whether the byte at the given address belongs to a
+    // reachable instruction.
+    pub fn is_reachable(&self, addr: u16) -> bool {
+        self.is_code(addr) || (addr > 0 && self.is_code(addr - 1))
+    }
+
+    // unreachable returns the ranges of ROM bytes that do not belong to any
+    // reachable instruction, as [start, end) pairs. These are either dead code
+    // or data.
+    pub fn unreachable(&self) -> Vec<(u16, u16)> {
+        let mut ranges = Vec::new();
+        let mut from: Option<u16> = None;
+        for addr in self.start..self.end {
+            match (self.is_reachable(addr), from) {
+                (false, None) => from = Some(addr),
+                (true, Some(f)) => {
+                    ranges.push((f, addr));
+                    from = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(f) = from {
+            ranges.push((f, self.end));
+        }
+        ranges
+    }
+
+    // to_dot exports the control flow graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for (addr, instr) in &block.instructions {
+                write!(label, "0x{:03x}: {}\\l", addr, instr).unwrap();
+            }
+            let periphery = match self.subroutines.contains_key(&block.start) {
+                true => ", peripheries=2",
+                false => "",
+            };
+            writeln!(
+                dot,
+                "    b{:03x} [label=\"{}\"{}];",
+                block.start, label, periphery
+            )
+            .unwrap();
+        }
+        for block in self.blocks.values() {
+            for (target, edge) in &block.successors {
+                if !self.blocks.contains_key(target) {
+                    continue;
+                }
+                let style = match edge {
+                    Edge::Next => "",
+                    Edge::Skip => " [style=dashed, label=skip]",
+                    Edge::Jump => " [label=jump]",
+                    Edge::Call => " [style=bold, label=call]",
+                };
+                writeln!(
+                    dot,
+                    "    b{:03x} -> b{:03x}{};",
+                    block.start, target, style
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+// successors returns the addresses control can flow to after an instruction.
+// Calls are assumed to return to the next instruction. Addresses wrap around
+// at the end of memory, as the program counter does.
+fn successors(addr: u16, instr: &Instruction) -> Vec<(u16, Edge)> {
+    let wrap = |offset: usize| ((addr as usize + offset) % MEM_SIZE) as u16;
+    let (next, skip) = (wrap(2), wrap(4));
+    match instr.metadata().flow {
+        Flow::Next | Flow::Wait => vec![(next, Edge::Next)],
+        Flow::Skip => vec![(next, Edge::Next), (skip, Edge::Skip)],
+        Flow::Jump => vec![(instr.addr(), Edge::Jump)],
+        Flow::Call => vec![(instr.addr(), Edge::Call), (next, Edge::Next)],
+        Flow::Return | Flow::JumpIndirect => vec![],
+    }
+}
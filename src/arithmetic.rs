@@ -7,7 +7,7 @@ pub fn check_carry(x: &u8, y: &u8) -> u8 {
 
 pub fn check_borrow(x: &u8, y: &u8) -> u8 {
     // If no borrow
-    if x > y {
+    if x >= y {
         return 1;
     }
     // If borrow (if y > x)
@@ -1,7 +1,9 @@
+use super::interpreter::chip8::{MEM_SIZE, PROGRAM_START};
+use super::interpreter::metadata::{Operand, METADATA};
 use hex;
-use std::fmt::{Display, Formatter, Result};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io;
 
 struct ErrInvalidProgram;
 impl std::fmt::Display for ErrInvalidProgram {
@@ -10,6 +12,27 @@ impl std::fmt::Display for ErrInvalidProgram {
     }
 }
 
+// ErrAssembly is returned when a source file cannot be assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrAssembly {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ErrAssembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// Arg is a single parsed operand of a source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Arg {
+    V(usize),      // A V register
+    Fixed(String), // A fixed operand such as I, [I] or DT
+    Value(String), // A number or a label, resolved in the second pass
+}
+
 pub struct Assembler;
 impl Assembler {
     pub fn assemble(hex_prgm: &str, outfile: &str) -> std::io::Result<()> {
@@ -17,4 +40,194 @@ impl Assembler {
         fs::write(outfile, bytes)?;
         Ok(())
     }
+
+    // assemble_source assembles a program written in the syntax instructions
+    // are displayed in, e.g. "LD V3, 0x02". A line may start with a label
+    // such as "loop:", and ';' starts a comment. DB and DW emit bytes and
+    // big endian words. Numbers are decimal, or hexadecimal and binary with
+    // a 0x and 0b prefix.
+    pub fn assemble_source(source: &str) -> Result<Vec<u8>, ErrAssembly> {
+        // Split every line into its label, mnemonic and operands
+        let mut lines = Vec::new();
+        for (n, text) in source.lines().enumerate() {
+            let text = text.split(';').next().unwrap().trim();
+            let (label, rest) = match text.find(':') {
+                Some(i) => (Some(text[..i].trim()), text[i + 1..].trim()),
+                None => (None, text),
+            };
+            let (mnemonic, args) = match rest.find(char::is_whitespace) {
+                Some(i) => (&rest[..i], rest[i..].trim()),
+                None => (rest, ""),
+            };
+            let args: Vec<Arg> = args
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(parse_arg)
+                .collect();
+            lines.push((n + 1, label, mnemonic.to_uppercase(), args));
+        }
+
+        // The first pass places every label
+        let mut labels: HashMap<&str, u16> = HashMap::new();
+        let mut addr = PROGRAM_START as usize;
+        for (n, label, mnemonic, args) in &lines {
+            let err = |message: String| ErrAssembly { line: *n, message };
+            if let Some(label) = label {
+                if !is_label(label) {
+                    return Err(err(format!("invalid label '{}'", label)));
+                }
+                if labels.insert(label, addr as u16).is_some() {
+                    return Err(err(format!("duplicate label '{}'", label)));
+                }
+            }
+            addr += match mnemonic.as_str() {
+                "" => 0,
+                "DB" => args.len(),
+                "DW" => 2 * args.len(),
+                _ => 2,
+            };
+            if addr > MEM_SIZE {
+                return Err(err(String::from(
+                    "program does not fit in memory",
+                )));
+            }
+        }
+
+        // The second pass encodes every line
+        let mut out = Vec::new();
+        for (n, _, mnemonic, args) in &lines {
+            let err = |message: String| ErrAssembly { line: *n, message };
+            let value = |arg: &Arg, max: u16| -> Result<u16, ErrAssembly> {
+                let text = match arg {
+                    Arg::Value(text) => text,
+                    _ => return Err(err(String::from("expected a value"))),
+                };
+                let value = match labels.get(text.as_str()) {
+                    Some(addr) => *addr as u32,
+                    None => parse_number(text).ok_or_else(|| {
+                        err(format!("unknown label or number '{}'", text))
+                    })?,
+                };
+                if value > max as u32 {
+                    return Err(err(format!(
+                        "{} does not fit in {}",
+                        text, max
+                    )));
+                }
+                Ok(value as u16)
+            };
+            match mnemonic.as_str() {
+                "" => (),
+                "DB" => {
+                    for arg in args {
+                        out.push(value(arg, 0xFF)? as u8);
+                    }
+                }
+                "DW" => {
+                    for arg in args {
+                        out.extend(&value(arg, 0xFFFF)?.to_be_bytes());
+                    }
+                }
+                _ => {
+                    let opcode =
+                        encode(mnemonic, args, value)?.ok_or_else(|| {
+                            err(format!("invalid instruction '{}'", mnemonic))
+                        })?;
+                    out.extend(&opcode.to_be_bytes());
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+// encode encodes a single instruction, or returns None when no instruction
+// takes the given operands.
+fn encode(
+    mnemonic: &str,
+    args: &[Arg],
+    value: impl Fn(&Arg, u16) -> Result<u16, ErrAssembly>,
+) -> Result<Option<u16>, ErrAssembly> {
+    for meta in METADATA.iter().filter(|m| m.mnemonic == mnemonic) {
+        if meta.operands.len() != args.len()
+            || !meta.operands.iter().zip(args).all(|(o, a)| fits(*o, a))
+        {
+            continue;
+        }
+        let pattern: String = meta
+            .opcode
+            .chars()
+            .map(|c| if c.is_ascii_hexdigit() { c } else { '0' })
+            .collect();
+        let mut opcode = u16::from_str_radix(&pattern, 16).unwrap();
+        for (operand, arg) in meta.operands.iter().zip(args) {
+            opcode |= match (operand, arg) {
+                (Operand::Vx, Arg::V(x)) => (*x as u16) << 8,
+                (Operand::Vy, Arg::V(y)) => (*y as u16) << 4,
+                (Operand::Addr, _) => value(arg, 0xFFF)?,
+                (Operand::Byte, _) => value(arg, 0xFF)?,
+                (Operand::Nib, _) => value(arg, 0xF)?,
+                _ => 0,
+            };
+        }
+        return Ok(Some(opcode));
+    }
+    Ok(None)
+}
+
+// fits returns whether an operand can be given as arg.
+fn fits(operand: Operand, arg: &Arg) -> bool {
+    let fixed = |name: &str| *arg == Arg::Fixed(String::from(name));
+    match operand {
+        Operand::Addr | Operand::Byte | Operand::Nib => {
+            matches!(arg, Arg::Value(_))
+        }
+        Operand::Vx | Operand::Vy => matches!(arg, Arg::V(_)),
+        Operand::V0 => *arg == Arg::V(0),
+        Operand::I => fixed("I"),
+        Operand::IndirectI => fixed("[I]"),
+        Operand::Delay => fixed("DT"),
+        Operand::Sound => fixed("ST"),
+        Operand::Key => fixed("K"),
+        Operand::Font => fixed("F"),
+        Operand::Bcd => fixed("B"),
+    }
+}
+
+// parse_arg classifies a single operand.
+fn parse_arg(text: &str) -> Arg {
+    let upper = text.to_uppercase();
+    match upper.as_str() {
+        "I" | "[I]" | "DT" | "ST" | "K" | "F" | "B" => Arg::Fixed(upper),
+        _ if upper.len() == 2 && upper.starts_with('V') => {
+            match usize::from_str_radix(&upper[1..], 16) {
+                Ok(r) => Arg::V(r),
+                Err(_) => Arg::Value(String::from(text)),
+            }
+        }
+        _ => Arg::Value(String::from(text)),
+    }
+}
+
+// is_label returns whether a name can be used as a label.
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = chars.next();
+    first.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && parse_arg(name) == Arg::Value(String::from(name))
+}
+
+// parse_number parses a decimal, 0x-prefixed hexadecimal or 0b-prefixed
+// binary number.
+fn parse_number(text: &str) -> Option<u32> {
+    let lower = text.to_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
 }
@@ -0,0 +1,293 @@
+// The configuration file sets up the frontends and the machine. Every
+// setting is optional and the command line overrides the file, e.g.
+//
+//     scale = 12
+//     palette = "33ff66,000000"   # Lit pixels, then the background, or a name
+//     decay = 3                   # Frames for pixels to fade out, 0 for none
+//     keymap = "1234qwerasdfzxcv"
+//     speed = 10                  # Instructions per frame
+//     quirks = "vip"
+//     title = "CHIP-8"
+//     rom_dir = "/home/me/roms"   # Where ROMs given by name are looked up
+//
+//     [audio]
+//     enabled = true
+//     frequency = 440             # The pitch of the tone in Hz
+//     volume = 0.25
+//
+//     [roms."PONG.bin"]           # A ROM file name, or the SHA-1 of a ROM
+//     speed = 8
+use super::interpreter::quirks::Quirks;
+use super::keymap::Keymap;
+use super::romdb::Game;
+use super::screenshot::Palette;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+// ErrConfig is returned when a configuration file cannot be read or
+// parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrConfig(pub String);
+
+impl fmt::Display for ErrConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Settings are the settings that can be given at every level: in the
+// configuration file, for a single ROM and on the command line. A setting
+// is None when it is left to the next level.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    pub scale: Option<usize>, // The size of a pixel in the window
+    pub palette: Option<Palette>,
+    pub keymap: Option<Keymap>,
+    pub speed: Option<usize>, // Instructions per frame
+    pub quirks: Option<Quirks>,
+    pub title: Option<String>, // The title of the window
+    pub decay: Option<usize>,  // How many frames pixels fade out over
+}
+
+impl Settings {
+    // or returns the settings, with those that are not set taken from
+    // other.
+    pub fn or(self, other: Settings) -> Settings {
+        Settings {
+            scale: self.scale.or(other.scale),
+            palette: self.palette.or(other.palette),
+            keymap: self.keymap.or(other.keymap),
+            speed: self.speed.or(other.speed),
+            quirks: self.quirks.or(other.quirks),
+            title: self.title.or(other.title),
+            decay: self.decay.or(other.decay),
+        }
+    }
+
+    // parse sets the setting with the given name from its text, as given on
+    // the command line.
+    pub fn parse(&mut self, name: &str, text: &str) -> Result<(), ErrConfig> {
+        self.set(name, &Value::String(String::from(text)))
+    }
+
+    // set sets the setting with the given name. Numbers may be given as
+    // strings, as they are on the command line.
+    fn set(&mut self, name: &str, value: &Value) -> Result<(), ErrConfig> {
+        let err = |expected: &str| {
+            ErrConfig(format!("{}: expected {}, got {}", name, expected, value))
+        };
+        let number = |min: usize| {
+            let n = match value {
+                Value::Integer(n) => usize::try_from(*n).ok(),
+                Value::String(s) => s.parse::<usize>().ok(),
+                _ => None,
+            };
+            n.filter(|n| *n >= min).ok_or_else(|| match min {
+                0 => err("a number"),
+                _ => err("a positive number"),
+            })
+        };
+        let text = || match value {
+            Value::String(s) => Ok(s.as_str()),
+            _ => Err(err("a string")),
+        };
+        match name {
+            "scale" => self.scale = Some(number(1)?),
+            "speed" => self.speed = Some(number(1)?),
+            "decay" => self.decay = Some(number(0)?),
+            "palette" => {
+                self.palette = Some(
+                    Palette::parse(text()?)
+                        .ok_or_else(|| err("two RRGGBB colors"))?,
+                )
+            }
+            "keymap" => {
+                self.keymap = Some(
+                    Keymap::parse(text()?)
+                        .ok_or_else(|| err("16 distinct keys"))?,
+                )
+            }
+            "quirks" => {
+                self.quirks = Some(
+                    Quirks::parse(text()?)
+                        .map_err(|e| ErrConfig(e.to_string()))?,
+                )
+            }
+            "title" => self.title = Some(String::from(text()?)),
+            _ => return Err(ErrConfig(format!("unknown setting '{}'", name))),
+        }
+        Ok(())
+    }
+}
+
+// A game from the ROM database gives the settings it plays best with.
+impl From<&Game> for Settings {
+    fn from(game: &Game) -> Self {
+        Settings {
+            speed: Some(game.speed),
+            quirks: Some(game.quirks),
+            palette: game.palette,
+            ..Settings::default()
+        }
+    }
+}
+
+// Audio is how the sound timer is played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Audio {
+    pub enabled: bool,
+    pub frequency: f64, // The pitch of the tone in Hz
+    pub volume: f64,    // From 0 to 1
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            frequency: 440.0,
+            volume: 0.25,
+        }
+    }
+}
+
+// Config is a parsed configuration file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub settings: Settings,
+    pub audio: Audio,
+    pub rom_dir: Option<PathBuf>,
+    pub roms: Vec<(String, Settings)>, // ROM file name or SHA-1 -> settings
+}
+
+impl Config {
+    // path returns where the configuration file is looked for when none is
+    // given: $XDG_CONFIG_HOME/chip8/config.toml, or under ~/.config.
+    pub fn path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("chip8").join("config.toml"))
+    }
+
+    // load reads a configuration file. Without a file name it reads the
+    // default one, if it exists.
+    pub fn load(filename: Option<&Path>) -> Result<Self, ErrConfig> {
+        let (path, required) = match filename {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Config::default())
+            }
+            Err(e) => {
+                return Err(ErrConfig(format!("{}: {}", path.display(), e)))
+            }
+        };
+        Self::parse(&text)
+            .map_err(|e| ErrConfig(format!("{}: {}", path.display(), e)))
+    }
+
+    // parse parses the text of a configuration file.
+    pub fn parse(text: &str) -> Result<Self, ErrConfig> {
+        let table: Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| ErrConfig(e.to_string()))?;
+        let mut config = Config::default();
+        for (key, value) in &table {
+            match (key.as_str(), value) {
+                ("rom_dir", Value::String(dir)) => {
+                    config.rom_dir = Some(PathBuf::from(dir))
+                }
+                ("audio", Value::Table(audio)) => {
+                    config.audio = parse_audio(audio)?
+                }
+                ("roms", Value::Table(roms)) => {
+                    for (rom, value) in roms {
+                        let section = match value {
+                            Value::Table(section) => section,
+                            _ => {
+                                return Err(ErrConfig(format!(
+                                    "roms.{}: expected a table",
+                                    rom
+                                )))
+                            }
+                        };
+                        let mut settings = Settings::default();
+                        for (name, value) in section {
+                            settings.set(name, value).map_err(|e| {
+                                ErrConfig(format!("roms.{}.{}", rom, e))
+                            })?;
+                        }
+                        config.roms.push((rom.clone(), settings));
+                    }
+                }
+                ("rom_dir", _) | ("audio", _) | ("roms", _) => {
+                    return Err(ErrConfig(format!("{}: invalid value", key)))
+                }
+                _ => config.settings.set(key, value)?,
+            }
+        }
+        Ok(config)
+    }
+
+    // find returns the file of a ROM, looking in the ROM directory for a
+    // ROM that is not found as given.
+    pub fn find(&self, rom: &str) -> PathBuf {
+        let path = PathBuf::from(rom);
+        match &self.rom_dir {
+            Some(dir) if !path.exists() && dir.join(&path).exists() => {
+                dir.join(path)
+            }
+            _ => path,
+        }
+    }
+
+    // rom returns the settings of the sections for a ROM, matched by its
+    // file name or its SHA-1 in hex.
+    pub fn rom(&self, path: &Path, sha1: &str) -> Settings {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        self.roms
+            .iter()
+            .filter(|(key, _)| key == name || key.eq_ignore_ascii_case(sha1))
+            .fold(Settings::default(), |settings, (_, section)| {
+                settings.or(section.clone())
+            })
+    }
+}
+
+// parse_audio parses the audio section.
+fn parse_audio(table: &Table) -> Result<Audio, ErrConfig> {
+    let mut audio = Audio::default();
+    for (key, value) in table {
+        let number = match value {
+            Value::Integer(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        };
+        match (key.as_str(), value, number) {
+            ("enabled", Value::Boolean(on), _) => audio.enabled = *on,
+            ("frequency", _, Some(hz)) if hz > 0.0 => audio.frequency = hz,
+            ("volume", _, Some(v)) if (0.0..=1.0).contains(&v) => {
+                audio.volume = v
+            }
+            _ => {
+                return Err(ErrConfig(format!(
+                    "audio.{}: invalid value {}",
+                    key, value
+                )))
+            }
+        }
+    }
+    Ok(audio)
+}
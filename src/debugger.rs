@@ -0,0 +1,251 @@
+use super::interpreter::chip8::{Chip8, Fault, MEM_SIZE};
+use super::interpreter::instruction::Instruction;
+use super::interpreter::metadata::Flow;
+use super::screenshot::Image;
+use super::tui::{self, Glyphs};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+// CONTINUE_FRAMES is how many frames continue runs for when no breakpoint
+// is hit, so a program that never stops does not hang the debugger.
+const CONTINUE_FRAMES: usize = 600;
+
+// HELP lists the commands of the debugger.
+const HELP: &str = "\
+s, step [N]          run N instructions (1)
+n, next              run an instruction, stepping over calls
+c, continue [F]      run until a breakpoint, for at most F frames (600)
+b, break [ADDR]      set a breakpoint, or list them
+d, delete ADDR       delete a breakpoint
+r, regs              show the registers
+m, mem ADDR [LEN]    show memory (64 bytes)
+l, list [ADDR] [N]   disassemble N instructions (8), from the PC by default
+screen               show the display
+key K on|off         press or release the hex key K
+q, quit              leave the debugger
+An empty line repeats the last command.";
+
+// Debugger runs a machine one command at a time. Timers tick after every
+// speed instructions, as if the machine ran frame by frame.
+pub struct Debugger {
+    pub breakpoints: BTreeSet<u16>,
+    pub speed: usize, // Instructions per frame
+    cycles: usize,    // Instructions run in the current frame
+}
+
+impl Debugger {
+    pub fn new(speed: usize) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            speed: speed.max(1),
+            cycles: 0,
+        }
+    }
+
+    // command runs a single command and returns its output, or None when
+    // the debugger should quit.
+    pub fn command(&mut self, c8: &mut Chip8, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let num = |i: usize, default: usize| -> Result<usize, String> {
+            match words.get(i) {
+                Some(word) => parse_number(word)
+                    .ok_or_else(|| format!("invalid number '{}'", word)),
+                None => Ok(default),
+            }
+        };
+        let addr = |i: usize, default: usize| -> Result<usize, String> {
+            num(i, default).and_then(|addr| match addr < MEM_SIZE {
+                true => Ok(addr),
+                false => Err(format!("0x{:x} is outside memory", addr)),
+            })
+        };
+        let result = match words.as_slice() {
+            [] => Ok(String::new()),
+            ["q"] | ["quit"] => return None,
+            ["h"] | ["help"] => Ok(String::from(HELP)),
+            ["s", ..] | ["step", ..] => {
+                num(1, 1).map(|n| self.run(c8, n, false))
+            }
+            ["n"] | ["next"] => Ok(self.next(c8)),
+            ["c", ..] | ["continue", ..] => {
+                num(1, CONTINUE_FRAMES).and_then(|frames| {
+                    match frames.checked_mul(self.speed) {
+                        Some(n) => Ok(self.run(c8, n, true)),
+                        None => Err(format!("too many frames '{}'", frames)),
+                    }
+                })
+            }
+            ["b"] | ["break"] => Ok(self
+                .breakpoints
+                .iter()
+                .map(|b| format!("0x{:03x}", b))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            ["b", _] | ["break", _] => addr(1, 0).map(|addr| {
+                self.breakpoints.insert(addr as u16);
+                format!("breakpoint at 0x{:03x}", addr)
+            }),
+            ["d", _] | ["delete", _] => addr(1, 0).map(|addr| {
+                if self.breakpoints.remove(&(addr as u16)) {
+                    format!("deleted 0x{:03x}", addr)
+                } else {
+                    format!("no breakpoint at 0x{:03x}", addr)
+                }
+            }),
+            ["r"] | ["regs"] => Ok(registers(c8)),
+            ["m", _, ..] | ["mem", _, ..] => addr(1, 0).and_then(|addr| {
+                num(2, 64).map(|len| hexdump(c8.bus().bytes(), addr, len))
+            }),
+            ["l", ..] | ["list", ..] => addr(1, c8.pc() as usize % MEM_SIZE)
+                .and_then(|addr| num(2, 8).map(|n| list(c8, addr, n))),
+            ["screen"] => Ok(tui::render(Image::of(c8), Glyphs::HalfBlock)
+                .iter()
+                .map(|line| format!("|{}|", line))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            ["key", _, state @ "on"] | ["key", _, state @ "off"] => num(1, 0)
+                .map(|k| {
+                    c8.set_key(k as u8, *state == "on");
+                    format!("key {:X} {}", k & 0xF, state)
+                }),
+            _ => Err(format!("unknown command '{}', try help", line.trim())),
+        };
+        Some(result.unwrap_or_else(|e| e))
+    }
+
+    // run runs up to n instructions, stopping early on a fault or, when
+    // breaks is set, on a breakpoint after the first instruction.
+    fn run(&mut self, c8: &mut Chip8, n: usize, breaks: bool) -> String {
+        for i in 0..n {
+            if breaks && i > 0 && self.breakpoints.contains(&c8.pc()) {
+                return format!(
+                    "breakpoint\n{}",
+                    list(c8, c8.pc() as usize, 1)
+                );
+            }
+            if let Err(fault) = self.step(c8) {
+                return format!("fault: {}", fault);
+            }
+        }
+        list(c8, c8.pc() as usize, 1)
+    }
+
+    // next runs a single instruction, or a whole subroutine for a call.
+    fn next(&mut self, c8: &mut Chip8) -> String {
+        let pc = c8.pc();
+        let depth = c8.stack().len();
+        let is_call = fetch(c8, pc as usize)
+            .is_some_and(|instr| instr.metadata().flow == Flow::Call);
+        if !is_call {
+            return self.run(c8, 1, false);
+        }
+        for i in 0..CONTINUE_FRAMES.saturating_mul(self.speed) {
+            if i > 0 && self.breakpoints.contains(&c8.pc()) {
+                return format!(
+                    "breakpoint\n{}",
+                    list(c8, c8.pc() as usize, 1)
+                );
+            }
+            if let Err(fault) = self.step(c8) {
+                return format!("fault: {}", fault);
+            }
+            if c8.pc() == pc.wrapping_add(2) && c8.stack().len() == depth {
+                break;
+            }
+        }
+        list(c8, c8.pc() as usize, 1)
+    }
+
+    // step runs a single instruction and ticks the timers at the end of a
+    // frame.
+    fn step(&mut self, c8: &mut Chip8) -> Result<(), Fault> {
+        c8.cycle()?;
+        self.cycles += 1;
+        if self.cycles == self.speed {
+            self.cycles = 0;
+            c8.tick();
+        }
+        Ok(())
+    }
+}
+
+// opcode returns the opcode at an address, wrapping around memory.
+fn opcode(c8: &Chip8, addr: usize) -> u16 {
+    let addr = addr % MEM_SIZE;
+    (c8.peek(addr as u16) as u16) << 8 | c8.peek((addr + 1) as u16) as u16
+}
+
+// fetch decodes the instruction at an address.
+fn fetch(c8: &Chip8, addr: usize) -> Option<Instruction> {
+    Instruction::try_from(opcode(c8, addr)).ok()
+}
+
+// list disassembles n instructions of memory starting at addr, wrapping
+// around memory. It lists all of memory at most.
+fn list(c8: &Chip8, addr: usize, n: usize) -> String {
+    let mut out = String::new();
+    for i in 0..n.min(MEM_SIZE / 2) {
+        let addr = (addr % MEM_SIZE + 2 * i) % MEM_SIZE;
+        let opcode = opcode(c8, addr);
+        let text = match fetch(c8, addr) {
+            Some(instr) => instr.to_string(),
+            None => String::from("???"),
+        };
+        let mark = if addr == c8.pc() as usize { '>' } else { ' ' };
+        writeln!(out, "{} 0x{:03x}  {:04x}  {}", mark, addr, opcode, text)
+            .unwrap();
+    }
+    out.pop();
+    out
+}
+
+// registers returns the registers, timers and stack.
+fn registers(c8: &Chip8) -> String {
+    let mut out = String::new();
+    for r in 0..16 {
+        write!(out, "V{:X}={:02x}", r, c8.register(r)).unwrap();
+        out.push(if r % 8 == 7 { '\n' } else { ' ' });
+    }
+    writeln!(
+        out,
+        "I={:03x} PC={:03x} DT={} ST={}",
+        c8.index(),
+        c8.pc(),
+        c8.delay_timer(),
+        c8.sound_timer()
+    )
+    .unwrap();
+    let stack: Vec<String> =
+        c8.stack().iter().map(|a| format!("{:03x}", a)).collect();
+    write!(out, "stack=[{}]", stack.join(" ")).unwrap();
+    out
+}
+
+// hexdump returns len bytes of memory starting at addr, 16 per line with
+// their ASCII text.
+pub fn hexdump(memory: &[u8], addr: usize, len: usize) -> String {
+    let end = addr.saturating_add(len).min(memory.len());
+    let mut out = String::new();
+    for start in (addr..end).step_by(16) {
+        let row = &memory[start..(start + 16).min(end)];
+        let bytes: Vec<String> =
+            row.iter().map(|b| format!("{:02x}", b)).collect();
+        let text: String = row
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+            .collect();
+        writeln!(out, "0x{:03x}  {:<47}  {}", start, bytes.join(" "), text)
+            .unwrap();
+    }
+    out.pop();
+    out
+}
+
+// parse_number parses a decimal or 0x-prefixed hexadecimal number.
+fn parse_number(word: &str) -> Option<usize> {
+    match word.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => word.parse().ok(),
+    }
+}
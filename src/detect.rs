@@ -0,0 +1,177 @@
+use super::analysis::Analysis;
+use super::interpreter::chip8::{MEM_SIZE, PROGRAM_START};
+use super::interpreter::quirks::Quirks;
+use super::romdb::Platform;
+use std::fmt;
+
+// Confidence is how sure a detection is of the platform it chose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,    // Data the code may reach through BNNN looks like extensions
+    Medium, // The code may use an extension, or was not fully followed
+    High,   // Reachable code uses an extension, the ROM is too large, or
+            // all of the code was followed and uses none
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Confidence::Low => write!(f, "low"),
+            Confidence::Medium => write!(f, "medium"),
+            Confidence::High => write!(f, "high"),
+        }
+    }
+}
+
+// Evidence is a single reason for choosing a platform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evidence {
+    pub platform: Platform,
+    pub confidence: Confidence,
+    pub reason: String,
+}
+
+// Detection is the platform a ROM most likely targets, with the evidence
+// for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub confidence: Confidence,
+    pub evidence: Vec<Evidence>,
+}
+
+// detect guesses the platform of a ROM from its size and from opcodes that
+// only exist on SUPER-CHIP or XO-CHIP. An opcode counts for more when the
+// code reaches it than when it only appears among the data.
+pub fn detect(rom: &[u8]) -> Detection {
+    let mut evidence = Vec::new();
+    if rom.len() > MEM_SIZE - PROGRAM_START as usize {
+        evidence.push(Evidence {
+            platform: Platform::XoChip,
+            confidence: Confidence::High,
+            reason: format!(
+                "{} bytes only fit in the 64K memory of XO-CHIP",
+                rom.len()
+            ),
+        });
+    }
+
+    // Opcodes are read where the code reaches, at whatever alignment, and
+    // two by two through the bytes it never reaches. Reachable code stops
+    // at an opcode it cannot decode, which counts as reached.
+    let analysis = Analysis::new(rom);
+    let mut opcodes: Vec<(u16, bool)> = analysis
+        .blocks
+        .values()
+        .flat_map(|block| block.instructions.iter())
+        .map(|&(addr, _)| (addr, true))
+        .collect();
+    for (start, end) in analysis.unreachable() {
+        let mut addr = start;
+        while addr < end {
+            if analysis.invalid.contains(&(addr + 1)) {
+                addr += 1; // Realign on the opcode the code reached
+                continue;
+            }
+            opcodes.push((addr, analysis.invalid.contains(&addr)));
+            addr += 2;
+        }
+    }
+    opcodes.sort_unstable();
+    for (addr, reached) in opcodes {
+        if addr < analysis.start || addr + 1 >= analysis.end {
+            continue;
+        }
+        let i = (addr - PROGRAM_START) as usize;
+        let opcode = (rom[i] as u16) << 8 | rom[i + 1] as u16;
+        let (platform, name) = match extension(opcode) {
+            Some(found) => found,
+            None => continue,
+        };
+        // DXY0 is a valid CHIP-8 instruction that draws nothing
+        let confidence = match (reached, opcode & 0xF00F == 0xD000) {
+            (true, false) => Confidence::High,
+            (true, true) => Confidence::Medium,
+            (false, _) => Confidence::Low,
+        };
+        let place = if reached { "code" } else { "data" };
+        evidence.push(Evidence {
+            platform,
+            confidence,
+            reason: format!(
+                "{:04x} {} in {} at 0x{:03x}",
+                opcode, name, place, addr
+            ),
+        });
+    }
+
+    // The most confident evidence wins, XO-CHIP over SUPER-CHIP on a tie
+    // since XO-CHIP is a superset of it. Data alone is no evidence unless
+    // the code jumps to addresses that were not followed.
+    let best = evidence
+        .iter()
+        .filter(|e| e.confidence > Confidence::Low)
+        .max_by_key(|e| (e.confidence, e.platform == Platform::XoChip));
+    let (platform, confidence) = match best {
+        Some(e) => (e.platform, e.confidence),
+        None if analysis.indirect.is_empty() => {
+            (Platform::Chip8, Confidence::High)
+        }
+        None if evidence.is_empty() => (Platform::Chip8, Confidence::Medium),
+        None => (Platform::Chip8, Confidence::Low),
+    };
+    Detection {
+        platform,
+        quirks: platform.quirks(),
+        confidence,
+        evidence,
+    }
+}
+
+// extension returns the platform and name of an opcode that CHIP-8 does not
+// have.
+fn extension(opcode: u16) -> Option<(Platform, &'static str)> {
+    let found = match opcode {
+        0x00FB => (Platform::Schip, "scroll right"),
+        0x00FC => (Platform::Schip, "scroll left"),
+        0x00FD => (Platform::Schip, "exit"),
+        0x00FE => (Platform::Schip, "low resolution"),
+        0x00FF => (Platform::Schip, "high resolution"),
+        0xF000 => (Platform::XoChip, "long load of I"),
+        0xF002 => (Platform::XoChip, "audio pattern"),
+        _ => match (opcode >> 12, opcode & 0xF, opcode & 0xFF) {
+            (0x0, _, 0xC1..=0xCF) => (Platform::Schip, "scroll down"),
+            (0x0, _, 0xD1..=0xDF) => (Platform::XoChip, "scroll up"),
+            (0x5, 0x2, _) => (Platform::XoChip, "save range"),
+            (0x5, 0x3, _) => (Platform::XoChip, "load range"),
+            (0xD, 0x0, _) => (Platform::Schip, "16x16 sprite"),
+            (0xF, _, 0x01) => (Platform::XoChip, "select plane"),
+            (0xF, _, 0x30) => (Platform::Schip, "large font"),
+            (0xF, _, 0x3A) => (Platform::XoChip, "pitch"),
+            (0xF, _, 0x75) => (Platform::Schip, "save flags"),
+            (0xF, _, 0x85) => (Platform::Schip, "load flags"),
+            _ => return None,
+        },
+    };
+    Some(found)
+}
+
+// A detection is displayed as a report of the platform, the confidence and
+// the evidence.
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} confidence), quirks {}",
+            self.platform, self.confidence, self.quirks
+        )?;
+        if self.evidence.is_empty() {
+            write!(f, "\n  no extension opcodes")?;
+        }
+        for e in &self.evidence {
+            write!(f, "\n  {}: {} ({})", e.platform, e.reason, e.confidence)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use super::analysis::{Analysis, Edge};
+use super::interpreter::instruction::Instruction;
+use super::interpreter::metadata::Operand;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+// DB_WIDTH is the number of data bytes per DB line.
+const DB_WIDTH: usize = 8;
+
+// Item is a single line of a listing: an instruction, or a run of data bytes.
+enum Item {
+    Code(Instruction),
+    Data(usize), // The number of bytes
+}
+
+// disassemble returns a listing of a ROM that assemble_source turns back
+// into the same bytes. Reachable instructions are disassembled and the rest
+// is written as data, with labels on the targets of jumps and calls.
+pub fn disassemble(rom: &[u8]) -> String {
+    let analysis = Analysis::new(rom);
+    let byte = |addr: u16| rom[(addr - analysis.start) as usize];
+
+    // Walk the ROM once, so labels only go where a line starts
+    let mut items: BTreeMap<u16, Item> = BTreeMap::new();
+    let mut addr = analysis.start;
+    while addr < analysis.end {
+        let instr = if addr + 1 < analysis.end && analysis.is_code(addr) {
+            let opcode = (byte(addr) as u16) << 8 | byte(addr + 1) as u16;
+            Instruction::try_from(opcode).ok()
+        } else {
+            None
+        };
+        match instr {
+            Some(instr) => {
+                items.insert(addr, Item::Code(instr));
+                addr += 2;
+            }
+            None => {
+                let start = addr;
+                addr += 1;
+                while addr < analysis.end
+                    && !analysis.is_code(addr)
+                    && ((addr - start) as usize) < DB_WIDTH
+                {
+                    addr += 1;
+                }
+                items.insert(start, Item::Data((addr - start) as usize));
+            }
+        }
+    }
+
+    let mut labels: BTreeMap<u16, String> = BTreeMap::new();
+    for block in analysis.blocks.values() {
+        for (target, edge) in &block.successors {
+            if *edge == Edge::Jump && items.contains_key(target) {
+                labels.insert(*target, format!("L{:03x}", target));
+            }
+        }
+    }
+    for entry in analysis.subroutines.keys() {
+        if items.contains_key(entry) {
+            labels.insert(*entry, format!("sub_{:03x}", entry));
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "; {} bytes", rom.len()).unwrap();
+    for (addr, item) in &items {
+        if let Some(label) = labels.get(addr) {
+            writeln!(out, "{}:", label).unwrap();
+        }
+        let (text, bytes) = match item {
+            Item::Code(instr) => {
+                let opcode = instr.encode();
+                (text(instr, &labels), format!("{:04x}", opcode))
+            }
+            Item::Data(len) => {
+                let data: Vec<u8> =
+                    (0..*len).map(|i| byte(addr + i as u16)).collect();
+                let values: Vec<String> =
+                    data.iter().map(|b| format!("0x{:02x}", b)).collect();
+                (format!("DB {}", values.join(", ")), hex::encode(data))
+            }
+        };
+        writeln!(out, "    {:<40} ; 0x{:03x}  {}", text, addr, bytes).unwrap();
+    }
+    out
+}
+
+// text returns an instruction as assembly, naming its address operand by
+// its label when it has one.
+fn text(instr: &Instruction, labels: &BTreeMap<u16, String>) -> String {
+    let text = instr.to_string();
+    if !instr.metadata().operands.contains(&Operand::Addr) {
+        return text;
+    }
+    match labels.get(&instr.addr()) {
+        Some(label) => text.replace(&format!("0x{:03x}", instr.addr()), label),
+        None => text,
+    }
+}
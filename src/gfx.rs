@@ -1,53 +1,169 @@
-use super::interpreter::chip8::{HEIGHT, OFF, ON, WIDTH};
+use super::interpreter::chip8::{
+    Chip8, Fault, HEIGHT, INSTRUCTIONS_PER_FRAME, WIDTH,
+};
+use super::interpreter::framebuffer::LoRes;
+use super::keymap::Keymap;
+use super::phosphor::Phosphor;
+use super::recorder::{Recorder, DEFAULT_SCALE};
+use super::screenshot::{self, Image, Palette};
 use piston_window::*;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const SCALE: f64 = 20.0;
+// SCALE is the default size of a pixel in the window.
+pub const SCALE: f64 = 20.0;
+
+// TITLE is the default title of the window.
+pub const TITLE: &str = "CHIP-8 Interpreter by @xoreo";
+
+// SCREENSHOT_KEY saves a screenshot of the display.
+const SCREENSHOT_KEY: Key = Key::F12;
+
+// RECORD_KEY starts and stops recording the display to a GIF.
+const RECORD_KEY: Key = Key::F11;
 
 pub struct Display {
-    pub pixels: [u8; WIDTH * HEIGHT], // The pixels themselves
     pub screen: PistonWindow,
+    pub palette: Palette,
+    pub keymap: Keymap,
+    pub speed: usize,               // Instructions per frame
+    pub recorder: Option<Recorder>, // Records every frame, when set
+    pub phosphor: Phosphor,         // Fades out pixels that turn off
+    scale: f64,                     // The size of a pixel in the window
 }
 
 impl Display {
     pub fn new() -> Self {
+        Self::with_scale(SCALE)
+    }
+
+    // with_scale opens a window with pixels of the given size.
+    pub fn with_scale(scale: f64) -> Self {
+        Self::open(TITLE, scale)
+    }
+
+    // open opens a window with the given title and pixels of the given size.
+    pub fn open(title: &str, scale: f64) -> Self {
         Self {
-            pixels: [OFF; WIDTH * HEIGHT],
             screen: WindowSettings::new(
-                "CHIP-8 Interpreter by @xoreo",
-                [WIDTH as f64 * SCALE, HEIGHT as f64 * SCALE],
+                title,
+                [WIDTH as f64 * scale, HEIGHT as f64 * scale],
             )
             .exit_on_esc(true)
             .build()
             .unwrap(),
+            palette: Palette::DEFAULT,
+            keymap: Keymap::DEFAULT,
+            speed: INSTRUCTIONS_PER_FRAME,
+            scale,
+            recorder: None,
+            phosphor: Phosphor::default(),
+        }
+    }
+
+    // run runs a machine in the window, one frame per render event, until the
+    // window is closed or the machine faults, returning the fault.
+    pub fn run(&mut self, c8: &mut Chip8) -> Option<Fault> {
+        let mut fault = None;
+        while let Some(event) = self.screen.next() {
+            if let Some(Button::Keyboard(key)) = event.press_args() {
+                if let Some(k) = self.keypad(key) {
+                    c8.set_key(k, true);
+                }
+                if key == SCREENSHOT_KEY {
+                    self.screenshot(c8);
+                }
+                if key == RECORD_KEY {
+                    self.toggle_recording();
+                }
+            }
+            if let Some(Button::Keyboard(key)) = event.release_args() {
+                if let Some(k) = self.keypad(key) {
+                    c8.set_key(k, false);
+                }
+            }
+            if event.render_args().is_some() {
+                if let Err(e) = c8.run_frame(self.speed) {
+                    fault = Some(e);
+                    break;
+                }
+                self.record(c8);
+                self.draw(&event, c8.display());
+            }
+        }
+        if self.recorder.is_some() {
+            self.toggle_recording();
+        }
+        fault
+    }
+
+    // keypad returns the hex key a keyboard key is mapped to, if any. Piston
+    // numbers the keys of letters and digits by their ASCII code.
+    fn keypad(&self, key: Key) -> Option<u8> {
+        std::char::from_u32(key as u32).and_then(|c| self.keymap.key(c))
+    }
+
+    // screenshot saves the display to a PNG file named after the current time.
+    fn screenshot(&self, c8: &Chip8) {
+        let filename = format!("screenshot-{}.png", timestamp());
+        let image = Image::of(c8);
+        let scale = self.scale as usize;
+        match screenshot::save(&filename, image, &self.palette, scale) {
+            Ok(()) => println!("saved {}", filename),
+            Err(e) => println!("could not save {}: {}", filename, e),
+        }
+    }
+
+    // toggle_recording starts recording to a GIF file named after the current
+    // time, or finishes the recording in progress.
+    fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some(recorder) => match recorder.finish() {
+                Ok(()) => println!("recording saved"),
+                Err(e) => println!("could not save recording: {}", e),
+            },
+            None => {
+                let filename = format!("recording-{}.gif", timestamp());
+                match Recorder::create(&filename, self.palette, DEFAULT_SCALE) {
+                    Ok(recorder) => {
+                        println!("recording to {}", filename);
+                        self.recorder = Some(recorder);
+                    }
+                    Err(e) => println!("could not record {}: {}", filename, e),
+                }
+            }
         }
     }
 
-    // draw draws the array of pixels.
-    pub fn draw(&mut self, event: &Event) {
-        let pixels = self.pixels; // Copy the pixels
+    // record adds the display to the recording in progress, if any. Recording
+    // stops if the file cannot be written.
+    fn record(&mut self, c8: &Chip8) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.frame(Image::of(c8)) {
+                println!("recording stopped: {}", e);
+                self.recorder = None;
+            }
+        }
+    }
+
+    // draw draws the display in the palette, fading out those that
+    // just turned off.
+    pub fn draw(&mut self, event: &Event, display: &LoRes) {
+        let scale = self.scale;
+        let palette = self.palette;
+        self.phosphor.update(display.pixels());
+        let levels = self.phosphor.levels();
         self.screen.draw_2d(event, |context, graphics, _device| {
-            clear([1.0; 4], graphics);
-            // Draw each pixel
+            clear(rgba(palette.off), graphics);
+            // Draw each pixel that is lit, or still fading out
             for i in 0..HEIGHT {
                 for j in 0..WIDTH {
-                    // Determine the color of the pixel
-                    let mut color: [f32; 4] = [0.0; 4];
-                    match pixels[WIDTH * i + j] {
-                        ON => color = [0.0, 0.0, 0.0, 1.0], // White
-                        OFF => color = [1.0; 4],            // Black
-                        _ => color = [0.0, 1.0, 0.0, 1.0],  // Green (for error)
+                    let level = levels[WIDTH * i + j];
+                    if level == 0.0 {
+                        continue;
                     }
-                    // Draw the pixel
                     rectangle(
-                        color,
-                        [
-                            i as f64 * SCALE, // x pos
-                            j as f64 * SCALE, // y pos
-                            SCALE,            // Pixel width
-                            SCALE,            // Pixel height
-                                              // 1.0 * SCALE + (i as f64 * SCALE), // Pixel width
-                                              // 1.0 * SCALE + (j as f64 * SCALE), // Pixel height
-                        ],
+                        rgba(palette.blend(level)),
+                        square(j, i, scale),
                         context.transform,
                         graphics,
                     );
@@ -56,3 +172,26 @@ impl Display {
         });
     }
 }
+
+// square returns where the pixel in column x of row y is drawn in a window
+// with pixels of the given size, as x, y, width and height.
+pub fn square(x: usize, y: usize, scale: f64) -> [f64; 4] {
+    [x as f64 * scale, y as f64 * scale, scale, scale]
+}
+
+// timestamp returns the current time in seconds, for naming files.
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// rgba converts an RGB color to the color type of piston.
+fn rgba(c: [u8; 3]) -> [f32; 4] {
+    [
+        c[0] as f32 / 255.0,
+        c[1] as f32 / 255.0,
+        c[2] as f32 / 255.0,
+        1.0,
+    ]
+}
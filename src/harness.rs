@@ -0,0 +1,321 @@
+// The harness runs ROMs headlessly and checks the machine against the
+// expectations in a manifest file. A manifest is a list of commands, one per
+// line, with '#' starting a comment:
+//
+//     rom ../../roms/PONG.bin     # Relative to the manifest
+//     frames 120                  # How many 60 Hz frames to run
+//     speed 10                    # Instructions per frame
+//     seed 1                      # Seed for CXNN
+//     press 10 1                  # Press key 1 before frame 10
+//     release 20 1                # Release key 1 before frame 20
+//     expect framebuffer 0123456789abcdef
+//     expect V6 0x03
+//     expect I 0x2f0
+//     expect memory 0x2ea 0x80 0x80
+use super::interpreter::chip8::{Chip8, Fault, INSTRUCTIONS_PER_FRAME};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ErrManifest is returned when a manifest cannot be read or parsed.
+#[derive(Debug)]
+pub struct ErrManifest {
+    pub line: usize, // 0 when the error is not about a single line
+    pub message: String,
+}
+
+impl fmt::Display for ErrManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.message),
+            line => write!(f, "line {}: {}", line, self.message),
+        }
+    }
+}
+
+// Input is a key press or release, applied before the given frame runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Input {
+    pub frame: usize,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// Expect is an assertion on the machine after the last frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expect {
+    Framebuffer(u64),     // The hash of the display
+    Register(usize, u8),  // The value of a V register
+    Index(u16),           // The value of I
+    Memory(u16, Vec<u8>), // The bytes starting at an address
+}
+
+// Manifest describes a single headless test.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub rom: PathBuf,
+    pub frames: usize,
+    pub speed: usize,
+    pub seed: u64,
+    pub inputs: Vec<Input>,
+    pub expects: Vec<Expect>,
+}
+
+impl Manifest {
+    // load reads and parses a manifest file.
+    pub fn load(filename: &str) -> Result<Self, ErrManifest> {
+        let text = fs::read_to_string(filename).map_err(|e| ErrManifest {
+            line: 0,
+            message: format!("{}: {}", filename, e),
+        })?;
+        let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+        Self::parse(&text, dir)
+    }
+
+    // parse parses a manifest. The ROM path is relative to dir.
+    pub fn parse(text: &str, dir: &Path) -> Result<Self, ErrManifest> {
+        let mut rom = None;
+        let mut manifest = Self {
+            rom: PathBuf::new(),
+            frames: 0,
+            speed: INSTRUCTIONS_PER_FRAME,
+            seed: 0,
+            inputs: Vec::new(),
+            expects: Vec::new(),
+        };
+
+        for (n, line) in text.lines().enumerate() {
+            let err = |message: &str| ErrManifest {
+                line: n + 1,
+                message: String::from(message),
+            };
+            let line = line.split('#').next().unwrap();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let num = |i: usize| -> Result<u64, ErrManifest> {
+                let word = words.get(i).ok_or_else(|| err("missing value"))?;
+                parse_number(word).ok_or_else(|| err("invalid number"))
+            };
+
+            match words.as_slice() {
+                [] => (),
+                ["rom", path] => rom = Some(dir.join(path)),
+                ["frames", _] => manifest.frames = num(1)? as usize,
+                ["speed", _] => manifest.speed = num(1)? as usize,
+                ["seed", _] => manifest.seed = num(1)?,
+                ["press", _, _] | ["release", _, _] => {
+                    manifest.inputs.push(Input {
+                        frame: num(1)? as usize,
+                        key: (num(2)? & 0xF) as u8,
+                        pressed: words[0] == "press",
+                    })
+                }
+                ["expect", "framebuffer", hash] => {
+                    let hash = u64::from_str_radix(hash, 16)
+                        .map_err(|_| err("invalid framebuffer hash"))?;
+                    manifest.expects.push(Expect::Framebuffer(hash));
+                }
+                ["expect", "I", _] => {
+                    manifest.expects.push(Expect::Index(num(2)? as u16))
+                }
+                ["expect", "memory", _, ..] => {
+                    let bytes = (3..words.len())
+                        .map(|i| num(i).map(|b| b as u8))
+                        .collect::<Result<Vec<u8>, ErrManifest>>()?;
+                    manifest
+                        .expects
+                        .push(Expect::Memory(num(2)? as u16, bytes));
+                }
+                ["expect", reg, _] if reg.starts_with('V') => {
+                    let r = usize::from_str_radix(&reg[1..], 16)
+                        .ok()
+                        .filter(|r| *r < 16)
+                        .ok_or_else(|| err("invalid register"))?;
+                    manifest.expects.push(Expect::Register(r, num(2)? as u8));
+                }
+                _ => return Err(err(&format!("unknown command '{}'", line))),
+            }
+        }
+
+        manifest.rom = rom.ok_or(ErrManifest {
+            line: 0,
+            message: String::from("no rom given"),
+        })?;
+        Ok(manifest)
+    }
+}
+
+// parse_number parses a decimal or 0x-prefixed hexadecimal number.
+fn parse_number(word: &str) -> Option<u64> {
+    match word.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => word.parse().ok(),
+    }
+}
+
+// Outcome is the result of running a manifest.
+#[derive(Debug)]
+pub struct Outcome {
+    pub frames: usize,         // The number of frames that ran
+    pub fault: Option<Fault>,  // The fault that stopped the machine
+    pub failures: Vec<String>, // Every expectation that did not hold
+    pub framebuffer: u64,      // The final hash of the display
+}
+
+impl Outcome {
+    // passed returns whether the machine ran without faulting and met every
+    // expectation.
+    pub fn passed(&self) -> bool {
+        self.fault.is_none() && self.failures.is_empty()
+    }
+}
+
+// run runs a manifest on a fresh machine.
+pub fn run(manifest: &Manifest) -> Result<Outcome, ErrManifest> {
+    run_with(manifest, &mut |_| ())
+}
+
+// run_with runs a manifest on a fresh machine, calling on_frame after every
+// frame.
+pub fn run_with(
+    manifest: &Manifest,
+    on_frame: &mut dyn FnMut(&Chip8),
+) -> Result<Outcome, ErrManifest> {
+    let mut c8 = load(manifest)?;
+    Ok(run_machine_with(&mut c8, manifest, on_frame))
+}
+
+// load returns a new machine with the ROM of a manifest loaded.
+fn load(manifest: &Manifest) -> Result<Chip8, ErrManifest> {
+    let mut c8 = Chip8::new();
+    let rom = manifest.rom.to_string_lossy();
+    c8.load_rom(&rom).map_err(|e| ErrManifest {
+        line: 0,
+        message: format!("{}: {}", rom, e),
+    })?;
+    Ok(c8)
+}
+
+// run_machine runs a manifest on a machine that already has a ROM loaded.
+pub fn run_machine(c8: &mut Chip8, manifest: &Manifest) -> Outcome {
+    run_machine_with(c8, manifest, &mut |_| ())
+}
+
+// run_machine_with runs a manifest on a machine that already has a ROM
+// loaded, calling on_frame after every frame.
+pub fn run_machine_with(
+    c8: &mut Chip8,
+    manifest: &Manifest,
+    on_frame: &mut dyn FnMut(&Chip8),
+) -> Outcome {
+    c8.seed(manifest.seed);
+    let mut outcome = Outcome {
+        frames: 0,
+        fault: None,
+        failures: Vec::new(),
+        framebuffer: 0,
+    };
+    for frame in 0..manifest.frames {
+        for input in manifest.inputs.iter().filter(|i| i.frame == frame) {
+            c8.set_key(input.key, input.pressed);
+        }
+        if let Err(fault) = c8.run_frame(manifest.speed) {
+            outcome.fault = Some(fault);
+            break;
+        }
+        outcome.frames += 1;
+        on_frame(c8);
+    }
+
+    outcome.framebuffer = c8.framebuffer_hash();
+    for expect in &manifest.expects {
+        let failure = match expect {
+            Expect::Framebuffer(hash) if *hash != outcome.framebuffer => {
+                Some(format!(
+                    "framebuffer is {:016x}, expected {:016x}",
+                    outcome.framebuffer, hash
+                ))
+            }
+            Expect::Register(r, value) if c8.register(*r) != *value => {
+                Some(format!(
+                    "V{:X} is 0x{:02x}, expected 0x{:02x}",
+                    r,
+                    c8.register(*r),
+                    value
+                ))
+            }
+            Expect::Index(value) if c8.index() != *value => Some(format!(
+                "I is 0x{:03x}, expected 0x{:03x}",
+                c8.index(),
+                value
+            )),
+            Expect::Memory(addr, bytes) => {
+                let start = *addr as usize;
+                let actual = c8.bus().bytes().get(start..start + bytes.len());
+                match actual {
+                    Some(actual) if actual == bytes.as_slice() => None,
+                    _ => Some(format!(
+                        "memory at 0x{:03x} is {:02x?}, expected {:02x?}",
+                        addr,
+                        actual.unwrap_or(&[]),
+                        bytes
+                    )),
+                }
+            }
+            _ => None,
+        };
+        outcome.failures.extend(failure);
+    }
+    outcome
+}
+
+// Divergence is where the block engine and the interpreter first disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub frame: usize, // The frame after which they disagree
+    pub differences: Vec<String>, // The block engine, then the interpreter
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "diverged in frame {}", self.frame)?;
+        for difference in &self.differences {
+            write!(f, "\n  {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+// differential runs a manifest with the block engine and with the
+// interpreter side by side, and compares the two machines after every frame.
+// It returns the first frame after which they differ, if any.
+pub fn differential(
+    manifest: &Manifest,
+) -> Result<Option<Divergence>, ErrManifest> {
+    let mut blocks = load(manifest)?;
+    let mut interpreter = load(manifest)?;
+    blocks.enable_blocks();
+    blocks.seed(manifest.seed);
+    interpreter.seed(manifest.seed);
+    for frame in 0..manifest.frames {
+        for input in manifest.inputs.iter().filter(|i| i.frame == frame) {
+            blocks.set_key(input.key, input.pressed);
+            interpreter.set_key(input.key, input.pressed);
+        }
+        let faults = (
+            blocks.run_frame(manifest.speed).err(),
+            interpreter.run_frame(manifest.speed).err(),
+        );
+        let mut differences = blocks.differences(&interpreter);
+        if faults.0 != faults.1 {
+            differences
+                .push(format!("fault: {:?} != {:?}", faults.0, faults.1));
+        }
+        if !differences.is_empty() {
+            return Ok(Some(Divergence { frame, differences }));
+        }
+        if faults.0.is_some() {
+            break;
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,152 @@
+use super::chip8::MEM_SIZE;
+use core::ops::Range;
+
+// Bus is what the machine reads and writes memory through. The program
+// reads and writes, so a bus can watch them, map devices into memory or
+// refuse them; the host peeks and pokes, to load ROMs and to look at memory
+// without the program noticing. Addresses are always below MEM_SIZE.
+pub trait Bus {
+    // peek returns the byte at an address, without side effects.
+    fn peek(&self, addr: u16) -> u8;
+
+    // poke sets the byte at an address, without side effects.
+    fn poke(&mut self, addr: u16, byte: u8);
+
+    // read returns the byte at an address for the program: an instruction
+    // being fetched, a sprite being drawn or a register being loaded.
+    fn read(&mut self, addr: u16) -> u8 {
+        self.peek(addr)
+    }
+
+    // write sets the byte at an address for the program and returns whether
+    // it was accepted. The machine faults on a refused write.
+    fn write(&mut self, addr: u16, byte: u8) -> bool {
+        self.poke(addr, byte);
+        true
+    }
+}
+
+// Memory is plain RAM, the default bus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memory {
+    bytes: [u8; MEM_SIZE],
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self {
+            bytes: [0; MEM_SIZE],
+        }
+    }
+
+    // bytes returns the whole memory.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for Memory {
+    #[inline]
+    fn peek(&self, addr: u16) -> u8 {
+        self.bytes[addr as usize]
+    }
+
+    #[inline]
+    fn poke(&mut self, addr: u16, byte: u8) {
+        self.bytes[addr as usize] = byte;
+    }
+}
+
+// Counted counts the reads and writes of the program at every address of
+// another bus. The machine caches decoded instructions by default, so an
+// instruction is fetched through the bus only once until its memory is
+// written; call Chip8::disable_cache first to count every fetch.
+#[derive(Debug, Clone)]
+pub struct Counted<B: Bus> {
+    pub bus: B,
+    pub reads: [u64; MEM_SIZE],
+    pub writes: [u64; MEM_SIZE],
+}
+
+impl<B: Bus> Counted<B> {
+    pub fn new(bus: B) -> Self {
+        Self {
+            bus,
+            reads: [0; MEM_SIZE],
+            writes: [0; MEM_SIZE],
+        }
+    }
+}
+
+impl<B: Bus> Bus for Counted<B> {
+    fn peek(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+
+    fn poke(&mut self, addr: u16, byte: u8) {
+        self.bus.poke(addr, byte)
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.reads[addr as usize] += 1;
+        self.bus.read(addr)
+    }
+
+    fn write(&mut self, addr: u16, byte: u8) -> bool {
+        self.writes[addr as usize] += 1;
+        self.bus.write(addr, byte)
+    }
+}
+
+// Protected refuses writes of the program to regions of another bus, such
+// as the font or the code of the ROM.
+#[derive(Debug, Clone)]
+pub struct Protected<B: Bus> {
+    pub bus: B,
+    protected: [bool; MEM_SIZE], // Whether every address is protected
+}
+
+impl<B: Bus> Protected<B> {
+    pub fn new(bus: B) -> Self {
+        Self {
+            bus,
+            protected: [false; MEM_SIZE],
+        }
+    }
+
+    // protect refuses writes to a range of addresses from now on.
+    pub fn protect(&mut self, range: Range<u16>) {
+        for addr in range.filter(|addr| (*addr as usize) < MEM_SIZE) {
+            self.protected[addr as usize] = true;
+        }
+    }
+
+    // is_protected returns whether writes to an address are refused.
+    pub fn is_protected(&self, addr: u16) -> bool {
+        self.protected[addr as usize % MEM_SIZE]
+    }
+}
+
+impl<B: Bus> Bus for Protected<B> {
+    fn peek(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+
+    fn poke(&mut self, addr: u16, byte: u8) {
+        self.bus.poke(addr, byte)
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.bus.read(addr)
+    }
+
+    fn write(&mut self, addr: u16, byte: u8) -> bool {
+        !self.protected[addr as usize] && self.bus.write(addr, byte)
+    }
+}
@@ -1,22 +1,54 @@
+#[cfg(feature = "piston")]
 use super::super::gfx;
+use super::bus::{Bus, Memory};
+#[cfg(feature = "std")]
+use super::coverage::Coverage;
+use super::events::Event;
+#[cfg(feature = "std")]
+use super::events::{HookId, Hooks};
+use super::framebuffer::{Edge, LoRes};
 use super::instruction::{ErrUnsupportedInstruction, Instruction};
+#[cfg(feature = "std")]
+use super::profiler::Profiler;
+use super::quirks::Quirks;
+use super::random::Random;
+#[cfg(feature = "std")]
+use super::trace::{self, Tracer};
 use crate::arithmetic;
-use rand::Rng;
-use std::convert::TryInto;
+#[cfg(feature = "std")]
+use crate::detect::{self, Detection};
+#[cfg(feature = "std")]
+use crate::romdb::{self, Game};
+#[cfg(feature = "std")]
+use crate::screenshot::Palette;
+#[cfg(feature = "std")]
+use blocks::Blocks;
+use core::convert::TryFrom;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::ops::Range;
+#[cfg(feature = "std")]
 use std::error;
-use std::fs;
+#[cfg(feature = "std")]
+use std::{fs, io};
 
-const MEM_SIZE: usize = 0x1000;
-const N_REGISTERS: usize = 16;
+#[cfg(feature = "std")]
+pub mod blocks;
+pub mod state;
+
+pub const MEM_SIZE: usize = 0x1000;
+pub const N_REGISTERS: usize = 16;
 const STACK_DEPTH: usize = 12;
-const PROGRAM_START: u16 = 0x200;
+pub const PROGRAM_START: u16 = 0x200;
 const F: usize = 0xF;
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
 
+// INSTRUCTIONS_PER_FRAME is how many instructions run between two ticks of
+// the 60 Hz timers, unless told otherwise.
+pub const INSTRUCTIONS_PER_FRAME: usize = 10;
+
 const FONTSET_SIZE: usize = 80;
-const FONT_HEIGHT: usize = 8;
-const FONT_WIDTH: usize = 5;
 const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, 0x20, 0x60, 0x20, 0x20, 0x70, 0xF0, 0x10,
     0xF0, 0x80, 0xF0, 0xF0, 0x10, 0xF0, 0x10, 0xF0, 0x90, 0x90, 0xF0, 0x10,
@@ -27,38 +59,144 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
 ];
 
-const N_KEYS: usize = 16;
-const KEYS: [u8; N_KEYS] = [
-    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x9, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB,
+pub const N_KEYS: usize = 16;
+// KEYS is the layout of the hex keypad, row by row.
+pub const KEYS: [u8; N_KEYS] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB,
     0xF,
 ];
 
 pub const OFF: u8 = 0x0;
 pub const ON: u8 = 0x1;
 
+// Fault is raised when the machine cannot go on executing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    UnsupportedInstruction(u16), // The opcode that did not decode
+    StackOverflow(u16),          // The address of the call
+    StackUnderflow(u16),         // The address of the return
+    ProtectedWrite(u16),         // The address the bus refused to write
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnsupportedInstruction(opcode) => {
+                write!(f, "{}", ErrUnsupportedInstruction(*opcode))
+            }
+            Fault::StackOverflow(pc) => {
+                write!(f, "stack overflow calling from 0x{:03x}", pc)
+            }
+            Fault::StackUnderflow(pc) => {
+                write!(f, "stack underflow returning from 0x{:03x}", pc)
+            }
+            Fault::ProtectedWrite(addr) => {
+                write!(f, "write to protected memory at 0x{:03x}", addr)
+            }
+        }
+    }
+}
+
+impl From<ErrUnsupportedInstruction> for Fault {
+    fn from(e: ErrUnsupportedInstruction) -> Self {
+        Fault::UnsupportedInstruction(e.0)
+    }
+}
+
+// ErrRomTooLarge is returned when a ROM does not fit in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrRomTooLarge(pub usize); // The size of the ROM
+
+impl fmt::Display for ErrRomTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ROM of {} bytes does not fit in memory", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ErrRomTooLarge {}
+
 // Chip8 is the struct that represents a single CHIP-8 interpreter.
-pub struct Chip8 {
-    pub memory: [u8; MEM_SIZE], // The memory
-    V: [u8; N_REGISTERS],       // The general purpose registers
-    I: u16,                     // The I register
-    stack: [u8; STACK_DEPTH],   // The stack
+pub struct Chip8<B: Bus = Memory> {
+    pub quirks: Quirks, // The behaviours of the emulated interpreter
+    #[cfg(feature = "std")]
+    pub game: Option<&'static Game>, // The database entry of the loaded ROM
+    #[cfg(feature = "std")]
+    pub detection: Option<Detection>, // The guess for ROMs missing from it
+
+    bus: B,                    // The bus the memory is on
+    V: [u8; N_REGISTERS],      // The general purpose registers
+    I: u16,                    // The I register
+    stack: [u16; STACK_DEPTH], // The stack
 
     pc: u16, // The program counter
-    sp: u8,  // The stack pointer
+    sp: u8,  // The stack pointer, the number of addresses on the stack
 
     delay_timer: u16, // The delay timer
     sound_timer: u16, // The sound timer
 
-    display: gfx::Display,
-    keys: [u8; N_KEYS], // The keys
-    key: Option<u8>,    // The current key being pressed
+    display: LoRes,         // The display
+    keys: [u8; N_KEYS],     // The keys
+    keypad: [bool; N_KEYS], // Whether each key is being pressed
+    rng: Random,            // The random number generator for CXNN
+    waiting: bool,          // Whether FX0A is waiting for a key
+
+    rom_size: usize, // The size of the loaded ROM
+
+    // The decoded instruction at every address, until memory under it is
+    // written, when enabled
+    #[cfg(feature = "std")]
+    cache: Option<Box<[Option<Instruction>]>>,
+
+    #[cfg(feature = "std")]
+    tracer: Option<Tracer>, // Traces every step, when enabled
+    #[cfg(feature = "std")]
+    coverage: Option<Coverage>, // Records memory accesses, when enabled
+    #[cfg(feature = "std")]
+    profiler: Option<Profiler>, // Counts executions and time, when enabled
+    #[cfg(feature = "std")]
+    blocks: Option<Blocks<B>>, // Runs translated blocks, when enabled
+    #[cfg(feature = "std")]
+    hooks: Hooks, // Are called on events
 }
 
 impl Chip8 {
-    // new constructs a new CHIP-8 interpreter.
+    // new constructs a new CHIP-8 interpreter with plain memory.
     pub fn new() -> Self {
+        Self::with_bus(Memory::new())
+    }
+
+    // run runs the contents of the virtual machine in a window, returning
+    // the fault that stopped it, if any.
+    #[cfg(feature = "piston")]
+    pub fn run(&mut self) -> Option<Fault> {
+        gfx::Display::new().run(self)
+    }
+
+    // rom returns the part of memory the ROM was loaded into.
+    pub fn rom(&self) -> &[u8] {
+        let start = PROGRAM_START as usize;
+        &self.bus.bytes()[start..start + self.rom_size]
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Bus> Chip8<B> {
+    // with_bus constructs a new CHIP-8 interpreter with its memory on a
+    // bus.
+    pub fn with_bus(bus: B) -> Self {
         let mut c8 = Self {
-            memory: [0; MEM_SIZE],
+            quirks: Quirks::default(),
+            #[cfg(feature = "std")]
+            game: None,
+            #[cfg(feature = "std")]
+            detection: None,
+            bus,
             V: [0; N_REGISTERS],
             I: 0,
             stack: [0; STACK_DEPTH],
@@ -69,40 +207,247 @@ impl Chip8 {
             delay_timer: 0,
             sound_timer: 0,
 
-            display: gfx::Display::new(),
+            display: LoRes::new(),
             keys: [0; N_KEYS],
-            key: None,
+            keypad: [false; N_KEYS],
+            rng: Random::new(),
+            waiting: false,
+
+            rom_size: 0,
+
+            #[cfg(feature = "std")]
+            cache: Some(vec![None; MEM_SIZE].into_boxed_slice()),
+
+            #[cfg(feature = "std")]
+            tracer: None,
+            #[cfg(feature = "std")]
+            coverage: None,
+            #[cfg(feature = "std")]
+            profiler: None,
+            #[cfg(feature = "std")]
+            blocks: None,
+            #[cfg(feature = "std")]
+            hooks: Hooks::default(),
         };
         c8.install_fontset();
         c8.init_keys();
         c8
     }
 
-    // run runs the contents of the virtual machine.
-    pub fn run(&mut self) {
-        // Run the program until it stops
-        while self.pc < self.memory.len() as u16 {
-            // Run the window
-            while let Some(event) = self.display.screen.next() {
-                // self.register_dump();
-                // Step the processor once
+    // run_frame runs the given number of instructions and then counts the
+    // timers down once, which is a single 60 Hz frame.
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Fault> {
+        // Blocks skip the tracer, the coverage, the profiler and the hooks,
+        // which see every instruction
+        #[cfg(feature = "std")]
+        {
+            let observed = self.tracer.is_some()
+                || self.coverage.is_some()
+                || self.profiler.is_some()
+                || !self.hooks.is_empty();
+            if self.blocks.is_some() && !observed {
+                self.run_blocks(instructions)?;
+                self.tick();
+                return Ok(());
+            }
+        }
+        for _ in 0..instructions {
+            self.cycle()?;
+        }
+        self.tick();
+        Ok(())
+    }
 
-                self.cycle();
+    // tick counts the timers down once, at the end of a 60 Hz frame.
+    pub fn tick(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+            if self.sound_timer == 0 {
+                self.emit(Event::SoundStopped);
+            }
+        }
+        self.emit(Event::FrameCompleted);
+    }
 
-                // Set some random pixels
-                self.display.pixels[0] = ON;
-                self.display.pixels[WIDTH * 3 + 2] = ON;
+    // add_hook adds a callback for every event of the machine and returns
+    // its ID. Hooks are called in the order they were added.
+    #[cfg(feature = "std")]
+    pub fn add_hook(&mut self, hook: impl FnMut(&Event) + 'static) -> HookId {
+        self.hooks.add(Box::new(hook))
+    }
 
-                // Draw the screen
-                self.display.draw(&event);
+    // remove_hook removes a hook and returns whether it was there.
+    #[cfg(feature = "std")]
+    pub fn remove_hook(&mut self, id: HookId) -> bool {
+        self.hooks.remove(id)
+    }
+
+    // watch makes writes of the program to a range of memory raise
+    // MemoryWritten events.
+    #[cfg(feature = "std")]
+    pub fn watch(&mut self, range: Range<u16>) {
+        self.hooks.watch(range);
+    }
+
+    // unwatch stops watching every range of memory.
+    #[cfg(feature = "std")]
+    pub fn unwatch(&mut self) {
+        self.hooks.unwatch();
+    }
+
+    // emit calls the hooks with an event.
+    #[cfg(feature = "std")]
+    #[inline]
+    fn emit(&mut self, event: Event) {
+        if !self.hooks.is_empty() {
+            self.hooks.emit(event);
+        }
+    }
+
+    // emit does nothing without std, which has no hooks.
+    #[cfg(not(feature = "std"))]
+    #[inline]
+    fn emit(&mut self, _: Event) {}
+
+    // set_key presses or releases a key of the hex keypad.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        self.keypad[key as usize % N_KEYS] = pressed;
+    }
+
+    // seed reseeds the random number generator, making CXNN deterministic.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Random::seeded(seed);
+    }
+
+    // display returns the display.
+    pub fn display(&self) -> &LoRes {
+        &self.display
+    }
+
+    // framebuffer_hash returns the 64-bit FNV-1a hash of the display.
+    pub fn framebuffer_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for pixel in self.display.pixels() {
+            hash ^= pixel as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+
+    // register returns the value of the V register r.
+    pub fn register(&self, r: usize) -> u8 {
+        self.V[r]
+    }
+
+    // index returns the value of the I register.
+    pub fn index(&self) -> u16 {
+        self.I
+    }
+
+    // pc returns the program counter.
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // set_register sets a general purpose register, for scripts and
+    // debuggers.
+    pub fn set_register(&mut self, r: usize, value: u8) {
+        self.V[r] = value;
+    }
+
+    // set_index sets the I register.
+    pub fn set_index(&mut self, value: u16) {
+        self.I = value;
+    }
+
+    // set_pc sets the program counter.
+    pub fn set_pc(&mut self, value: u16) {
+        self.pc = value;
+    }
+
+    // stack returns the return addresses on the stack, oldest first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    // delay_timer returns the value of the delay timer.
+    pub fn delay_timer(&self) -> u16 {
+        self.delay_timer
+    }
+
+    // sound_timer returns the value of the sound timer.
+    pub fn sound_timer(&self) -> u16 {
+        self.sound_timer
+    }
+
+    // bus returns the bus the memory is on.
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
+
+    // bus_mut returns the bus the memory is on. Code changed through it
+    // is not seen by the decode cache or translated blocks; use store.
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    // peek returns the byte of memory at an address, without side effects.
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.bus.peek(addr % MEM_SIZE as u16)
+    }
+
+    // store writes a byte of memory from the host, past any protection of
+    // the bus. Memory must be written through store for changes to code to
+    // be seen by the decode cache and translated blocks.
+    pub fn store(&mut self, addr: u16, byte: u8) {
+        let addr = addr % MEM_SIZE as u16;
+        self.bus.poke(addr, byte);
+        #[cfg(feature = "std")]
+        self.invalidate(addr as usize);
+    }
+
+    // write writes a byte of memory for the program, which faults when the
+    // bus refuses it.
+    fn write(&mut self, addr: u16, byte: u8) -> Result<(), Fault> {
+        let addr = addr % MEM_SIZE as u16;
+        if !self.bus.write(addr, byte) {
+            return Err(Fault::ProtectedWrite(addr));
+        }
+        #[cfg(feature = "std")]
+        {
+            self.invalidate(addr as usize);
+            if self.hooks.watches(addr) {
+                self.emit(Event::MemoryWritten { addr, byte });
             }
         }
+        Ok(())
+    }
+
+    // invalidate drops the decoded instructions and translated blocks that
+    // include a written address.
+    #[cfg(feature = "std")]
+    fn invalidate(&mut self, addr: usize) {
+        if let Some(cache) = &mut self.cache {
+            cache[addr] = None;
+            cache[(addr + MEM_SIZE - 1) % MEM_SIZE] = None;
+        }
+        if let Some(blocks) = &mut self.blocks {
+            blocks.invalidate(addr);
+        }
+    }
+
+    // read reads a byte of memory for the program.
+    fn read(&mut self, addr: usize) -> u8 {
+        self.bus.read((addr % MEM_SIZE) as u16)
     }
 
     // install_fontset loads the font ROM into memory.
     fn install_fontset(&mut self) {
         for i in 0..FONTSET.len() {
-            self.memory[i] = FONTSET[i];
+            self.store(i as u16, FONTSET[i]);
         }
     }
 
@@ -114,26 +459,29 @@ impl Chip8 {
     }
 
     // memory_dump prints a certain amount of bytes of the system memory.
+    #[cfg(feature = "std")]
     pub fn memory_dump(&self, n_bytes: usize) {
         let chars_per_line = 200;
         for i in 0..n_bytes {
             for j in 0..chars_per_line {
-                print!("{:x}", self.memory[i]);
+                print!("{:x}", self.peek(i as u16));
             }
             println!("");
         }
     }
 
     // full_dump prints the entire memory space and address space.
+    #[cfg(feature = "std")]
     pub fn full_dump(&self) {
         println!("ADR    | BYTE");
         println!("-------|------");
-        for i in 0..self.memory.len() {
-            println!("0x{:04x} | 0x{:02x}", i, self.memory[i]);
+        for i in 0..MEM_SIZE {
+            println!("0x{:04x} | 0x{:02x}", i, self.peek(i as u16));
         }
     }
 
     // register_dump prints the registers of the system.
+    #[cfg(feature = "std")]
     pub fn register_dump(&self) {
         print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
         for i in 0..self.V.len() {
@@ -145,80 +493,231 @@ impl Chip8 {
     pub fn parse_file(&mut self, filename: &str) {}
 
     // load_rom loads a ROM given the filename of the ROM and loads it into the machine.
-    pub fn load_rom(&mut self, filename: &str) -> Result<(), std::io::Error> {
+    #[cfg(feature = "std")]
+    pub fn load_rom(&mut self, filename: &str) -> Result<(), io::Error> {
         let rom = fs::read(filename)?;
-        for i in (0..rom.len()) {
-            self.memory[i + PROGRAM_START as usize] = rom[i];
+        self.load_rom_bytes(&rom)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // load_rom_bytes loads a ROM that is already in memory into the machine,
+    // and sets the quirks it was written for. Known games also come with a
+    // speed and palette, which the caller applies, see recommended_speed and
+    // recommended_palette.
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), ErrRomTooLarge> {
+        if rom.len() > MEM_SIZE - PROGRAM_START as usize {
+            return Err(ErrRomTooLarge(rom.len()));
+        }
+        for (i, byte) in rom.iter().enumerate() {
+            self.store(PROGRAM_START + i as u16, *byte);
+        }
+        self.rom_size = rom.len();
+        #[cfg(feature = "std")]
+        {
+            if self.blocks.is_some() {
+                self.blocks = Some(Blocks::new());
+            }
+            self.identify(rom);
         }
         Ok(())
     }
 
+    // identify sets the quirks for a ROM. Known games get the quirks they
+    // were written for, and the rest those of the platform they look
+    // written for. The speed and palette of known games are left to the
+    // caller, see recommended_speed and recommended_palette. Without std,
+    // the quirks are left to the embedder.
+    #[cfg(feature = "std")]
+    fn identify(&mut self, rom: &[u8]) {
+        self.game = romdb::lookup(rom);
+        self.detection = None;
+        match self.game {
+            Some(game) => self.quirks = game.quirks,
+            None => {
+                let detection = detect::detect(rom);
+                self.quirks = detection.quirks;
+                self.detection = Some(detection);
+            }
+        }
+    }
+
+    // recommended_speed returns the instructions per frame the loaded ROM
+    // plays best at, from the ROM database, or INSTRUCTIONS_PER_FRAME. The
+    // machine runs as many as it is told, so the caller passes it to
+    // run_frame.
+    #[cfg(feature = "std")]
+    pub fn recommended_speed(&self) -> usize {
+        self.game.map_or(INSTRUCTIONS_PER_FRAME, |game| game.speed)
+    }
+
+    // recommended_palette returns the colors the loaded ROM is best drawn
+    // with, from the ROM database, or the default palette. Drawing is up to
+    // the caller.
+    #[cfg(feature = "std")]
+    pub fn recommended_palette(&self) -> Palette {
+        self.game
+            .and_then(|game| game.palette)
+            .unwrap_or(Palette::DEFAULT)
+    }
+
     // cycle will step the virtual machine once.
-    pub fn cycle(&mut self) {
-        // Count down the timers
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+    pub fn cycle(&mut self) -> Result<(), Fault> {
+        let result = self.step();
+        if let Err(fault) = result {
+            self.emit(Event::Faulted(fault));
         }
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
+        result
+    }
+
+    // step fetches, decodes and executes an instruction.
+    fn step(&mut self) -> Result<(), Fault> {
+        // Fetch and decode an instruction
+        let pc = self.pc as usize % MEM_SIZE;
+        let instr = self.fetch(pc)?;
+
+        // Execute the instruction
+        let pc = self.pc;
+        #[cfg(feature = "std")]
+        let (v, i) = (self.V, self.I);
+        #[cfg(feature = "std")]
+        {
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record(pc, &instr, i);
+            }
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record(pc, &instr);
+            }
+        }
+        let result = self.execute(instr);
+
+        // Trace the step, even one that faulted
+        #[cfg(feature = "std")]
+        if let Some(tracer) = &mut self.tracer {
+            let mut changed = 0;
+            for (r, (old, new)) in v.iter().zip(self.V.iter()).enumerate() {
+                if old != new {
+                    changed |= 1 << r;
+                }
+            }
+            if i != self.I {
+                changed |= trace::I_CHANGED;
+            }
+            tracer.record(pc, &instr, self.V, self.I, changed);
+        }
+        result?;
+        self.emit(Event::Executed {
+            pc,
+            instruction: instr,
+        });
+        Ok(())
+    }
+
+    // fetch fetches and decodes the instruction at an address. Cached
+    // instructions are not fetched again from the bus.
+    #[cfg(feature = "std")]
+    fn fetch(&mut self, pc: usize) -> Result<Instruction, Fault> {
+        if let Some(instr) = self.cache.as_ref().and_then(|cache| cache[pc]) {
+            return Ok(instr);
         }
+        let instr = self.decode(pc)?;
+        if let Some(cache) = &mut self.cache {
+            cache[pc] = Some(instr);
+        }
+        Ok(instr)
+    }
 
-        // println!("program counter: {}", self.pc);
+    // fetch fetches and decodes the instruction at an address. Without std
+    // there is no cache to keep decoded instructions in.
+    #[cfg(not(feature = "std"))]
+    fn fetch(&mut self, pc: usize) -> Result<Instruction, Fault> {
+        self.decode(pc)
+    }
 
-        // Fetch an instruction
-        // The only reason why these are u16s is because it will make them easier
-        // to deal with when determining the instruction.
-        let b1: u16 = self.memory[self.pc as usize].into(); // Fetch the first byte
-        let b2: u16 = self.memory[self.pc as usize + 1].into(); // Fetch the second byte
+    // decode reads the opcode at an address from the bus and decodes it.
+    fn decode(&mut self, pc: usize) -> Result<Instruction, Fault> {
+        // The only reason why these are u16s is because it will make them
+        // easier to deal with when determining the instruction.
+        let b1: u16 = self.read(pc).into(); // Fetch the first byte
+        let b2: u16 = self.read(pc + 1).into(); // Fetch the second byte
         let opcode: u16 = (b1 << 8) | b2; // Concat the two
-        let jmp: u16 = (opcode & 0x0FFF).into(); // NNN (the jump address)
-        let x: usize = ((opcode & 0x0F00) >> 8).into();
-        let y: usize = ((opcode & 0x00F0) >> 4).into();
-        let nn: u16 = opcode & 0x00FF;
-
-        println!(
-            "self.memory[self.pc as usize] = {}",
-            self.memory[self.pc as usize]
-        );
-        println!("program counter: {}", self.pc);
-
-        // println!("random operation: 0x{:04x}", (0xab << 8) | 0xcd);
-        // Execute the fetched instruction
-        let instr = Instruction::get_instr_from_parts(opcode, jmp, x, y, nn);
-        println!(
-            "opcode: {:x}, jmp: {:x}, x: {}, y: {}, nn: {}",
-            opcode, jmp, x, y, nn
-        );
-        println!("fetched instruction '{:?}'", instr);
-        self.execute(instr);
-        println!("");
-    }
-
-    // execute executes a single instruction.
-    fn execute(
-        &mut self,
-        i: Instruction,
-    ) -> Result<(), ErrUnsupportedInstruction> {
+        Ok(Instruction::try_from(opcode)?)
+    }
+
+    // disable_cache makes every step fetch its instruction from the bus, for
+    // buses that must see every fetch or whose code changes behind the
+    // machine's back.
+    #[cfg(feature = "std")]
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
+    // set_tracer enables tracing of every step to the given tracer, or
+    // disables tracing when given None. The old tracer is flushed, dropping
+    // any error, which take_tracer leaves to the caller.
+    #[cfg(feature = "std")]
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        if let Some(old) = &mut self.tracer {
+            old.flush().ok();
+        }
+        self.tracer = tracer;
+    }
+
+    // tracer returns the tracer, if enabled.
+    #[cfg(feature = "std")]
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    // take_tracer disables tracing and returns the tracer, if enabled,
+    // without flushing it.
+    #[cfg(feature = "std")]
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    // enable_coverage starts recording which addresses are executed, read
+    // and written. Any coverage recorded so far is discarded.
+    #[cfg(feature = "std")]
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    // coverage returns the coverage recorded so far, if enabled.
+    #[cfg(feature = "std")]
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    // enable_profiler starts profiling every executed instruction. Any
+    // profile recorded so far is discarded.
+    #[cfg(feature = "std")]
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    // profiler returns the profile recorded so far, if enabled.
+    #[cfg(feature = "std")]
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    // execute executes a single instruction as if it were at the program
+    // counter, without the tracer, the coverage or the profiler.
+    pub fn execute(&mut self, i: Instruction) -> Result<(), Fault> {
         let mut should_jump = false;
         match i {
             Instruction::I0NNN(a) => {} // Not really implemented
             Instruction::I00E0 => {
-                // Clear the display
-                for i in 0..self.display.pixels.len() {
-                    self.display.pixels[i] = OFF;
-                }
-                println!("display cleared");
+                self.display.clear(); // Clear the display
+                self.emit(Event::DisplayCleared);
             }
             Instruction::I00EE => {
                 // Return from subroutine
-                if self.stack[self.sp as usize] == 0 {
-                    panic!("no value to return to on the stack");
-                }
-                self.pc = self.stack[self.sp as usize].into(); // Pop off the stack
-                self.stack[self.sp as usize] = 0; // Remove the value
-                if self.sp > 0 {
-                    self.sp -= 1; // Update the stack pointer
+                if self.sp == 0 {
+                    return Err(Fault::StackUnderflow(self.pc));
                 }
+                self.sp -= 1; // Update the stack pointer
+                self.pc = self.stack[self.sp as usize]; // Pop off the stack
             }
             Instruction::I1NNN(a) => {
                 // Jump to address NNN
@@ -227,10 +726,11 @@ impl Chip8 {
             }
             Instruction::I2NNN(a) => {
                 // Call suborutine at address NNN
-                if self.stack[self.sp as usize] != 0 {
-                    self.sp += 1;
+                if self.sp as usize == STACK_DEPTH {
+                    return Err(Fault::StackOverflow(self.pc));
                 }
-                self.stack[self.sp as usize] = self.pc as u8; // Push current address to the stack
+                self.stack[self.sp as usize] = self.pc; // Push current address to the stack
+                self.sp += 1;
                 self.pc = a;
                 should_jump = true;
             }
@@ -252,30 +752,47 @@ impl Chip8 {
             Instruction::I6XNN(x, b) => {
                 self.V[x] = b;
             }
-            Instruction::I7XNN(x, b) => self.V[x] += b,
+            Instruction::I7XNN(x, b) => self.V[x] = self.V[x].wrapping_add(b),
             Instruction::I8XY0(x, y) => self.V[x] = self.V[y],
-            Instruction::I8XY1(x, y) => self.V[x] |= self.V[y],
-            Instruction::I8XY2(x, y) => self.V[x] &= self.V[y],
-            Instruction::I8XY3(x, y) => self.V[x] ^= self.V[y],
+            Instruction::I8XY1(x, y) => {
+                self.V[x] |= self.V[y];
+                if self.quirks.vf_reset {
+                    self.V[F] = 0;
+                }
+            }
+            Instruction::I8XY2(x, y) => {
+                self.V[x] &= self.V[y];
+                if self.quirks.vf_reset {
+                    self.V[F] = 0;
+                }
+            }
+            Instruction::I8XY3(x, y) => {
+                self.V[x] ^= self.V[y];
+                if self.quirks.vf_reset {
+                    self.V[F] = 0;
+                }
+            }
             Instruction::I8XY4(x, y) => {
                 self.V[F] = arithmetic::check_carry(&self.V[x], &self.V[y]);
-                self.V[x] += self.V[y];
+                self.V[x] = self.V[x].wrapping_add(self.V[y]);
             }
             Instruction::I8XY5(x, y) => {
                 self.V[F] = arithmetic::check_borrow(&self.V[x], &self.V[y]);
-                self.V[x] -= self.V[y];
+                self.V[x] = self.V[x].wrapping_sub(self.V[y]);
             }
             Instruction::I8XY6(x, y) => {
-                self.V[F] = arithmetic::get_lsb(&self.V[y]);
-                self.V[x] = self.V[y] >> 1;
+                let src = if self.quirks.shift { x } else { y };
+                self.V[F] = arithmetic::get_lsb(&self.V[src]);
+                self.V[x] = self.V[src] >> 1;
             }
             Instruction::I8XY7(x, y) => {
-                self.V[F] = arithmetic::check_borrow(&self.V[x], &self.V[y]);
-                self.V[x] = self.V[y] - self.V[x];
+                self.V[F] = arithmetic::check_borrow(&self.V[y], &self.V[x]);
+                self.V[x] = self.V[y].wrapping_sub(self.V[x]);
             }
             Instruction::I8XYE(x, y) => {
-                self.V[F] = arithmetic::get_msb(&self.V[y]);
-                self.V[x] = self.V[y] << 1;
+                let src = if self.quirks.shift { x } else { y };
+                self.V[F] = arithmetic::get_msb(&self.V[src]);
+                self.V[x] = self.V[src] << 1;
             }
             Instruction::I9XY0(x, y) => {
                 if self.V[x] != self.V[y] {
@@ -283,111 +800,109 @@ impl Chip8 {
                 }
             }
             Instruction::IANNN(a) => self.I = a,
-            Instruction::IBNNN(a) => self.pc = a + (self.V[0] as u16),
+            Instruction::IBNNN(a) => {
+                // With the jump quirk, the X of BXNN is the offset register
+                let r = if self.quirks.jump {
+                    (a >> 8) as usize
+                } else {
+                    0
+                };
+                self.pc = a + (self.V[r] as u16);
+                should_jump = true;
+            }
             Instruction::ICXNN(x, b) => {
-                let r: u8 = rand::thread_rng().gen();
+                let r = self.rng.byte();
                 self.V[x] = r & b;
             }
             Instruction::IDXYN(x, y, n) => {
                 // Draw sprite at position (Vx, Vy) with N bytes of sprite data starting
                 // at the address stored in I. Set VF to 01 if any set pixels are
                 // changed to unset, and 00 otherwise.
-                self.V[F] = 1;
-                let xpos = self.V[x];
-                let ypos = self.V[y];
-                let mut sprite: Vec<u8> = Vec::new();
-                for i in 0..n {
-                    sprite.push(self.memory[self.I as usize + i as usize]);
-                }
-
-                for i in 0..FONT_HEIGHT {
-                    for j in 0..FONT_WIDTH {}
-                }
-
-                /*
-                println!("FONT: ");
-                for byte in sprite.clone() {
-                    println!("{:#018b}", byte);
+                let bus = &mut self.bus;
+                let start = self.I as usize;
+                let sprite = (0..n as usize)
+                    .map(|i| bus.read(((start + i) % MEM_SIZE) as u16));
+                let edge = if self.quirks.clip {
+                    Edge::Clip
+                } else {
+                    Edge::Wrap
+                };
+                let (xpos, ypos) = (self.V[x] as usize, self.V[y] as usize);
+                let collision = self.display.blit(xpos, ypos, sprite, edge);
+                self.V[F] = collision as u8;
+                self.emit(Event::SpriteDrawn {
+                    x: xpos as u8,
+                    y: ypos as u8,
+                    rows: n,
+                    collision,
+                });
+            }
+            Instruction::IEX9E(x) => {
+                if self.keypad[self.V[x] as usize % N_KEYS] {
+                    self.pc += 2;
                 }
-                */
-
-                let mut yi = ypos as usize;
-                for byte in sprite {
-                    for bit in 0..8 {
-                        let mut xi = (xpos + (0x7 - bit)) as usize;
-                        if xi > 0x3F {
-                            xi = 0x45;
-                        }
-                        if yi > 0x1F {
-                            yi -= 0x20;
-                        }
-
-                        let mut flipped = OFF;
-                        if (byte >> bit) & 1 == 1 {
-                            flipped = ON;
-                        }
-
-                        if flipped != self.display.pixels[WIDTH * yi + xi] {
-                            self.display.pixels[WIDTH * yi + xi] = ON;
-                        } else {
-                            if self.display.pixels[WIDTH * yi + xi] == ON {
-                                self.V[F] = 1;
-                            }
-                            self.display.pixels[WIDTH * yi + xi] = OFF;
-                        }
-                    }
-                    yi += 1;
+            }
+            Instruction::IEXA1(x) => {
+                if !self.keypad[self.V[x] as usize % N_KEYS] {
+                    self.pc += 2;
                 }
             }
-            Instruction::IEX9E(x) => match self.key {
-                Some(k) => {
-                    if self.V[x] == k {
-                        self.pc += 2;
+            Instruction::IFX07(x) => self.V[x] = self.delay_timer as u8,
+            Instruction::IFX0A(x) => {
+                // Wait by running this instruction again until a key is down
+                match self.keypad.iter().position(|&down| down) {
+                    Some(k) => {
+                        self.V[x] = k as u8;
+                        self.waiting = false;
                     }
-                }
-                None => (),
-            },
-            Instruction::IEXA1(x) => match self.key {
-                Some(k) => {
-                    if self.V[x] != k {
-                        self.pc += 2;
+                    None => {
+                        should_jump = true;
+                        if !self.waiting {
+                            self.waiting = true;
+                            self.emit(Event::WaitingForKey { register: x });
+                        }
                     }
                 }
-                None => (),
-            },
-            Instruction::IFX07(x) => self.V[x] = self.delay_timer as u8,
-            Instruction::IFX0A(x) => loop {
-                match self.key {
-                    Some(k) => self.V[x] = k,
-                    None => continue,
-                }
-            },
+            }
             Instruction::IFX15(x) => self.delay_timer = self.V[x] as u16,
-            Instruction::IFX18(x) => self.sound_timer = self.V[x] as u16,
-            Instruction::IFX1E(x) => self.I += self.V[x] as u16,
+            Instruction::IFX18(x) => {
+                let silent = self.sound_timer == 0;
+                self.sound_timer = self.V[x] as u16;
+                match (silent, self.sound_timer == 0) {
+                    (true, false) => self.emit(Event::SoundStarted),
+                    (false, true) => self.emit(Event::SoundStopped),
+                    _ => (),
+                }
+            }
+            Instruction::IFX1E(x) => {
+                self.I = self.I.wrapping_add(self.V[x] as u16)
+            }
             Instruction::IFX29(x) => {
-                let sprite: usize = (5 * (self.V[x] + 1)).into();
-                self.I = self.memory[sprite] as u16;
+                // The font is at the start of memory, 5 bytes per digit
+                self.I = (self.V[x] & 0xF) as u16 * 5;
             }
             Instruction::IFX33(x) => {
-                self.memory[self.I as usize] = self.V[x] / 100;
-                self.memory[self.I as usize + 1] = (self.V[x] / 10) % 10;
-                self.memory[self.I as usize + 2] = self.V[x] % 10;
+                let (i, v) = (self.I, self.V[x]);
+                self.write(i, v / 100)?;
+                self.write(i.wrapping_add(1), (v / 10) % 10)?;
+                self.write(i.wrapping_add(2), v % 10)?;
             }
             Instruction::IFX55(x) => {
                 for i in 0..(x + 1) {
-                    self.memory[self.I as usize + i] = self.V[i];
+                    self.write(self.I.wrapping_add(i as u16), self.V[i])?;
+                }
+                if !self.quirks.load_store {
+                    self.I = self.I.wrapping_add((x + 1) as u16);
                 }
-                self.I += (x + 1) as u16;
             }
             Instruction::IFX65(x) => {
                 for i in 0..(x + 1) {
-                    self.V[i] = self.memory[self.I as usize + i];
+                    self.V[i] = self.read(self.I as usize + i);
+                }
+                if !self.quirks.load_store {
+                    self.I = self.I.wrapping_add((x + 1) as u16);
                 }
-                self.I += (x + 1) as u16;
             }
-
-            Instruction::UNSUPPORTED => return Err(ErrUnsupportedInstruction),
         };
 
         if !should_jump {
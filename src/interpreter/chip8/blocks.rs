@@ -0,0 +1,287 @@
+// The block engine runs straight-line code without fetching and decoding
+// every instruction. A basic block, the instructions from an address up to
+// and including the first one that may not fall through, is translated once
+// into a chain of closures bound to their operands. Code that is written to
+// after it was translated is interpreted from then on.
+use super::super::bus::Bus;
+use super::super::instruction::Instruction;
+use super::super::metadata::Flow;
+use super::{Chip8, Fault, F, MEM_SIZE};
+use crate::arithmetic;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+// MAX_OPS is the most instructions a block holds.
+const MAX_OPS: usize = 64;
+
+// Op is an instruction bound to its operands.
+type Op<B> = Box<dyn Fn(&mut Chip8<B>) -> Result<(), Fault>>;
+
+// Block is a translated basic block.
+struct Block<B: Bus> {
+    ops: Vec<Op<B>>,
+}
+
+// Blocks is the cache of translated blocks of a machine.
+pub struct Blocks<B: Bus> {
+    blocks: Vec<Option<Rc<Block<B>>>>, // The block starting at every address
+    code: Vec<bool>, // Whether every byte is in a translated block
+    modified: Vec<bool>, // Whether every byte was written after translation
+    generation: u64, // Counts the flushes of the cache
+    pub translated: usize, // The number of blocks translated
+    pub flushes: usize, // The number of times code was written to
+}
+
+impl<B: Bus> Blocks<B> {
+    pub fn new() -> Self {
+        Self {
+            blocks: vec![None; MEM_SIZE],
+            code: vec![false; MEM_SIZE],
+            modified: vec![false; MEM_SIZE],
+            generation: 0,
+            translated: 0,
+            flushes: 0,
+        }
+    }
+
+    // invalidate records a write to memory. Writing to translated code
+    // flushes every block, and the written bytes are never translated again.
+    pub fn invalidate(&mut self, addr: usize) {
+        if !self.code[addr] {
+            return;
+        }
+        self.modified[addr] = true;
+        for block in self.blocks.iter_mut() {
+            *block = None;
+        }
+        for byte in self.code.iter_mut() {
+            *byte = false;
+        }
+        self.generation += 1;
+        self.flushes += 1;
+    }
+
+    // block returns the block starting at addr, translating it from memory
+    // if needed. There is none when the first instruction was modified or
+    // does not decode. Code is peeked once, when it is translated.
+    fn block(&mut self, addr: usize, bus: &B) -> Option<Rc<Block<B>>> {
+        if let Some(block) = &self.blocks[addr] {
+            return Some(Rc::clone(block));
+        }
+        let mut ops = Vec::new();
+        let mut end = addr;
+        // Blocks stop short of the end of memory, where the program counter
+        // wraps around
+        while ops.len() < MAX_OPS && end + 1 < MEM_SIZE {
+            if self.modified[end] || self.modified[end + 1] {
+                break;
+            }
+            let byte = |addr: usize| bus.peek(addr as u16) as u16;
+            let opcode = byte(end) << 8 | byte(end + 1);
+            let instr = match Instruction::try_from(opcode) {
+                Ok(instr) => instr,
+                Err(_) => break,
+            };
+            ops.push(translate(instr));
+            end += 2;
+            if instr.metadata().flow != Flow::Next {
+                break;
+            }
+        }
+        if ops.is_empty() {
+            return None;
+        }
+        for byte in &mut self.code[addr..end] {
+            *byte = true;
+        }
+        let block = Rc::new(Block { ops });
+        self.blocks[addr] = Some(Rc::clone(&block));
+        self.translated += 1;
+        Some(block)
+    }
+}
+
+impl<B: Bus> Default for Blocks<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// translate binds an instruction to its operands. The instructions that
+// straight-line code is mostly made of are done inline, and the rest by the
+// interpreter. Quirks are read as the instruction runs, since they may be
+// changed at any time.
+fn translate<B: Bus>(instr: Instruction) -> Op<B> {
+    match instr {
+        Instruction::I6XNN(x, b) => Box::new(move |c8| {
+            c8.V[x] = b;
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I7XNN(x, b) => Box::new(move |c8| {
+            c8.V[x] = c8.V[x].wrapping_add(b);
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I8XY0(x, y) => Box::new(move |c8| {
+            c8.V[x] = c8.V[y];
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I8XY1(x, y) => Box::new(move |c8| {
+            c8.V[x] |= c8.V[y];
+            if c8.quirks.vf_reset {
+                c8.V[F] = 0;
+            }
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I8XY2(x, y) => Box::new(move |c8| {
+            c8.V[x] &= c8.V[y];
+            if c8.quirks.vf_reset {
+                c8.V[F] = 0;
+            }
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I8XY3(x, y) => Box::new(move |c8| {
+            c8.V[x] ^= c8.V[y];
+            if c8.quirks.vf_reset {
+                c8.V[F] = 0;
+            }
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I8XY4(x, y) => Box::new(move |c8| {
+            c8.V[F] = arithmetic::check_carry(&c8.V[x], &c8.V[y]);
+            c8.V[x] = c8.V[x].wrapping_add(c8.V[y]);
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::I8XY5(x, y) => Box::new(move |c8| {
+            c8.V[F] = arithmetic::check_borrow(&c8.V[x], &c8.V[y]);
+            c8.V[x] = c8.V[x].wrapping_sub(c8.V[y]);
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::IANNN(a) => Box::new(move |c8| {
+            c8.I = a;
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::IFX07(x) => Box::new(move |c8| {
+            c8.V[x] = c8.delay_timer as u8;
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::IFX15(x) => Box::new(move |c8| {
+            c8.delay_timer = c8.V[x] as u16;
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::IFX1E(x) => Box::new(move |c8| {
+            c8.I = c8.I.wrapping_add(c8.V[x] as u16);
+            c8.pc += 2;
+            Ok(())
+        }),
+        Instruction::IFX29(x) => Box::new(move |c8| {
+            c8.I = (c8.V[x] & 0xF) as u16 * 5;
+            c8.pc += 2;
+            Ok(())
+        }),
+        _ => Box::new(move |c8| c8.execute(instr)),
+    }
+}
+
+impl<B: Bus> Chip8<B> {
+    // enable_blocks makes run_frame run translated blocks instead of
+    // interpreting every instruction, while no tracer, coverage or profiler
+    // is enabled.
+    pub fn enable_blocks(&mut self) {
+        self.blocks = Some(Blocks::new());
+    }
+
+    // blocks returns the block cache, if enabled.
+    pub fn blocks(&self) -> Option<&Blocks<B>> {
+        self.blocks.as_ref()
+    }
+
+    // run_blocks runs the given number of instructions block by block. A
+    // block is left early when the budget runs out or when it writes to
+    // translated code, which may be itself.
+    pub(super) fn run_blocks(
+        &mut self,
+        mut instructions: usize,
+    ) -> Result<(), Fault> {
+        while instructions > 0 {
+            let pc = self.pc as usize % MEM_SIZE;
+            let blocks = self.blocks.as_mut().expect("blocks are enabled");
+            let generation = blocks.generation;
+            let block = match blocks.block(pc, &self.bus) {
+                Some(block) => block,
+                None => {
+                    // Let the interpreter run it, or fault on it
+                    self.cycle()?;
+                    instructions -= 1;
+                    continue;
+                }
+            };
+            for op in block.ops.iter().take(instructions) {
+                instructions -= 1;
+                op(self)?;
+                if self.blocks.as_ref().map(|b| b.generation)
+                    != Some(generation)
+                {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // differences returns how the state of the machine differs from another
+    // one: the registers, the stack, the timers, the keypad, the memory and
+    // the display. It is empty when they would go on running the same.
+    pub fn differences(&self, other: &Chip8<B>) -> Vec<String> {
+        let mut diffs = Vec::new();
+        let mut differ = |what: String, a: String, b: String| {
+            if a != b {
+                diffs.push(format!("{}: {} != {}", what, a, b));
+            }
+        };
+        for r in 0..self.V.len() {
+            let (a, b) = (self.V[r], other.V[r]);
+            differ(format!("V{:X}", r), hex(a), hex(b));
+        }
+        differ("I".into(), hex(self.I), hex(other.I));
+        differ("pc".into(), hex(self.pc), hex(other.pc));
+        differ(
+            "stack".into(),
+            format!("{:03x?}", self.stack()),
+            format!("{:03x?}", other.stack()),
+        );
+        differ("DT".into(), hex(self.delay_timer), hex(other.delay_timer));
+        differ("ST".into(), hex(self.sound_timer), hex(other.sound_timer));
+        differ(
+            "keypad".into(),
+            format!("{:?}", self.keypad),
+            format!("{:?}", other.keypad),
+        );
+        for addr in 0..MEM_SIZE as u16 {
+            let (a, b) = (self.peek(addr), other.peek(addr));
+            differ(format!("memory at 0x{:03x}", addr), hex(a), hex(b));
+        }
+        differ(
+            "display".into(),
+            format!("{:016x}", self.framebuffer_hash()),
+            format!("{:016x}", other.framebuffer_hash()),
+        );
+        diffs
+    }
+}
+
+// hex formats a value as hex.
+fn hex(value: impl fmt::LowerHex) -> String {
+    format!("0x{:02x}", value)
+}
@@ -0,0 +1,183 @@
+// A state is a snapshot of a machine in STATE_SIZE bytes, to save a game
+// and to come back to it: the memory, the registers, the stack, the timers,
+// the keypad, the display and the quirks. The random number generator and
+// whatever is attached to the machine, such as the tracer or the hooks, are
+// not part of it. Numbers are little-endian.
+use super::super::bus::Bus;
+use super::super::quirks::Quirks;
+use super::{Chip8, HEIGHT, MEM_SIZE, N_KEYS, N_REGISTERS, PROGRAM_START};
+use super::{STACK_DEPTH, WIDTH};
+use core::fmt;
+
+// MAGIC starts every state, with the version of the layout.
+const MAGIC: [u8; 4] = *b"C8S1";
+
+// STATE_SIZE is the size of a state.
+pub const STATE_SIZE: usize = MAGIC.len()
+    + MEM_SIZE
+    + N_REGISTERS
+    + 2 // I
+    + 2 // pc
+    + 1 // sp
+    + 2 * STACK_DEPTH
+    + 2 // The delay timer
+    + 2 // The sound timer
+    + N_KEYS
+    + WIDTH * HEIGHT / 8
+    + 1 // The quirks, a bit each
+    + 2 // The size of the ROM
+    + 1; // Whether FX0A is waiting for a key
+
+// ErrState is returned when a state cannot be saved or loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrState(pub &'static str); // What is wrong
+
+impl fmt::Display for ErrState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid state: {}", self.0)
+    }
+}
+
+// Writer writes a state into a buffer.
+struct Writer<'a> {
+    out: &'a mut [u8],
+    at: usize,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.out[self.at..self.at + bytes.len()].copy_from_slice(bytes);
+        self.at += bytes.len();
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+}
+
+// Reader reads a state from a buffer.
+struct Reader<'a> {
+    state: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> &'a [u8] {
+        let bytes = &self.state[self.at..self.at + n];
+        self.at += n;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        let bytes = self.bytes(2);
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+}
+
+impl<B: Bus> Chip8<B> {
+    // save_state writes the state of the machine to the first STATE_SIZE
+    // bytes of out.
+    pub fn save_state(&self, out: &mut [u8]) -> Result<(), ErrState> {
+        if out.len() < STATE_SIZE {
+            return Err(ErrState("the buffer is too small"));
+        }
+        let mut w = Writer { out, at: 0 };
+        w.bytes(&MAGIC);
+        for addr in 0..MEM_SIZE as u16 {
+            w.bytes(&[self.peek(addr)]);
+        }
+        w.bytes(&self.V);
+        w.u16(self.I);
+        w.u16(self.pc);
+        w.bytes(&[self.sp]);
+        for addr in &self.stack {
+            w.u16(*addr);
+        }
+        w.u16(self.delay_timer);
+        w.u16(self.sound_timer);
+        for down in &self.keypad {
+            w.bytes(&[*down as u8]);
+        }
+        let mut display = [0; WIDTH * HEIGHT / 8];
+        for (bit, on) in self.display.pixels().enumerate() {
+            display[bit / 8] |= (on as u8) << (7 - bit % 8);
+        }
+        w.bytes(&display);
+        let q = &self.quirks;
+        let quirks = [q.shift, q.load_store, q.jump, q.vf_reset, q.clip];
+        let bits = quirks.iter().enumerate();
+        w.bytes(&[bits.fold(0, |byte, (i, on)| byte | (*on as u8) << i)]);
+        w.u16(self.rom_size as u16);
+        w.bytes(&[self.waiting as u8]);
+        Ok(())
+    }
+
+    // load_state restores a state saved by save_state. The machine is left
+    // as it was when the state is invalid.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), ErrState> {
+        if state.len() < STATE_SIZE {
+            return Err(ErrState("too short"));
+        }
+        let mut r = Reader { state, at: 0 };
+        if r.bytes(MAGIC.len()) != MAGIC {
+            return Err(ErrState("not a state of this version"));
+        }
+        let memory = r.bytes(MEM_SIZE);
+        let v = r.bytes(N_REGISTERS);
+        let (i, pc, sp) = (r.u16(), r.u16(), r.u8());
+        let mut stack = [0; STACK_DEPTH];
+        for addr in stack.iter_mut() {
+            *addr = r.u16();
+        }
+        let (delay_timer, sound_timer) = (r.u16(), r.u16());
+        let keypad = r.bytes(N_KEYS);
+        let display = r.bytes(WIDTH * HEIGHT / 8);
+        let (quirks, rom_size, waiting) = (r.u8(), r.u16(), r.u8());
+        if sp as usize > STACK_DEPTH {
+            return Err(ErrState("the stack overflows"));
+        }
+        if rom_size as usize > MEM_SIZE - PROGRAM_START as usize {
+            return Err(ErrState("the ROM does not fit in memory"));
+        }
+
+        for (addr, byte) in memory.iter().enumerate() {
+            self.store(addr as u16, *byte);
+        }
+        #[cfg(feature = "std")]
+        if self.blocks.is_some() {
+            self.blocks = Some(super::Blocks::new());
+        }
+        self.V.copy_from_slice(v);
+        self.I = i;
+        self.pc = pc;
+        self.sp = sp;
+        self.stack = stack;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        for (down, byte) in self.keypad.iter_mut().zip(keypad) {
+            *down = *byte != 0;
+        }
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let bit = y * WIDTH + x;
+                let on = display[bit / 8] & 0x80 >> (bit % 8) != 0;
+                self.display.set(x, y, on);
+            }
+        }
+        let bit = |i: u8| quirks & 1 << i != 0;
+        self.quirks = Quirks {
+            shift: bit(0),
+            load_store: bit(1),
+            jump: bit(2),
+            vf_reset: bit(3),
+            clip: bit(4),
+        };
+        self.rom_size = rom_size as usize;
+        self.waiting = waiting != 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+use super::chip8::{MEM_SIZE, PROGRAM_START};
+use super::instruction::Instruction;
+use super::metadata::Access;
+use crate::analysis::Analysis;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+// HEATMAP_WIDTH is the number of bytes per row of a heatmap.
+const HEATMAP_WIDTH: usize = 32;
+
+// RAMP is used to draw execution counts in a text heatmap, coldest first.
+const RAMP: &[u8] = b".:-=+*#%@";
+
+// Coverage counts how often each memory address was executed, read and
+// written during a session.
+pub struct Coverage {
+    pub executed: Vec<u32>,
+    pub read: Vec<u32>,
+    pub written: Vec<u32>,
+}
+
+// Summary is the headline numbers of a coverage report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub reachable: usize, // Statically reachable instructions
+    pub executed: usize,  // Reachable instructions that were executed
+    pub unknown: usize,   // Executed instructions the analysis missed
+    pub percent: f64,     // Percentage of reachable instructions executed
+}
+
+impl Coverage {
+    // new constructs an empty coverage map.
+    pub fn new() -> Self {
+        Self {
+            executed: vec![0; MEM_SIZE],
+            read: vec![0; MEM_SIZE],
+            written: vec![0; MEM_SIZE],
+        }
+    }
+
+    // record records the execution of an instruction at pc, with I holding
+    // its value before the instruction ran.
+    pub fn record(&mut self, pc: u16, instr: &Instruction, i: u16) {
+        self.executed[pc as usize % MEM_SIZE] += 1;
+
+        // How many bytes the instruction touches through I
+        let len = match instr {
+            Instruction::IDXYN(_, _, n) => *n as usize,
+            Instruction::IFX33(_) => 3,
+            Instruction::IFX55(x) | Instruction::IFX65(x) => x + 1,
+            _ => 0,
+        };
+        let counts = match instr.metadata().memory {
+            Access::Read => &mut self.read,
+            Access::Write => &mut self.written,
+            Access::None => return,
+        };
+        for addr in i as usize..i as usize + len {
+            counts[addr % MEM_SIZE] += 1;
+        }
+    }
+
+    // summary compares the executed instructions of a ROM with the ones that
+    // are statically reachable.
+    pub fn summary(&self, rom: &[u8]) -> Summary {
+        let rom = loaded(rom);
+        let analysis = Analysis::new(rom);
+        let mut summary = Summary {
+            reachable: 0,
+            executed: 0,
+            unknown: 0,
+            percent: 0.0,
+        };
+        for addr in analysis.start..analysis.end {
+            let executed = self.executed[addr as usize] > 0;
+            match (analysis.is_code(addr), executed) {
+                (true, true) => {
+                    summary.reachable += 1;
+                    summary.executed += 1;
+                }
+                (true, false) => summary.reachable += 1,
+                (false, true) => summary.unknown += 1,
+                _ => (),
+            }
+        }
+        if summary.reachable > 0 {
+            summary.percent =
+                100.0 * summary.executed as f64 / summary.reachable as f64;
+        }
+        summary
+    }
+
+    // annotated returns a disassembly of a ROM with the execution count of
+    // every instruction. Lines start with '>' for executed instructions, '-'
+    // for reachable ones that never ran and ' ' for data.
+    pub fn annotated(&self, rom: &[u8]) -> String {
+        let rom = loaded(rom);
+        let analysis = Analysis::new(rom);
+        let summary = self.summary(rom);
+        let mut out = String::new();
+        writeln!(
+            out,
+            "; {}/{} reachable instructions executed ({:.1}%)",
+            summary.executed, summary.reachable, summary.percent
+        )
+        .unwrap();
+
+        let mut addr = analysis.start;
+        while addr < analysis.end {
+            let at = (addr - PROGRAM_START) as usize;
+            let executed = self.executed[addr as usize];
+            let is_code = analysis.is_code(addr) || executed > 0;
+            if is_code && at + 1 < rom.len() {
+                let opcode = (rom[at] as u16) << 8 | rom[at + 1] as u16;
+                let text = match Instruction::try_from(opcode) {
+                    Ok(instr) => instr.to_string(),
+                    Err(_) => String::from("???"),
+                };
+                let mark = if executed > 0 { '>' } else { '-' };
+                writeln!(
+                    out,
+                    "{} 0x{:03x}  {:04x}  {:<18} ; {}",
+                    mark, addr, opcode, text, executed
+                )
+                .unwrap();
+                addr += 2;
+            } else {
+                writeln!(
+                    out,
+                    "  0x{:03x}  {:02x}    {:<18} ; r{} w{}",
+                    addr,
+                    rom[at],
+                    format!("DB 0x{:02x}", rom[at]),
+                    self.read[addr as usize],
+                    self.written[addr as usize]
+                )
+                .unwrap();
+                addr += 1;
+            }
+        }
+        out
+    }
+
+    // heatmap_text draws a text heatmap of a ROM, one character per byte.
+    // Executed bytes use a ramp from '.' to '@', bytes only read or written
+    // use 'r', 'w' or 'b' (both), and untouched bytes are blank.
+    pub fn heatmap_text(&self, rom: &[u8]) -> String {
+        let rom = loaded(rom);
+        let max = self.max_executed(rom);
+        let mut out = String::new();
+        for (row, chunk) in rom.chunks(HEATMAP_WIDTH).enumerate() {
+            let start = PROGRAM_START as usize + row * HEATMAP_WIDTH;
+            write!(out, "0x{:03x} |", start).unwrap();
+            for addr in start..start + chunk.len() {
+                let c = match self.heat(addr) {
+                    0 => match (self.read[addr], self.written[addr]) {
+                        (0, 0) => ' ',
+                        (_, 0) => 'r',
+                        (0, _) => 'w',
+                        _ => 'b',
+                    },
+                    n => RAMP[scale(n, max, RAMP.len())] as char,
+                };
+                out.push(c);
+            }
+            out.push_str("|\n");
+        }
+        out
+    }
+
+    // heatmap_html draws a heatmap of a ROM as a standalone HTML page. Code is
+    // shaded red by execution count, data blue for reads and green for writes.
+    pub fn heatmap_html(&self, rom: &[u8]) -> String {
+        let rom = loaded(rom);
+        let max = self.max_executed(rom);
+        let summary = self.summary(rom);
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">");
+        out.push_str("<title>CHIP-8 coverage</title><style>");
+        out.push_str("body{font-family:monospace}td{width:1.6em;");
+        out.push_str(
+            "text-align:center;font-size:10px}</style></head><body>\n",
+        );
+        writeln!(
+            out,
+            "<p>{}/{} reachable instructions executed ({:.1}%)</p>",
+            summary.executed, summary.reachable, summary.percent
+        )
+        .unwrap();
+        out.push_str("<table>\n");
+        for (row, chunk) in rom.chunks(HEATMAP_WIDTH).enumerate() {
+            let start = PROGRAM_START as usize + row * HEATMAP_WIDTH;
+            write!(out, "<tr><th>0x{:03x}</th>", start).unwrap();
+            for (addr, byte) in (start..).zip(chunk) {
+                let color = match self.heat(addr) {
+                    0 => match (self.read[addr], self.written[addr]) {
+                        (0, 0) => String::from("#fff"),
+                        (_, 0) => String::from("#cce0ff"),
+                        _ => String::from("#ccf5cc"),
+                    },
+                    n => {
+                        let heat = scale(n, max, 200) as u8;
+                        format!("rgb(255,{},{})", 220 - heat, 220 - heat)
+                    }
+                };
+                write!(
+                    out,
+                    "<td style=\"background:{}\" title=\"0x{:03x}: x{} r{} w{}\">{:02x}</td>",
+                    color,
+                    addr,
+                    self.executed[addr],
+                    self.read[addr],
+                    self.written[addr],
+                    byte
+                )
+                .unwrap();
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table></body></html>\n");
+        out
+    }
+
+    // heat returns the execution count of the instruction a byte belongs to,
+    // counting both of its bytes.
+    fn heat(&self, addr: usize) -> u32 {
+        match addr {
+            0 => self.executed[0],
+            _ => self.executed[addr].max(self.executed[addr - 1]),
+        }
+    }
+
+    // max_executed returns the highest execution count within a ROM.
+    fn max_executed(&self, rom: &[u8]) -> u32 {
+        let start = PROGRAM_START as usize;
+        let end = start + loaded(rom).len();
+        self.executed[start..end].iter().cloned().max().unwrap_or(0)
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// loaded returns the part of a ROM that fits in memory. The rest is never
+// loaded, so it has no coverage.
+fn loaded(rom: &[u8]) -> &[u8] {
+    &rom[..rom.len().min(MEM_SIZE - PROGRAM_START as usize)]
+}
+
+// scale maps a count in [1, max] onto [0, steps) logarithmically.
+fn scale(n: u32, max: u32, steps: usize) -> usize {
+    if max <= 1 {
+        return steps - 1;
+    }
+    let t = (n as f64).ln() / (max as f64).ln();
+    ((t * (steps - 1) as f64).round() as usize).min(steps - 1)
+}
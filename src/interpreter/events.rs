@@ -0,0 +1,105 @@
+use super::chip8::Fault;
+use super::instruction::Instruction;
+#[cfg(feature = "std")]
+use core::ops::Range;
+
+// Event is something the machine did that an embedder may react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    // An instruction ran without faulting
+    Executed {
+        pc: u16,
+        instruction: Instruction,
+    },
+    // The timers counted down at the end of a frame
+    FrameCompleted,
+    // 00E0 cleared the display
+    DisplayCleared,
+    // DXYN drew a sprite of the given number of rows at (x, y)
+    SpriteDrawn {
+        x: u8,
+        y: u8,
+        rows: u8,
+        collision: bool,
+    },
+    // The sound timer was set while silent
+    SoundStarted,
+    // The sound timer ran out, or was set to zero
+    SoundStopped,
+    // FX0A found no key down and started waiting for one
+    WaitingForKey {
+        register: usize,
+    },
+    // The program wrote a byte in a watched range
+    MemoryWritten {
+        addr: u16,
+        byte: u8,
+    },
+    // The machine stopped
+    Faulted(Fault),
+}
+
+// HookId identifies a hook, to remove it.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookId(usize);
+
+// Hook is a callback for the events of a machine.
+#[cfg(feature = "std")]
+type Hook = Box<dyn FnMut(&Event)>;
+
+// Hooks are the hooks of a machine and the ranges of memory it watches.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<(HookId, Hook)>,
+    watched: Vec<Range<u16>>, // Where writes raise MemoryWritten
+    next: usize,              // The ID of the next hook
+}
+
+#[cfg(feature = "std")]
+impl Hooks {
+    // add adds a hook and returns its ID.
+    pub(super) fn add(&mut self, hook: Hook) -> HookId {
+        let id = HookId(self.next);
+        self.next += 1;
+        self.hooks.push((id, hook));
+        id
+    }
+
+    // remove removes a hook and returns whether it was there.
+    pub(super) fn remove(&mut self, id: HookId) -> bool {
+        let len = self.hooks.len();
+        self.hooks.retain(|(hook, _)| *hook != id);
+        self.hooks.len() != len
+    }
+
+    // watch makes writes to a range of memory raise MemoryWritten.
+    pub(super) fn watch(&mut self, range: Range<u16>) {
+        self.watched.push(range);
+    }
+
+    // unwatch stops watching every range.
+    pub(super) fn unwatch(&mut self) {
+        self.watched.clear();
+    }
+
+    // is_empty returns whether there are no hooks, so that events need not
+    // be made.
+    #[inline]
+    pub(super) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    // watches returns whether writes to an address raise MemoryWritten.
+    pub(super) fn watches(&self, addr: u16) -> bool {
+        self.watched.iter().any(|range| range.contains(&addr))
+    }
+
+    // emit calls every hook with an event, in the order they were added.
+    pub(super) fn emit(&mut self, event: Event) {
+        for (_, hook) in self.hooks.iter_mut() {
+            hook(&event);
+        }
+    }
+}
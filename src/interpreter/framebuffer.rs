@@ -0,0 +1,159 @@
+use core::fmt;
+use core::ops::{BitAnd, BitXor};
+
+// Row is a row of pixels packed into an integer, the leftmost pixel in the
+// most significant bit.
+pub trait Row:
+    Copy + Eq + fmt::Debug + BitAnd<Output = Self> + BitXor<Output = Self>
+{
+    // WIDTH is the number of pixels in a row.
+    const WIDTH: usize;
+
+    // EMPTY is a row with every pixel off.
+    const EMPTY: Self;
+
+    // sprite returns a row with the 8 pixels of a sprite byte starting at
+    // column x. Pixels past the right edge are cut off, or wrap around to
+    // the left edge.
+    fn sprite(byte: u8, x: usize, wrap: bool) -> Self;
+
+    // pixel returns whether the pixel in column x is on.
+    fn pixel(self, x: usize) -> bool;
+
+    // toggle flips the pixel in column x.
+    fn toggle(self, x: usize) -> Self;
+}
+
+macro_rules! row {
+    ($t:ty) => {
+        impl Row for $t {
+            const WIDTH: usize = <$t>::BITS as usize;
+            const EMPTY: Self = 0;
+
+            fn sprite(byte: u8, x: usize, wrap: bool) -> Self {
+                let bits = (byte as $t) << (Self::WIDTH - 8);
+                if wrap {
+                    bits.rotate_right(x as u32)
+                } else {
+                    bits.checked_shr(x as u32).unwrap_or(0)
+                }
+            }
+
+            fn pixel(self, x: usize) -> bool {
+                (self >> (Self::WIDTH - 1 - x)) & 1 == 1
+            }
+
+            fn toggle(self, x: usize) -> Self {
+                self ^ (1 << (Self::WIDTH - 1 - x))
+            }
+        }
+    };
+}
+
+row!(u64);
+row!(u128);
+
+// Edge is what happens to the parts of a sprite past the edges of the
+// display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Clip, // They are not drawn
+    Wrap, // They are drawn on the opposite side
+}
+
+// Framebuffer is a monochrome display of H rows packed as integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Framebuffer<R: Row, const H: usize> {
+    rows: [R; H],
+}
+
+// LoRes is the 64x32 display of CHIP-8.
+pub type LoRes = Framebuffer<u64, 32>;
+
+// HiRes is the 128x64 display of SUPER-CHIP.
+pub type HiRes = Framebuffer<u128, 64>;
+
+impl<R: Row, const H: usize> Framebuffer<R, H> {
+    // WIDTH is the number of pixels in a row.
+    pub const WIDTH: usize = R::WIDTH;
+
+    // HEIGHT is the number of rows.
+    pub const HEIGHT: usize = H;
+
+    pub fn new() -> Self {
+        Self {
+            rows: [R::EMPTY; H],
+        }
+    }
+
+    // clear turns every pixel off.
+    pub fn clear(&mut self) {
+        self.rows = [R::EMPTY; H];
+    }
+
+    // rows returns the packed rows, top to bottom.
+    pub fn rows(&self) -> &[R] {
+        &self.rows
+    }
+
+    // pixel returns whether the pixel at (x, y) is on. Pixels outside the
+    // display are off.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        x < R::WIDTH && y < H && self.rows[y].pixel(x)
+    }
+
+    // set turns the pixel at (x, y) on or off. Pixels outside the display
+    // are ignored.
+    pub fn set(&mut self, x: usize, y: usize, on: bool) {
+        if x < R::WIDTH && y < H && self.rows[y].pixel(x) != on {
+            self.rows[y] = self.rows[y].toggle(x);
+        }
+    }
+
+    // blit XORs a sprite onto the display with its top left corner at
+    // (x, y), one byte per row, and returns whether any pixel was turned
+    // off. The corner itself always wraps onto the display.
+    pub fn blit(
+        &mut self,
+        x: usize,
+        y: usize,
+        sprite: impl IntoIterator<Item = u8>,
+        edge: Edge,
+    ) -> bool {
+        let (x, y) = (x % R::WIDTH, y % H);
+        let mut collision = false;
+        for (i, byte) in sprite.into_iter().enumerate() {
+            let row = match (y + i, edge) {
+                (row, _) if row < H => row,
+                (_, Edge::Clip) => break,
+                (row, Edge::Wrap) => row % H,
+            };
+            let bits = R::sprite(byte, x, edge == Edge::Wrap);
+            collision |= self.rows[row] & bits != R::EMPTY;
+            self.rows[row] = self.rows[row] ^ bits;
+        }
+        collision
+    }
+
+    // pixels returns whether every pixel is on, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = bool> + '_ {
+        self.rows
+            .iter()
+            .flat_map(|row| (0..R::WIDTH).map(move |x| row.pixel(x)))
+    }
+
+    // lit returns the coordinates of the pixels that are on, row by row.
+    pub fn lit(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            (0..R::WIDTH)
+                .filter(move |x| row.pixel(*x))
+                .map(move |x| (x, y))
+        })
+    }
+}
+
+impl<R: Row, const H: usize> Default for Framebuffer<R, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
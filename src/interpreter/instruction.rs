@@ -1,4 +1,4 @@
-use std::fmt::{Display, Formatter, Result};
+use core::convert::TryFrom;
 
 type Addr = u16;
 type Vx = usize;
@@ -8,16 +8,18 @@ type Nib = u8;
 
 // lsb = least significant bit
 
-// ErrUnsupportedInstruction is thrown when an unsupported instruction was used.
-pub struct ErrUnsupportedInstruction;
+// ErrUnsupportedInstruction is returned when an opcode does not decode to any
+// known instruction. It carries the offending opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrUnsupportedInstruction(pub u16);
 
-impl std::fmt::Display for ErrUnsupportedInstruction {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "that's an unsupported instruction")
+impl core::fmt::Display for ErrUnsupportedInstruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "0x{:04x} is an unsupported instruction", self.0)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     I0NNN(Addr),        // 0NNN
     I00E0,              // 00E0
@@ -54,66 +56,121 @@ pub enum Instruction {
     IFX33(Vx),          // FX33
     IFX55(Vx),          // FX55
     IFX65(Vx),          // FX65
-
-    UNSUPPORTED, // Represents an unsupported instruction
 }
 
-impl Instruction {
-    pub fn get_instr_from_parts(
-        opcode: u16,
-        jmp: u16,
-        x: usize,
-        y: usize,
-        nn: u16,
-    ) -> Self {
-        return match opcode & 0xF000 {
+impl TryFrom<u16> for Instruction {
+    type Error = ErrUnsupportedInstruction;
+
+    // try_from decodes a raw opcode. Every opcode that decodes successfully
+    // encodes back to exactly the same value.
+    fn try_from(opcode: u16) -> core::result::Result<Self, Self::Error> {
+        let nnn: Addr = opcode & 0x0FFF;
+        let x: Vx = ((opcode & 0x0F00) >> 8).into();
+        let y: Vy = ((opcode & 0x00F0) >> 4).into();
+        let nn: Byte = (opcode & 0x00FF) as u8;
+        let n: Nib = (opcode & 0x000F) as u8;
+
+        let instr = match opcode & 0xF000 {
             0x0000 => match opcode {
                 0x00E0 => Instruction::I00E0,
                 0x00EE => Instruction::I00EE,
-                _ => Instruction::UNSUPPORTED,
+                _ => Instruction::I0NNN(nnn),
             },
-            0x1000 => Instruction::I1NNN(jmp),
-            0x2000 => Instruction::I2NNN(jmp),
-            0x3000 => Instruction::I3XNN(x, nn as u8),
-            0x4000 => Instruction::I4XNN(x, nn as u8),
-            0x5000 => Instruction::I5XY0(x, y),
-            0x6000 => Instruction::I6XNN(x, nn as u8),
-            0x7000 => Instruction::I7XNN(x, nn as u8),
-            0x8000 => match opcode & 0x000F {
-                0x0000 => Instruction::I8XY0(x, y),
-                0x0001 => Instruction::I8XY1(x, y),
-                0x0002 => Instruction::I8XY2(x, y),
-                0x0003 => Instruction::I8XY3(x, y),
-                0x0004 => Instruction::I8XY4(x, y),
-                0x0005 => Instruction::I8XY5(x, y),
-                0x0006 => Instruction::I8XY6(x, y),
-                0x0007 => Instruction::I8XY7(x, y),
-                0x000E => Instruction::I8XYE(x, y),
-                _ => Instruction::UNSUPPORTED,
+            0x1000 => Instruction::I1NNN(nnn),
+            0x2000 => Instruction::I2NNN(nnn),
+            0x3000 => Instruction::I3XNN(x, nn),
+            0x4000 => Instruction::I4XNN(x, nn),
+            0x5000 if n == 0 => Instruction::I5XY0(x, y),
+            0x6000 => Instruction::I6XNN(x, nn),
+            0x7000 => Instruction::I7XNN(x, nn),
+            0x8000 => match n {
+                0x0 => Instruction::I8XY0(x, y),
+                0x1 => Instruction::I8XY1(x, y),
+                0x2 => Instruction::I8XY2(x, y),
+                0x3 => Instruction::I8XY3(x, y),
+                0x4 => Instruction::I8XY4(x, y),
+                0x5 => Instruction::I8XY5(x, y),
+                0x6 => Instruction::I8XY6(x, y),
+                0x7 => Instruction::I8XY7(x, y),
+                0xE => Instruction::I8XYE(x, y),
+                _ => return Err(ErrUnsupportedInstruction(opcode)),
             },
-            0x9000 => Instruction::I9XY0(x, y),
-            0xA000 => Instruction::IANNN(jmp),
-            0xB000 => Instruction::IBNNN(jmp),
-            0xC000 => Instruction::ICXNN(x, nn as u8),
-            0xD000 => Instruction::IDXYN(x, y, (opcode & 0x000F) as u8),
-            0xE000 => match opcode & 0x000F {
-                0x000E => Instruction::IEX9E(x),
-                0x0001 => Instruction::IEXA1(x),
-                _ => Instruction::UNSUPPORTED,
+            0x9000 if n == 0 => Instruction::I9XY0(x, y),
+            0xA000 => Instruction::IANNN(nnn),
+            0xB000 => Instruction::IBNNN(nnn),
+            0xC000 => Instruction::ICXNN(x, nn),
+            0xD000 => Instruction::IDXYN(x, y, n),
+            0xE000 => match nn {
+                0x9E => Instruction::IEX9E(x),
+                0xA1 => Instruction::IEXA1(x),
+                _ => return Err(ErrUnsupportedInstruction(opcode)),
             },
-            0xF000 => match opcode & 0x00FF {
-                0x0007 => Instruction::IFX07(x),
-                0x000A => Instruction::IFX0A(x),
-                0x0015 => Instruction::IFX15(x),
-                0x0018 => Instruction::IFX18(x),
-                0x001E => Instruction::IFX1E(x),
-                0x0029 => Instruction::IFX29(x),
-                0x0033 => Instruction::IFX33(x),
-                0x0055 => Instruction::IFX55(x),
-                0x0065 => Instruction::IFX65(x),
-                _ => Instruction::UNSUPPORTED,
+            0xF000 => match nn {
+                0x07 => Instruction::IFX07(x),
+                0x0A => Instruction::IFX0A(x),
+                0x15 => Instruction::IFX15(x),
+                0x18 => Instruction::IFX18(x),
+                0x1E => Instruction::IFX1E(x),
+                0x29 => Instruction::IFX29(x),
+                0x33 => Instruction::IFX33(x),
+                0x55 => Instruction::IFX55(x),
+                0x65 => Instruction::IFX65(x),
+                _ => return Err(ErrUnsupportedInstruction(opcode)),
             },
-            _ => Instruction::UNSUPPORTED,
+            _ => return Err(ErrUnsupportedInstruction(opcode)),
         };
+        Ok(instr)
+    }
+}
+
+impl Instruction {
+    // encode turns the instruction back into its raw opcode. Operands that do
+    // not fit in their field are truncated to the field width.
+    pub fn encode(&self) -> u16 {
+        // Helpers to place each operand in its field
+        let a = |a: Addr| a & 0x0FFF;
+        let x = |x: Vx| ((x as u16) & 0xF) << 8;
+        let y = |y: Vy| ((y as u16) & 0xF) << 4;
+        let b = |b: Byte| b as u16;
+
+        match *self {
+            Instruction::I0NNN(n) => a(n),
+            Instruction::I00E0 => 0x00E0,
+            Instruction::I00EE => 0x00EE,
+            Instruction::I1NNN(n) => 0x1000 | a(n),
+            Instruction::I2NNN(n) => 0x2000 | a(n),
+            Instruction::I3XNN(vx, nn) => 0x3000 | x(vx) | b(nn),
+            Instruction::I4XNN(vx, nn) => 0x4000 | x(vx) | b(nn),
+            Instruction::I5XY0(vx, vy) => 0x5000 | x(vx) | y(vy),
+            Instruction::I6XNN(vx, nn) => 0x6000 | x(vx) | b(nn),
+            Instruction::I7XNN(vx, nn) => 0x7000 | x(vx) | b(nn),
+            Instruction::I8XY0(vx, vy) => 0x8000 | x(vx) | y(vy),
+            Instruction::I8XY1(vx, vy) => 0x8001 | x(vx) | y(vy),
+            Instruction::I8XY2(vx, vy) => 0x8002 | x(vx) | y(vy),
+            Instruction::I8XY3(vx, vy) => 0x8003 | x(vx) | y(vy),
+            Instruction::I8XY4(vx, vy) => 0x8004 | x(vx) | y(vy),
+            Instruction::I8XY5(vx, vy) => 0x8005 | x(vx) | y(vy),
+            Instruction::I8XY6(vx, vy) => 0x8006 | x(vx) | y(vy),
+            Instruction::I8XY7(vx, vy) => 0x8007 | x(vx) | y(vy),
+            Instruction::I8XYE(vx, vy) => 0x800E | x(vx) | y(vy),
+            Instruction::I9XY0(vx, vy) => 0x9000 | x(vx) | y(vy),
+            Instruction::IANNN(n) => 0xA000 | a(n),
+            Instruction::IBNNN(n) => 0xB000 | a(n),
+            Instruction::ICXNN(vx, nn) => 0xC000 | x(vx) | b(nn),
+            Instruction::IDXYN(vx, vy, n) => {
+                0xD000 | x(vx) | y(vy) | (n as u16 & 0xF)
+            }
+            Instruction::IEX9E(vx) => 0xE09E | x(vx),
+            Instruction::IEXA1(vx) => 0xE0A1 | x(vx),
+            Instruction::IFX07(vx) => 0xF007 | x(vx),
+            Instruction::IFX0A(vx) => 0xF00A | x(vx),
+            Instruction::IFX15(vx) => 0xF015 | x(vx),
+            Instruction::IFX18(vx) => 0xF018 | x(vx),
+            Instruction::IFX1E(vx) => 0xF01E | x(vx),
+            Instruction::IFX29(vx) => 0xF029 | x(vx),
+            Instruction::IFX33(vx) => 0xF033 | x(vx),
+            Instruction::IFX55(vx) => 0xF055 | x(vx),
+            Instruction::IFX65(vx) => 0xF065 | x(vx),
+        }
     }
 }
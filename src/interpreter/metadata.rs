@@ -0,0 +1,293 @@
+use super::instruction::Instruction;
+use super::quirks::Quirks;
+use core::fmt;
+
+// Operand is the kind of a single operand, in the order it is written in
+// assembly. Fixed operands such as I or DT carry no value of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Addr,      // NNN
+    Vx,        // The X register
+    Vy,        // The Y register
+    V0,        // The V0 register
+    Byte,      // NN
+    Nib,       // N
+    I,         // The I register
+    IndirectI, // The memory pointed to by I
+    Delay,     // The delay timer
+    Sound,     // The sound timer
+    Key,       // A key press
+    Font,      // The font sprite of a digit
+    Bcd,       // The BCD representation of a number
+}
+
+// Reg is a register, relative to the operands of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    X,     // The X register
+    Y,     // The Y register
+    V0,    // The V0 register
+    F,     // The flag register
+    UpToX, // V0 through the X register
+    I,     // The I register
+    Delay, // The delay timer
+    Sound, // The sound timer
+}
+
+// Access is how an instruction touches memory through I.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    None,
+    Read,
+    Write,
+}
+
+// Flow is how an instruction affects the program counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Next,         // Falls through to the next instruction
+    Skip,         // May skip the next instruction
+    Jump,         // Jumps to NNN
+    JumpIndirect, // Jumps to NNN + V0
+    Call,         // Calls the subroutine at NNN
+    Return,       // Returns from a subroutine
+    Wait,         // Blocks until a key is pressed
+}
+
+impl Flow {
+    // branches returns whether the instruction may not fall through to the
+    // next instruction.
+    pub fn branches(&self) -> bool {
+        *self != Flow::Next && *self != Flow::Wait
+    }
+}
+
+// Metadata describes a single instruction variant. The registers and memory
+// accesses are those with every quirk off, see metadata_with.
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub opcode: &'static str, // The opcode pattern, e.g. "8XY4"
+    pub mnemonic: &'static str,
+    pub operands: &'static [Operand],
+    pub reads: &'static [Reg],
+    pub writes: &'static [Reg],
+    pub memory: Access,
+    pub flow: Flow,
+    pub micros: u32, // Nominal COSMAC VIP duration, in microseconds
+    pub cycles: u32, // Nominal COSMAC VIP cost, in machine cycles
+}
+
+// RegSet is a set of concrete registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegSet {
+    pub v: u16, // One bit per V register
+    pub i: bool,
+    pub delay: bool,
+    pub sound: bool,
+}
+
+impl RegSet {
+    // has_v returns whether the set contains the V register r.
+    pub fn has_v(&self, r: usize) -> bool {
+        self.v & (1 << r) != 0
+    }
+}
+
+use Operand as O;
+
+// METADATA holds one entry per instruction variant, in declaration order.
+#[rustfmt::skip]
+pub const METADATA: [Metadata; 35] = [
+    Metadata { opcode: "0NNN", mnemonic: "SYS",  operands: &[O::Addr],              reads: &[],                     writes: &[],                     memory: Access::None,  flow: Flow::Next,         micros: 0,    cycles: 0 },
+    Metadata { opcode: "00E0", mnemonic: "CLS",  operands: &[],                     reads: &[],                     writes: &[],                     memory: Access::None,  flow: Flow::Next,         micros: 109,  cycles: 24 },
+    Metadata { opcode: "00EE", mnemonic: "RET",  operands: &[],                     reads: &[],                     writes: &[],                     memory: Access::None,  flow: Flow::Return,       micros: 105,  cycles: 23 },
+    Metadata { opcode: "1NNN", mnemonic: "JP",   operands: &[O::Addr],              reads: &[],                     writes: &[],                     memory: Access::None,  flow: Flow::Jump,         micros: 105,  cycles: 23 },
+    Metadata { opcode: "2NNN", mnemonic: "CALL", operands: &[O::Addr],              reads: &[],                     writes: &[],                     memory: Access::None,  flow: Flow::Call,         micros: 105,  cycles: 23 },
+    Metadata { opcode: "3XNN", mnemonic: "SE",   operands: &[O::Vx, O::Byte],       reads: &[Reg::X],               writes: &[],                     memory: Access::None,  flow: Flow::Skip,         micros: 55,   cycles: 12 },
+    Metadata { opcode: "4XNN", mnemonic: "SNE",  operands: &[O::Vx, O::Byte],       reads: &[Reg::X],               writes: &[],                     memory: Access::None,  flow: Flow::Skip,         micros: 55,   cycles: 12 },
+    Metadata { opcode: "5XY0", mnemonic: "SE",   operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[],                     memory: Access::None,  flow: Flow::Skip,         micros: 73,   cycles: 16 },
+    Metadata { opcode: "6XNN", mnemonic: "LD",   operands: &[O::Vx, O::Byte],       reads: &[],                     writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 27,   cycles: 6 },
+    Metadata { opcode: "7XNN", mnemonic: "ADD",  operands: &[O::Vx, O::Byte],       reads: &[Reg::X],               writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 45,   cycles: 10 },
+    Metadata { opcode: "8XY0", mnemonic: "LD",   operands: &[O::Vx, O::Vy],         reads: &[Reg::Y],               writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY1", mnemonic: "OR",   operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY2", mnemonic: "AND",  operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY3", mnemonic: "XOR",  operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY4", mnemonic: "ADD",  operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[Reg::X, Reg::F],       memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY5", mnemonic: "SUB",  operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[Reg::X, Reg::F],       memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY6", mnemonic: "SHR",  operands: &[O::Vx, O::Vy],         reads: &[Reg::Y],               writes: &[Reg::X, Reg::F],       memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XY7", mnemonic: "SUBN", operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[Reg::X, Reg::F],       memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "8XYE", mnemonic: "SHL",  operands: &[O::Vx, O::Vy],         reads: &[Reg::Y],               writes: &[Reg::X, Reg::F],       memory: Access::None,  flow: Flow::Next,         micros: 200,  cycles: 44 },
+    Metadata { opcode: "9XY0", mnemonic: "SNE",  operands: &[O::Vx, O::Vy],         reads: &[Reg::X, Reg::Y],       writes: &[],                     memory: Access::None,  flow: Flow::Skip,         micros: 73,   cycles: 16 },
+    Metadata { opcode: "ANNN", mnemonic: "LD",   operands: &[O::I, O::Addr],        reads: &[],                     writes: &[Reg::I],               memory: Access::None,  flow: Flow::Next,         micros: 55,   cycles: 12 },
+    Metadata { opcode: "BNNN", mnemonic: "JP",   operands: &[O::V0, O::Addr],       reads: &[Reg::V0],              writes: &[],                     memory: Access::None,  flow: Flow::JumpIndirect, micros: 105,  cycles: 23 },
+    Metadata { opcode: "CXNN", mnemonic: "RND",  operands: &[O::Vx, O::Byte],       reads: &[],                     writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 164,  cycles: 36 },
+    Metadata { opcode: "DXYN", mnemonic: "DRW",  operands: &[O::Vx, O::Vy, O::Nib], reads: &[Reg::X, Reg::Y, Reg::I], writes: &[Reg::F],             memory: Access::Read,  flow: Flow::Next,         micros: 22734, cycles: 5004 },
+    Metadata { opcode: "EX9E", mnemonic: "SKP",  operands: &[O::Vx],                reads: &[Reg::X],               writes: &[],                     memory: Access::None,  flow: Flow::Skip,         micros: 73,   cycles: 16 },
+    Metadata { opcode: "EXA1", mnemonic: "SKNP", operands: &[O::Vx],                reads: &[Reg::X],               writes: &[],                     memory: Access::None,  flow: Flow::Skip,         micros: 73,   cycles: 16 },
+    Metadata { opcode: "FX07", mnemonic: "LD",   operands: &[O::Vx, O::Delay],      reads: &[Reg::Delay],           writes: &[Reg::X],               memory: Access::None,  flow: Flow::Next,         micros: 45,   cycles: 10 },
+    Metadata { opcode: "FX0A", mnemonic: "LD",   operands: &[O::Vx, O::Key],        reads: &[],                     writes: &[Reg::X],               memory: Access::None,  flow: Flow::Wait,         micros: 0,    cycles: 0 },
+    Metadata { opcode: "FX15", mnemonic: "LD",   operands: &[O::Delay, O::Vx],      reads: &[Reg::X],               writes: &[Reg::Delay],           memory: Access::None,  flow: Flow::Next,         micros: 45,   cycles: 10 },
+    Metadata { opcode: "FX18", mnemonic: "LD",   operands: &[O::Sound, O::Vx],      reads: &[Reg::X],               writes: &[Reg::Sound],           memory: Access::None,  flow: Flow::Next,         micros: 45,   cycles: 10 },
+    Metadata { opcode: "FX1E", mnemonic: "ADD",  operands: &[O::I, O::Vx],          reads: &[Reg::X, Reg::I],       writes: &[Reg::I],               memory: Access::None,  flow: Flow::Next,         micros: 86,   cycles: 19 },
+    Metadata { opcode: "FX29", mnemonic: "LD",   operands: &[O::Font, O::Vx],       reads: &[Reg::X],               writes: &[Reg::I],               memory: Access::None,  flow: Flow::Next,         micros: 91,   cycles: 20 },
+    Metadata { opcode: "FX33", mnemonic: "LD",   operands: &[O::Bcd, O::Vx],        reads: &[Reg::X, Reg::I],       writes: &[],                     memory: Access::Write, flow: Flow::Next,         micros: 927,  cycles: 204 },
+    Metadata { opcode: "FX55", mnemonic: "LD",   operands: &[O::IndirectI, O::Vx],  reads: &[Reg::UpToX, Reg::I],   writes: &[Reg::I],               memory: Access::Write, flow: Flow::Next,         micros: 605,  cycles: 133 },
+    Metadata { opcode: "FX65", mnemonic: "LD",   operands: &[O::Vx, O::IndirectI],  reads: &[Reg::I],               writes: &[Reg::UpToX, Reg::I],   memory: Access::Read,  flow: Flow::Next,         micros: 605,  cycles: 133 },
+];
+
+impl Instruction {
+    // id returns the index of the instruction variant in METADATA.
+    pub fn id(&self) -> usize {
+        match self {
+            Instruction::I0NNN(_) => 0,
+            Instruction::I00E0 => 1,
+            Instruction::I00EE => 2,
+            Instruction::I1NNN(_) => 3,
+            Instruction::I2NNN(_) => 4,
+            Instruction::I3XNN(..) => 5,
+            Instruction::I4XNN(..) => 6,
+            Instruction::I5XY0(..) => 7,
+            Instruction::I6XNN(..) => 8,
+            Instruction::I7XNN(..) => 9,
+            Instruction::I8XY0(..) => 10,
+            Instruction::I8XY1(..) => 11,
+            Instruction::I8XY2(..) => 12,
+            Instruction::I8XY3(..) => 13,
+            Instruction::I8XY4(..) => 14,
+            Instruction::I8XY5(..) => 15,
+            Instruction::I8XY6(..) => 16,
+            Instruction::I8XY7(..) => 17,
+            Instruction::I8XYE(..) => 18,
+            Instruction::I9XY0(..) => 19,
+            Instruction::IANNN(_) => 20,
+            Instruction::IBNNN(_) => 21,
+            Instruction::ICXNN(..) => 22,
+            Instruction::IDXYN(..) => 23,
+            Instruction::IEX9E(_) => 24,
+            Instruction::IEXA1(_) => 25,
+            Instruction::IFX07(_) => 26,
+            Instruction::IFX0A(_) => 27,
+            Instruction::IFX15(_) => 28,
+            Instruction::IFX18(_) => 29,
+            Instruction::IFX1E(_) => 30,
+            Instruction::IFX29(_) => 31,
+            Instruction::IFX33(_) => 32,
+            Instruction::IFX55(_) => 33,
+            Instruction::IFX65(_) => 34,
+        }
+    }
+
+    // metadata returns the metadata of the instruction variant.
+    pub fn metadata(&self) -> &'static Metadata {
+        &METADATA[self.id()]
+    }
+
+    // metadata_with returns the metadata of the instruction variant under the
+    // given quirks, which change the registers some instructions use.
+    pub fn metadata_with(&self, quirks: &Quirks) -> Metadata {
+        let mut meta = *self.metadata();
+        match self {
+            Instruction::I8XY6(..) | Instruction::I8XYE(..) if quirks.shift => {
+                meta.reads = &[Reg::X]
+            }
+            Instruction::I8XY1(..)
+            | Instruction::I8XY2(..)
+            | Instruction::I8XY3(..)
+                if quirks.vf_reset =>
+            {
+                meta.writes = &[Reg::X, Reg::F]
+            }
+            Instruction::IBNNN(_) if quirks.jump => meta.reads = &[Reg::X],
+            Instruction::IFX55(_) if quirks.load_store => meta.writes = &[],
+            Instruction::IFX65(_) if quirks.load_store => {
+                meta.writes = &[Reg::UpToX]
+            }
+            _ => {}
+        }
+        meta
+    }
+
+    // The operand fields, pulled back out of the encoded instruction.
+    pub fn addr(&self) -> u16 {
+        self.encode() & 0x0FFF
+    }
+    pub fn x(&self) -> usize {
+        ((self.encode() & 0x0F00) >> 8) as usize
+    }
+    pub fn y(&self) -> usize {
+        ((self.encode() & 0x00F0) >> 4) as usize
+    }
+    pub fn byte(&self) -> u8 {
+        (self.encode() & 0x00FF) as u8
+    }
+    pub fn nib(&self) -> u8 {
+        (self.encode() & 0x000F) as u8
+    }
+
+    // reads returns the concrete registers read by the instruction.
+    pub fn reads(&self) -> RegSet {
+        self.resolve(self.metadata().reads)
+    }
+
+    // writes returns the concrete registers written by the instruction.
+    pub fn writes(&self) -> RegSet {
+        self.resolve(self.metadata().writes)
+    }
+
+    // reads_with returns the concrete registers read by the instruction under
+    // the given quirks.
+    pub fn reads_with(&self, quirks: &Quirks) -> RegSet {
+        self.resolve(self.metadata_with(quirks).reads)
+    }
+
+    // writes_with returns the concrete registers written by the instruction
+    // under the given quirks.
+    pub fn writes_with(&self, quirks: &Quirks) -> RegSet {
+        self.resolve(self.metadata_with(quirks).writes)
+    }
+
+    // resolve turns operand-relative registers into concrete ones.
+    fn resolve(&self, regs: &[Reg]) -> RegSet {
+        let mut set = RegSet::default();
+        for reg in regs {
+            match reg {
+                Reg::X => set.v |= 1 << self.x(),
+                Reg::Y => set.v |= 1 << self.y(),
+                Reg::V0 => set.v |= 1,
+                Reg::F => set.v |= 1 << 0xF,
+                Reg::UpToX => set.v |= ((1u32 << (self.x() + 1)) - 1) as u16,
+                Reg::I => set.i = true,
+                Reg::Delay => set.delay = true,
+                Reg::Sound => set.sound = true,
+            }
+        }
+        set
+    }
+}
+
+// Instructions are displayed as assembly, e.g. "LD V3, 0x02".
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let meta = self.metadata();
+        write!(f, "{}", meta.mnemonic)?;
+        for (i, operand) in meta.operands.iter().enumerate() {
+            write!(f, "{}", if i == 0 { " " } else { ", " })?;
+            match operand {
+                Operand::Addr => write!(f, "0x{:03x}", self.addr())?,
+                Operand::Vx => write!(f, "V{:X}", self.x())?,
+                Operand::Vy => write!(f, "V{:X}", self.y())?,
+                Operand::V0 => write!(f, "V0")?,
+                Operand::Byte => write!(f, "0x{:02x}", self.byte())?,
+                Operand::Nib => write!(f, "{}", self.nib())?,
+                Operand::I => write!(f, "I")?,
+                Operand::IndirectI => write!(f, "[I]")?,
+                Operand::Delay => write!(f, "DT")?,
+                Operand::Sound => write!(f, "ST")?,
+                Operand::Key => write!(f, "K")?,
+                Operand::Font => write!(f, "F")?,
+                Operand::Bcd => write!(f, "B")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,14 @@
+pub mod bus;
 pub mod chip8;
+#[cfg(feature = "std")]
+pub mod coverage;
+pub mod events;
+pub mod framebuffer;
 pub mod instruction;
+pub mod metadata;
+#[cfg(feature = "std")]
+pub mod profiler;
+pub mod quirks;
+pub mod random;
+#[cfg(feature = "std")]
+pub mod trace;
@@ -0,0 +1,256 @@
+use super::chip8::{MEM_SIZE, PROGRAM_START};
+use super::instruction::Instruction;
+use super::metadata::{Flow, METADATA};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+// HOT_SPOTS is the number of addresses listed in a report.
+const HOT_SPOTS: usize = 20;
+
+// MAX_EVENTS is how many calls and returns are kept for the Chrome trace,
+// 16 MB of them. Calls made after that are counted but left out, so a long
+// run does not grow without bound.
+pub const MAX_EVENTS: usize = 1 << 20;
+
+// Subroutine holds the statistics of a single subroutine. Times are nominal
+// COSMAC VIP microseconds, see Metadata::micros.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Subroutine {
+    pub calls: u64,
+    pub inclusive: u64, // Time spent in the subroutine and its callees
+    pub exclusive: u64, // Time spent in the subroutine itself
+}
+
+// Frame is an active subroutine call.
+struct Frame {
+    entry: u16,
+    start: u64,   // The time when the call was made
+    traced: bool, // Whether the call is in the Chrome trace
+}
+
+// Event is a subroutine entry or exit, for exporting Chrome traces.
+struct Event {
+    begin: bool,
+    entry: u16,
+    ts: u64,
+}
+
+// Profiler counts executions per address and per instruction, and attributes
+// time to the subroutines found through 2NNN and 00EE.
+pub struct Profiler {
+    pub by_address: Vec<u64>,
+    pub by_kind: [u64; METADATA.len()],
+    pub subroutines: BTreeMap<u16, Subroutine>, // The program entry included
+    pub micros: u64, // The nominal time of every instruction so far
+    pub untraced: u64, // Calls left out of the Chrome trace
+    opcodes: Vec<u16>, // The last opcode executed at every address
+    stack: Vec<Frame>,
+    events: Vec<Event>,
+}
+
+impl Profiler {
+    // new constructs a profiler. The program entry point is treated as the
+    // outermost subroutine.
+    pub fn new() -> Self {
+        let mut subroutines = BTreeMap::new();
+        subroutines.insert(
+            PROGRAM_START,
+            Subroutine {
+                calls: 1,
+                ..Default::default()
+            },
+        );
+        Self {
+            by_address: vec![0; MEM_SIZE],
+            by_kind: [0; METADATA.len()],
+            subroutines,
+            micros: 0,
+            untraced: 0,
+            opcodes: vec![0; MEM_SIZE],
+            stack: vec![Frame {
+                entry: PROGRAM_START,
+                start: 0,
+                traced: true,
+            }],
+            events: vec![Event {
+                begin: true,
+                entry: PROGRAM_START,
+                ts: 0,
+            }],
+        }
+    }
+
+    // record records the execution of an instruction at pc.
+    pub fn record(&mut self, pc: u16, instr: &Instruction) {
+        let meta = instr.metadata();
+        let cost = meta.micros as u64;
+        let addr = pc as usize % MEM_SIZE;
+        self.by_address[addr] += 1;
+        self.opcodes[addr] = instr.encode();
+        self.by_kind[instr.id()] += 1;
+        self.micros += cost;
+        if let Some(frame) = self.stack.last() {
+            let sub = self.subroutines.entry(frame.entry).or_default();
+            sub.exclusive += cost;
+        }
+
+        match meta.flow {
+            Flow::Call => {
+                let entry = instr.addr();
+                self.subroutines.entry(entry).or_default().calls += 1;
+                let traced = self.events.len() < MAX_EVENTS;
+                self.stack.push(Frame {
+                    entry,
+                    start: self.micros,
+                    traced,
+                });
+                if traced {
+                    self.events.push(Event {
+                        begin: true,
+                        entry,
+                        ts: self.micros,
+                    });
+                } else {
+                    self.untraced += 1;
+                }
+            }
+            // The outermost frame is never popped, so stray returns are
+            // ignored rather than ending the program's frame
+            Flow::Return if self.stack.len() > 1 => {
+                let frame = self.stack.pop().unwrap();
+                let sub = self.subroutines.entry(frame.entry).or_default();
+                sub.inclusive += self.micros - frame.start;
+                // The returns of traced calls are kept past MAX_EVENTS, at
+                // most one per frame of the stack
+                if frame.traced {
+                    self.events.push(Event {
+                        begin: false,
+                        entry: frame.entry,
+                        ts: self.micros,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // inclusive returns the inclusive time of a subroutine, counting the
+    // calls still on the stack as if they returned now.
+    fn inclusive(&self, entry: u16) -> u64 {
+        let open: u64 = self
+            .stack
+            .iter()
+            .filter(|frame| frame.entry == entry)
+            .map(|frame| self.micros - frame.start)
+            .sum();
+        self.subroutines.get(&entry).map_or(0, |s| s.inclusive) + open
+    }
+
+    // report returns a text report of the hottest addresses, the instruction
+    // mix and the subroutines, each sorted by cost.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let steps: u64 = self.by_kind.iter().sum();
+        let percent = |n: u64, of: u64| match of {
+            0 => 0.0,
+            _ => 100.0 * n as f64 / of as f64,
+        };
+        writeln!(out, "{} instructions, {} us", steps, self.micros).unwrap();
+
+        writeln!(out, "\nHot spots:").unwrap();
+        writeln!(out, "  addr    count       %  instruction").unwrap();
+        let mut hot: Vec<usize> =
+            (0..MEM_SIZE).filter(|&a| self.by_address[a] > 0).collect();
+        hot.sort_by_key(|&a| std::cmp::Reverse(self.by_address[a]));
+        for &addr in hot.iter().take(HOT_SPOTS) {
+            let count = self.by_address[addr];
+            let text = match Instruction::try_from(self.opcodes[addr]) {
+                Ok(instr) => instr.to_string(),
+                Err(_) => String::from("???"),
+            };
+            writeln!(
+                out,
+                "  0x{:03x} {:>8} {:>6.2}%  {}",
+                addr,
+                count,
+                percent(count, steps),
+                text
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "\nInstructions:").unwrap();
+        writeln!(out, "  op    mnemonic    count         us       %").unwrap();
+        let mut kinds: Vec<usize> = (0..METADATA.len())
+            .filter(|&k| self.by_kind[k] > 0)
+            .collect();
+        let cost = |k: usize| self.by_kind[k] * METADATA[k].micros as u64;
+        kinds.sort_by_key(|&k| std::cmp::Reverse(cost(k)));
+        for k in kinds {
+            writeln!(
+                out,
+                "  {}  {:<8} {:>8} {:>10} {:>6.2}%",
+                METADATA[k].opcode,
+                METADATA[k].mnemonic,
+                self.by_kind[k],
+                cost(k),
+                percent(cost(k), self.micros)
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "\nSubroutines:").unwrap();
+        writeln!(out, "  entry    calls  inclusive  exclusive       %")
+            .unwrap();
+        let mut subs: Vec<(u16, Subroutine)> =
+            self.subroutines.iter().map(|(e, s)| (*e, *s)).collect();
+        subs.sort_by_key(|(_, s)| std::cmp::Reverse(s.exclusive));
+        for (entry, sub) in subs {
+            writeln!(
+                out,
+                "  0x{:03x} {:>8} {:>10} {:>10} {:>6.2}%",
+                entry,
+                sub.calls,
+                self.inclusive(entry),
+                sub.exclusive,
+                percent(sub.exclusive, self.micros)
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    // chrome_trace exports the subroutine calls in the Chrome trace event
+    // format, timed in nominal microseconds. Calls still on the stack are
+    // closed at the current time. Calls past MAX_EVENTS are left out.
+    pub fn chrome_trace(&self) -> String {
+        let closing = self
+            .stack
+            .iter()
+            .rev()
+            .filter(|f| f.traced)
+            .map(|f| (false, f.entry, self.micros));
+        let events: Vec<String> = self
+            .events
+            .iter()
+            .map(|e| (e.begin, e.entry, e.ts))
+            .chain(closing)
+            .map(|(begin, entry, ts)| {
+                format!(
+                    "{{\"name\":\"sub_{:03x}\",\"cat\":\"chip8\",\"ph\":\"{}\",\"ts\":{},\"pid\":1,\"tid\":1}}",
+                    entry,
+                    if begin { "B" } else { "E" },
+                    ts
+                )
+            })
+            .collect();
+        format!("{{\"traceEvents\":[\n{}\n]}}\n", events.join(",\n"))
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
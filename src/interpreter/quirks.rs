@@ -0,0 +1,113 @@
+use core::fmt;
+
+// ErrQuirks is returned when a list of quirks cannot be parsed.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrQuirks(pub String);
+
+#[cfg(feature = "std")]
+impl fmt::Display for ErrQuirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown quirk '{}', expected one of none, vip, schip, {}",
+            self.0,
+            NAMES.join(", ")
+        )
+    }
+}
+
+// NAMES are the names of the quirks, in the order of the fields of Quirks.
+const NAMES: [&str; 5] = ["shift", "load-store", "jump", "vf-reset", "clip"];
+
+// Quirks selects between the behaviours that differ among CHIP-8
+// interpreters. The default has every quirk off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    pub shift: bool, // 8XY6 and 8XYE shift VX in place, ignoring VY
+    pub load_store: bool, // FX55 and FX65 leave I unchanged
+    pub jump: bool,  // BNNN jumps to NNN + VX, as BXNN
+    pub vf_reset: bool, // 8XY1, 8XY2 and 8XY3 reset VF
+    pub clip: bool,  // Sprites are clipped at the edges of the display
+}
+
+impl Quirks {
+    // VIP is the behaviour of the original COSMAC VIP interpreter.
+    pub const VIP: Quirks = Quirks {
+        shift: false,
+        load_store: false,
+        jump: false,
+        vf_reset: true,
+        clip: true,
+    };
+
+    // SCHIP is the behaviour of SUPER-CHIP 1.1 on the HP 48.
+    pub const SCHIP: Quirks = Quirks {
+        shift: true,
+        load_store: true,
+        jump: true,
+        vf_reset: false,
+        clip: true,
+    };
+
+    // parse parses a comma separated list of presets and quirks, applied in
+    // order on top of the default. A quirk prefixed with '-' is turned off,
+    // e.g. "schip,-clip".
+    #[cfg(feature = "std")]
+    pub fn parse(list: &str) -> Result<Self, ErrQuirks> {
+        let mut quirks = Quirks::default();
+        for word in list.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+            let (on, name) = match word.strip_prefix('-') {
+                Some(name) => (false, name),
+                None => (true, word.strip_prefix('+').unwrap_or(word)),
+            };
+            match name {
+                "none" if on => quirks = Quirks::default(),
+                "vip" if on => quirks = Quirks::VIP,
+                "schip" if on => quirks = Quirks::SCHIP,
+                _ => {
+                    *quirks
+                        .field(name)
+                        .ok_or_else(|| ErrQuirks(String::from(word)))? = on
+                }
+            }
+        }
+        Ok(quirks)
+    }
+
+    // field returns the quirk with the given name.
+    #[cfg(feature = "std")]
+    fn field(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "shift" => Some(&mut self.shift),
+            "load-store" => Some(&mut self.load_store),
+            "jump" => Some(&mut self.jump),
+            "vf-reset" => Some(&mut self.vf_reset),
+            "clip" => Some(&mut self.clip),
+            _ => None,
+        }
+    }
+}
+
+// Quirks are displayed as the list of quirks that are on, which parse
+// accepts back.
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let on = [
+            self.shift,
+            self.load_store,
+            self.jump,
+            self.vf_reset,
+            self.clip,
+        ];
+        let mut names = NAMES.iter().zip(on.iter()).filter(|(_, on)| **on);
+        match names.next() {
+            Some((first, _)) => write!(f, "{}", first)?,
+            None => return write!(f, "none"),
+        }
+        for (name, _) in names {
+            write!(f, ",{}", name)?;
+        }
+        Ok(())
+    }
+}
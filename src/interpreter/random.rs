@@ -0,0 +1,71 @@
+// Random is the random number generator of CXNN. With the rand feature it
+// is the StdRng of rand, seeded by the operating system. Without it, it is
+// a xorshift generator with a fixed seed, which needs nothing from the
+// platform, so firmware should seed it from whatever entropy it has. A
+// seeded generator repeats the same bytes either way.
+#[cfg(feature = "rand")]
+use rand::rngs::StdRng;
+#[cfg(feature = "rand")]
+use rand::{Rng, SeedableRng};
+
+#[cfg(feature = "rand")]
+pub struct Random(StdRng);
+
+#[cfg(feature = "rand")]
+impl Random {
+    // new returns a generator seeded by the operating system.
+    pub fn new() -> Self {
+        Random(StdRng::from_entropy())
+    }
+
+    // seeded returns a generator that always yields the same bytes.
+    pub fn seeded(seed: u64) -> Self {
+        Random(StdRng::seed_from_u64(seed))
+    }
+
+    // byte returns a random byte.
+    pub fn byte(&mut self) -> u8 {
+        self.0.gen()
+    }
+}
+
+// SEED is the seed of generators that were not given one, without rand.
+#[cfg(not(feature = "rand"))]
+const SEED: u64 = 0x0C8_5EED;
+
+#[cfg(not(feature = "rand"))]
+pub struct Random(u64); // The xorshift state, never zero
+
+#[cfg(not(feature = "rand"))]
+impl Random {
+    // new returns a generator with a fixed seed.
+    pub fn new() -> Self {
+        Self::seeded(SEED)
+    }
+
+    // seeded returns a generator that always yields the same bytes. The
+    // seed is scrambled by a step of splitmix64, so that close seeds give
+    // unrelated bytes.
+    pub fn seeded(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Random((z ^ (z >> 31)).max(1))
+    }
+
+    // byte returns a random byte, the high byte of a step of xorshift64*.
+    pub fn byte(&mut self) -> u8 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,435 @@
+use super::instruction::Instruction;
+use super::metadata::METADATA;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+// MAGIC starts every binary trace file, followed by a version byte.
+pub const MAGIC: &[u8; 4] = b"C8TR";
+pub const VERSION: u8 = 1;
+
+// RECORD_SIZE is the size of a single record in a binary trace.
+pub const RECORD_SIZE: usize = 34;
+
+// I_CHANGED is the bit of Record::changed set when I changed.
+pub const I_CHANGED: u32 = 1 << 16;
+
+// Format is the on-disk format of a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Binary,    // Fixed-size little endian records after a header
+    JsonLines, // One JSON object per line
+}
+
+// ErrFilter is returned when a trace filter cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrFilter(pub String);
+
+impl fmt::Display for ErrFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid trace filter '{}'", self.0)
+    }
+}
+
+// Filter selects which steps are written to a trace.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub range: Option<(u16, u16)>, // Inclusive range of program counters
+    pub kinds: Option<Vec<usize>>, // Instruction ids, see Instruction::id
+}
+
+impl Filter {
+    // matches returns whether a step should be traced.
+    pub fn matches(&self, pc: u16, instr: &Instruction) -> bool {
+        if let Some((from, to)) = self.range {
+            if pc < from || pc > to {
+                return false;
+            }
+        }
+        match &self.kinds {
+            Some(kinds) => kinds.contains(&instr.id()),
+            None => true,
+        }
+    }
+
+    // parse_range parses an inclusive range of program counters, written as
+    // two addresses separated by '-', e.g. "0x200-0x2ff", or a single
+    // address.
+    pub fn parse_range(text: &str) -> Result<(u16, u16), ErrFilter> {
+        let err = || ErrFilter(String::from(text));
+        let address = |word: &str| {
+            let word = word.trim();
+            match word.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16).ok(),
+                None => word.parse().ok(),
+            }
+        };
+        let (from, to) = match text.split_once('-') {
+            Some((from, to)) => (address(from), address(to)),
+            None => (address(text), address(text)),
+        };
+        match (from, to) {
+            (Some(from), Some(to)) if from <= to => Ok((from, to)),
+            _ => Err(err()),
+        }
+    }
+
+    // parse_kinds parses a comma separated list of opcode patterns or
+    // mnemonics, e.g. "DXYN,se", into the ids of the instructions they name.
+    pub fn parse_kinds(text: &str) -> Result<Vec<usize>, ErrFilter> {
+        let mut kinds = Vec::new();
+        for word in text.split(',').map(str::trim) {
+            let before = kinds.len();
+            for (id, meta) in METADATA.iter().enumerate() {
+                if meta.opcode.eq_ignore_ascii_case(word)
+                    || meta.mnemonic.eq_ignore_ascii_case(word)
+                {
+                    kinds.push(id);
+                }
+            }
+            if kinds.len() == before {
+                return Err(ErrFilter(String::from(word)));
+            }
+        }
+        Ok(kinds)
+    }
+}
+
+// Record is a single traced step. The registers hold their values after the
+// step, or as it left them when it faulted, and changed has one bit set per V
+// register (and I_CHANGED for I) that the step modified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub step: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub changed: u32,
+}
+
+impl Record {
+    // instruction returns the decoded instruction of the step, if any.
+    pub fn instruction(&self) -> Option<Instruction> {
+        Instruction::try_from(self.opcode).ok()
+    }
+
+    // to_bytes encodes the record for a binary trace.
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut b = [0; RECORD_SIZE];
+        b[0..8].copy_from_slice(&self.step.to_le_bytes());
+        b[8..10].copy_from_slice(&self.pc.to_le_bytes());
+        b[10..12].copy_from_slice(&self.opcode.to_le_bytes());
+        b[12..16].copy_from_slice(&self.changed.to_le_bytes());
+        b[16..32].copy_from_slice(&self.v);
+        b[32..34].copy_from_slice(&self.i.to_le_bytes());
+        b
+    }
+
+    // to_json encodes the record as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        let instr = match self.instruction() {
+            Some(instr) => instr.to_string(),
+            None => String::from("???"),
+        };
+        let v: Vec<String> = self.v.iter().map(|r| r.to_string()).collect();
+        let mut delta: Vec<String> = (0..16)
+            .filter(|r| self.changed & (1 << r) != 0)
+            .map(|r| format!("\"V{:X}\":{}", r, self.v[r]))
+            .collect();
+        if self.changed & I_CHANGED != 0 {
+            delta.push(format!("\"I\":{}", self.i));
+        }
+        format!(
+            "{{\"step\":{},\"pc\":{},\"opcode\":{},\"instr\":\"{}\",\"v\":[{}],\"i\":{},\"delta\":{{{}}}}}",
+            self.step,
+            self.pc,
+            self.opcode,
+            instr,
+            v.join(","),
+            self.i,
+            delta.join(",")
+        )
+    }
+
+    // from_bytes decodes a record of a binary trace.
+    pub fn from_bytes(b: &[u8]) -> Self {
+        let mut v = [0; 16];
+        v.copy_from_slice(&b[16..32]);
+        Self {
+            step: u64::from_le_bytes(b[0..8].try_into().unwrap()),
+            pc: u16::from_le_bytes([b[8], b[9]]),
+            opcode: u16::from_le_bytes([b[10], b[11]]),
+            changed: u32::from_le_bytes(b[12..16].try_into().unwrap()),
+            v,
+            i: u16::from_le_bytes([b[32], b[33]]),
+        }
+    }
+
+    // from_json decodes a line of a JSON lines trace.
+    pub fn from_json(line: &str) -> Option<Self> {
+        let mut v = [0; 16];
+        let regs = field(line, "v")?.trim_matches(|c| c == '[' || c == ']');
+        for (r, value) in regs.split(',').enumerate() {
+            *v.get_mut(r)? = value.trim().parse().ok()?;
+        }
+
+        // The changed registers are the keys of the delta object
+        let mut changed = 0;
+        let start = line.find("\"delta\":{")? + 9;
+        let end = start + line[start..].find('}')?;
+        for entry in line[start..end].split(',').filter(|e| !e.is_empty()) {
+            let key = entry.split(':').next()?.trim_matches('"');
+            changed |= match key {
+                "I" => I_CHANGED,
+                _ => 1 << u32::from_str_radix(key.get(1..)?, 16).ok()?,
+            };
+        }
+
+        Some(Self {
+            step: field(line, "step")?.parse().ok()?,
+            pc: field(line, "pc")?.parse().ok()?,
+            opcode: field(line, "opcode")?.parse().ok()?,
+            v,
+            i: field(line, "i")?.parse().ok()?,
+            changed,
+        })
+    }
+
+    // same_state returns whether two records describe the same step of the
+    // machine, ignoring step numbers.
+    pub fn same_state(&self, other: &Record) -> bool {
+        self.pc == other.pc
+            && self.opcode == other.opcode
+            && self.v == other.v
+            && self.i == other.i
+    }
+}
+
+// field returns the raw value of a top level key of a JSON trace line.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("\"{}\":", key))? + key.len() + 3;
+    let rest = &line[start..];
+    let end = match rest.starts_with('[') {
+        true => rest.find(']')? + 1,
+        false => rest.find(&[',', '}'][..])?,
+    };
+    Some(&rest[..end])
+}
+
+// read reads a whole trace file, in either format.
+pub fn read(filename: &str) -> io::Result<Vec<Record>> {
+    let data = fs::read(filename)?;
+    let invalid = |what: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", filename, what),
+        )
+    };
+
+    if data.starts_with(MAGIC) {
+        if data.get(MAGIC.len()) != Some(&VERSION) {
+            return Err(invalid("unsupported trace version"));
+        }
+        let body = &data[MAGIC.len() + 1..];
+        if body.len() % RECORD_SIZE != 0 {
+            return Err(invalid("truncated trace"));
+        }
+        return Ok(body.chunks(RECORD_SIZE).map(Record::from_bytes).collect());
+    }
+
+    let text = String::from_utf8(data).map_err(|_| invalid("not a trace"))?;
+    let mut records = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Record::from_json(line) {
+            Some(record) => records.push(record),
+            None => {
+                return Err(invalid(&format!("bad record on line {}", n + 1)))
+            }
+        }
+    }
+    Ok(records)
+}
+
+// Divergence is the first point at which two traces disagree.
+#[derive(Debug)]
+pub struct Divergence {
+    pub index: usize,          // Index of the step after alignment
+    pub last: Option<Record>,  // The last step both traces agree on
+    pub left: Option<Record>,  // None if the left trace ended first
+    pub right: Option<Record>, // None if the right trace ended first
+}
+
+// ALIGN_WINDOW is how many leading steps diff will skip in either trace when
+// looking for a common starting point.
+const ALIGN_WINDOW: usize = 64;
+
+// diff aligns two traces on their first common step and returns the first
+// step at which they diverge, or None if they match to the end. A trace that
+// ends early diverges from the longer one.
+pub fn diff(left: &[Record], right: &[Record]) -> Option<Divergence> {
+    // Traces from other tools may start a few steps apart, so align them on
+    // the earliest pair of records with the same state
+    let mut offsets = (0, 0);
+    'align: for skip in 0..ALIGN_WINDOW.min(left.len().max(right.len())) {
+        for &(l, r) in &[(skip, 0), (0, skip)] {
+            if let (Some(a), Some(b)) = (left.get(l), right.get(r)) {
+                if a.same_state(b) {
+                    offsets = (l, r);
+                    break 'align;
+                }
+            }
+        }
+    }
+
+    let (left, right) = (&left[offsets.0..], &right[offsets.1..]);
+    for index in 0..left.len().max(right.len()) {
+        let (l, r) = (left.get(index), right.get(index));
+        let agree = match (l, r) {
+            (Some(a), Some(b)) => a.same_state(b),
+            _ => false,
+        };
+        if !agree {
+            return Some(Divergence {
+                index,
+                last: index.checked_sub(1).map(|i| left[i]),
+                left: l.cloned(),
+                right: r.cloned(),
+            });
+        }
+    }
+    None
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "traces diverge at aligned step {}", self.index)?;
+        let describe = |r: &Option<Record>| match r {
+            Some(r) => match r.instruction() {
+                Some(instr) => format!(
+                    "step {} pc 0x{:03x} op 0x{:04x} {}",
+                    r.step, r.pc, r.opcode, instr
+                ),
+                None => format!(
+                    "step {} pc 0x{:03x} op 0x{:04x}",
+                    r.step, r.pc, r.opcode
+                ),
+            },
+            None => String::from("(end of trace)"),
+        };
+        if let Some(last) = &self.last {
+            writeln!(f, "last agreed: {}", describe(&Some(*last)))?;
+        }
+        writeln!(f, "left:        {}", describe(&self.left))?;
+        writeln!(f, "right:       {}", describe(&self.right))?;
+
+        // Show every register side by side, marking the ones that differ
+        if let (Some(l), Some(r)) = (&self.left, &self.right) {
+            writeln!(f, "reg   left  right")?;
+            for reg in 0..16 {
+                let mark = if l.v[reg] != r.v[reg] { " <" } else { "" };
+                writeln!(
+                    f,
+                    "V{:X}    0x{:02x}  0x{:02x}{}",
+                    reg, l.v[reg], r.v[reg], mark
+                )?;
+            }
+            let mark = if l.i != r.i { " <" } else { "" };
+            writeln!(f, "I    0x{:03x} 0x{:03x}{}", l.i, r.i, mark)?;
+        }
+        Ok(())
+    }
+}
+
+// Tracer writes the steps of a running machine to a trace. Tracing stops at
+// the first error writing the trace, which is kept for the caller.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    format: Format,
+    filter: Filter,
+    step: u64,
+    error: Option<io::Error>, // The error that stopped tracing, if any
+}
+
+impl Tracer {
+    // new constructs a tracer writing to the given output.
+    pub fn new(
+        mut out: Box<dyn Write>,
+        format: Format,
+        filter: Filter,
+    ) -> io::Result<Self> {
+        if format == Format::Binary {
+            out.write_all(MAGIC)?;
+            out.write_all(&[VERSION])?;
+        }
+        Ok(Self {
+            out,
+            format,
+            filter,
+            step: 0,
+            error: None,
+        })
+    }
+
+    // create constructs a tracer writing to a new file.
+    pub fn create(
+        filename: &str,
+        format: Format,
+        filter: Filter,
+    ) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(filename)?);
+        Self::new(Box::new(file), format, filter)
+    }
+
+    // record traces a single step, if it passes the filter. Every call counts
+    // as a step, whether it is written or not. Nothing is written once
+    // writing has failed, see error.
+    pub fn record(
+        &mut self,
+        pc: u16,
+        instr: &Instruction,
+        v: [u8; 16],
+        i: u16,
+        changed: u32,
+    ) {
+        let step = self.step;
+        self.step += 1;
+        if self.error.is_some() || !self.filter.matches(pc, instr) {
+            return;
+        }
+
+        let record = Record {
+            step,
+            pc,
+            opcode: instr.encode(),
+            v,
+            i,
+            changed,
+        };
+        let written = match self.format {
+            Format::Binary => self.out.write_all(&record.to_bytes()),
+            Format::JsonLines => writeln!(self.out, "{}", record.to_json()),
+        };
+        if let Err(e) = written {
+            self.error = Some(e);
+        }
+    }
+
+    // error returns the error that stopped tracing, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    // flush flushes the underlying output, or returns the error that stopped
+    // tracing.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use super::interpreter::chip8::{KEYS, N_KEYS};
+
+// Keymap is where the hex keypad sits on the keyboard: the character typed
+// for every key, row by row in the same order as KEYS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keymap(pub [char; N_KEYS]);
+
+impl Keymap {
+    // DEFAULT puts the keypad on the left of a QWERTY keyboard, from 1 down
+    // to V.
+    #[rustfmt::skip]
+    pub const DEFAULT: Keymap = Keymap([
+        '1', '2', '3', '4',
+        'q', 'w', 'e', 'r',
+        'a', 's', 'd', 'f',
+        'z', 'x', 'c', 'v',
+    ]);
+
+    // parse parses a keymap written as 16 distinct characters, e.g.
+    // "1234qwerasdfzxcv".
+    pub fn parse(text: &str) -> Option<Self> {
+        let chars: Vec<char> =
+            text.chars().map(|c| c.to_ascii_lowercase()).collect();
+        if chars.len() != N_KEYS
+            || (1..N_KEYS).any(|i| chars[..i].contains(&chars[i]))
+        {
+            return None;
+        }
+        let mut keys = [' '; N_KEYS];
+        keys.copy_from_slice(&chars);
+        Some(Keymap(keys))
+    }
+
+    // key returns the hex key a character is mapped to, if any.
+    pub fn key(&self, c: char) -> Option<u8> {
+        let c = c.to_ascii_lowercase();
+        self.0.iter().position(|&k| k == c).map(|i| KEYS[i])
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::DEFAULT
+    }
+}
@@ -1,4 +1,38 @@
+// Without the std feature the crate is only its core, the interpreter and
+// the arithmetic, which need neither the standard library nor an allocator.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod analysis;
 pub mod arithmetic;
+#[cfg(feature = "std")]
 pub mod assembler;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod debugger;
+#[cfg(feature = "std")]
+pub mod detect;
+#[cfg(feature = "std")]
+pub mod disassembler;
+#[cfg(feature = "piston")]
 pub mod gfx;
+#[cfg(feature = "std")]
+pub mod harness;
 pub mod interpreter;
+#[cfg(feature = "std")]
+pub mod keymap;
+#[cfg(feature = "std")]
+pub mod phosphor;
+#[cfg(feature = "std")]
+pub mod recompiler;
+#[cfg(feature = "std")]
+pub mod recorder;
+#[cfg(feature = "std")]
+pub mod romdb;
+#[cfg(feature = "std")]
+pub mod screenshot;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "std")]
+pub mod tui;
@@ -1,27 +1,723 @@
-use chip8::arithmetic;
-use chip8::assembler;
-use chip8::interpreter;
+use chip8::analysis::Analysis;
+use chip8::assembler::Assembler;
+use chip8::config::{Audio, Config, Settings};
+use chip8::debugger::{self, Debugger};
+use chip8::detect;
+use chip8::disassembler;
+#[cfg(feature = "piston")]
+use chip8::gfx;
+use chip8::harness::{self, Manifest};
+use chip8::interpreter::chip8::{
+    Chip8, Fault, INSTRUCTIONS_PER_FRAME, MEM_SIZE,
+};
+use chip8::interpreter::trace::{self, Filter, Format, Tracer};
+use chip8::keymap::Keymap;
+#[cfg(feature = "piston")]
+use chip8::phosphor::Phosphor;
+use chip8::recompiler;
+use chip8::recorder::{self, Recorder};
+use chip8::romdb;
+use chip8::screenshot::{Image, Palette};
+#[cfg(feature = "scripting")]
+use chip8::script::{ErrScript, Script};
+use chip8::tui::{self, Glyphs};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process;
 
-fn generate_test_program() {
-    let program = "62ff63ab8520";
-    assembler::Assembler::assemble(program, "roms/TEST.bin")
-        .expect("Could not assemble program");
+// USAGE describes the commands and options.
+const USAGE: &str = "\
+usage: chip8 <command> [options] <arguments>
+
+commands:
+  run <rom>                    run a ROM in a window, or the terminal
+  asm <source> [-o <rom>]      assemble a source file
+  disasm <rom> [-o <source>]   disassemble a ROM
+  recompile <rom> [-o <module.rs>]
+                               write a ROM as a Rust module
+  debug <rom>                  step through a ROM, type help for commands
+  dump <rom> [--frames N]      print memory and registers after N frames
+  info <rom>                   describe the code and data of a ROM
+  test [--record <file>] <manifest>...
+                               run headless test manifests
+  test --differential <manifest>...
+                               compare the block engine to the
+                               interpreter on test manifests
+  script <rom> <script.rhai> [--frames N]
+                               run a ROM under a Rhai script, until it
+                               stops or for N frames
+  trace-diff <left> <right>    compare two traces
+
+options:
+  --config FILE      the configuration file, instead of
+                     ~/.config/chip8/config.toml
+  --quirks LIST      none, vip or schip, then quirks to turn on or off with
+                     '-': shift, load-store, jump, vf-reset, clip
+  --speed N          instructions per frame (10)
+  --scale N          size of a pixel in the window (20)
+  --palette ON,OFF   colors as RRGGBB, e.g. 33ff66,000000, or one of
+                     default, classic, green, amber, lcd, octo
+  --decay N          frames for pixels to fade out in the window (0)
+  --keymap KEYS      the keys for 123C 456D 789E A0BF (1234qwerasdfzxcv)
+  --title TEXT       the title of the window
+  --seed N           seed for the random number generator
+  --blocks           run translated blocks instead of interpreting every
+                     instruction
+  --tui, --braille   run in the terminal, drawn with half blocks or braille
+  --record FILE      record the run to a .gif or .y4m file
+  --trace FILE       trace every instruction, as JSON lines for .jsonl
+  --trace-range A-B  trace only the instructions at addresses A to B
+  --trace-op OPS     trace only the given instructions, as opcodes or
+                     mnemonics, e.g. DXYN,se
+  --coverage FILE    write a coverage report, as HTML for .html
+  --profile FILE     write a profile, as a Chrome trace for .json
+
+exit status: 0 on success, 1 when the machine faults, a test fails or
+traces diverge, 2 on usage and I/O errors";
+
+// trace_diff compares two trace files and reports the first divergent step.
+// It returns the exit code: 0 if the traces match, 1 if they diverge and 2 on
+// error.
+fn trace_diff(args: &[String]) -> i32 {
+    if args.len() != 2 {
+        eprintln!("usage: chip8 trace-diff <left trace> <right trace>");
+        return 2;
+    }
+    let read = |filename: &str| {
+        trace::read(filename).map_err(|e| eprintln!("{}: {}", filename, e))
+    };
+    let (left, right) = match (read(&args[0]), read(&args[1])) {
+        (Ok(left), Ok(right)) => (left, right),
+        _ => return 2,
+    };
+
+    match trace::diff(&left, &right) {
+        Some(divergence) => {
+            print!("{}", divergence);
+            1
+        }
+        None => {
+            println!("traces match ({} steps)", left.len());
+            0
+        }
+    }
 }
 
-fn main() {
-    // generate_test_program();
-    let mut c8 = interpreter::chip8::Chip8::new();
-    println!("\n\n");
+// test runs headless test manifests. With --record, the frames of a single
+// manifest are saved to a GIF or Y4M file. It returns the exit code: 0 if
+// every test passed, 1 if any failed and 2 on error.
+fn test(args: &[String]) -> i32 {
+    if let [flag, manifests @ ..] = args {
+        if flag == "--differential" && !manifests.is_empty() {
+            return differential(manifests);
+        }
+    }
+    let (record, args) = match args {
+        [flag, file, rest @ ..] if flag == "--record" => (Some(file), rest),
+        _ => (None, args),
+    };
+    if args.is_empty() || (record.is_some() && args.len() != 1) {
+        eprintln!("usage: chip8 test <manifest>...");
+        eprintln!("       chip8 test --record <file.gif|file.y4m> <manifest>");
+        eprintln!("       chip8 test --differential <manifest>...");
+        return 2;
+    }
+    let mut recorder = None;
+    if let Some(file) = record {
+        let scale = recorder::DEFAULT_SCALE;
+        match Recorder::create(file, Palette::DEFAULT, scale) {
+            Ok(r) => recorder = Some(r),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            }
+        }
+    }
+    let mut failed = None;
+    let mut on_frame = |c8: &Chip8| {
+        if let (Some(r), None) = (recorder.as_mut(), &failed) {
+            failed = r.frame(Image::of(c8)).err();
+        }
+    };
+
+    let mut code = 0;
+    for filename in args {
+        let outcome = match Manifest::load(filename)
+            .and_then(|m| harness::run_with(&m, &mut on_frame))
+        {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                eprintln!("{}: {}", filename, e);
+                code = 2;
+                continue;
+            }
+        };
+        if outcome.passed() {
+            println!("PASS {}", filename);
+            continue;
+        }
+        println!("FAIL {}", filename);
+        if let Some(fault) = outcome.fault {
+            println!("  fault after {} frames: {}", outcome.frames, fault);
+        }
+        for failure in outcome.failures {
+            println!("  {}", failure);
+        }
+        code = code.max(1);
+    }
+
+    if let (Some(file), Some(recorder)) = (record, recorder) {
+        if let Some(e) = failed.or_else(|| recorder.finish().err()) {
+            eprintln!("{}: {}", file, e);
+            return 2;
+        }
+        println!("recorded {}", file);
+    }
+    code
+}
+
+// differential runs test manifests with the block engine and with the
+// interpreter, and reports the first frame in which they diverge. It returns
+// the exit code: 0 if they agree on every manifest, 1 if they diverge and 2
+// on error.
+fn differential(manifests: &[String]) -> i32 {
+    let mut code = 0;
+    for filename in manifests {
+        match Manifest::load(filename).and_then(|m| harness::differential(&m)) {
+            Ok(None) => println!("SAME {}", filename),
+            Ok(Some(divergence)) => {
+                println!("DIFF {}\n  {}", filename, divergence);
+                code = code.max(1);
+            }
+            Err(e) => {
+                eprintln!("{}: {}", filename, e);
+                code = 2;
+            }
+        }
+    }
+    code
+}
+
+// Options are the options of the commands that run a ROM.
+struct Options {
+    settings: Settings, // Overrides the configuration and the ROM database
+    config: Option<String>, // The configuration file, if not the default
+    audio: Audio,
+    seed: Option<u64>,
+    blocks: bool, // Run translated blocks
+    frames: usize,
+    glyphs: Option<Glyphs>, // Run in the terminal, when set
+    record: Option<String>,
+    trace: Option<String>,
+    filter: Filter, // Which instructions to trace
+    coverage: Option<String>,
+    profile: Option<String>,
+    output: Option<String>,
+}
+
+impl Options {
+    // parse parses the options among args, returning them with the other
+    // arguments.
+    fn parse(args: &[String]) -> Result<(Self, Vec<String>), String> {
+        let mut options = Options {
+            settings: Settings::default(),
+            config: None,
+            audio: Audio::default(),
+            seed: None,
+            blocks: false,
+            frames: 0,
+            glyphs: None,
+            record: None,
+            trace: None,
+            filter: Filter::default(),
+            coverage: None,
+            profile: None,
+            output: None,
+        };
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", arg))
+            };
+            let number = |text: String| {
+                text.parse::<u64>()
+                    .map_err(|_| format!("{}: invalid number '{}'", arg, text))
+            };
+            match arg.as_str() {
+                "--quirks" | "--speed" | "--scale" | "--palette"
+                | "--keymap" | "--title" | "--decay" => options
+                    .settings
+                    .parse(&arg[2..], &value()?)
+                    .map_err(|e| e.to_string())?,
+                "--config" => options.config = Some(value()?),
+                "--seed" => options.seed = Some(number(value()?)?),
+                "--blocks" => options.blocks = true,
+                "--frames" => options.frames = number(value()?)? as usize,
+                "--tui" => options.glyphs = Some(Glyphs::HalfBlock),
+                "--braille" => options.glyphs = Some(Glyphs::Braille),
+                "--record" => options.record = Some(value()?),
+                "--trace" => options.trace = Some(value()?),
+                "--trace-range" => {
+                    options.filter.range = Some(
+                        Filter::parse_range(&value()?)
+                            .map_err(|e| e.to_string())?,
+                    )
+                }
+                "--trace-op" => {
+                    options.filter.kinds = Some(
+                        Filter::parse_kinds(&value()?)
+                            .map_err(|e| e.to_string())?,
+                    )
+                }
+                "--coverage" => options.coverage = Some(value()?),
+                "--profile" => options.profile = Some(value()?),
+                "-o" | "--output" => options.output = Some(value()?),
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option '{}'", arg))
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+        Ok((options, rest))
+    }
+
+    // machine loads a ROM into a new machine set up with the options. The
+    // settings not given on the command line come from the section of the
+    // ROM in the configuration file, then from the ROM database, then from
+    // the rest of the configuration file.
+    fn machine(&mut self, rom: &str) -> Result<Chip8, String> {
+        let config = Config::load(self.config.as_deref().map(Path::new))
+            .map_err(|e| e.to_string())?;
+        let path = config.find(rom);
+        let mut c8 = Chip8::new();
+        c8.load_rom(&path.to_string_lossy())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let sha1 = hex::encode(romdb::sha1(c8.rom()));
+        let game = c8.game.map(Settings::from).unwrap_or_default();
+        self.settings = self
+            .settings
+            .clone()
+            .or(config.rom(&path, &sha1))
+            .or(game)
+            .or(config.settings);
+        self.audio = config.audio;
+        if let Some(quirks) = self.settings.quirks {
+            c8.quirks = quirks;
+        }
+        if let Some(seed) = self.seed {
+            c8.seed(seed);
+        }
+        if self.blocks {
+            c8.enable_blocks();
+        }
+        if let Some(file) = &self.trace {
+            let format = if file.ends_with(".jsonl") {
+                Format::JsonLines
+            } else {
+                Format::Binary
+            };
+            let tracer = Tracer::create(file, format, self.filter.clone())
+                .map_err(|e| format!("{}: {}", file, e))?;
+            c8.set_tracer(Some(tracer));
+        }
+        if self.coverage.is_some() {
+            c8.enable_coverage();
+        }
+        if self.profile.is_some() {
+            c8.enable_profiler();
+        }
+        Ok(c8)
+    }
+
+    // speed returns the instructions to run per frame.
+    fn speed(&self) -> usize {
+        self.settings.speed.unwrap_or(INSTRUCTIONS_PER_FRAME)
+    }
+
+    // scale returns the size of a pixel in the window and recordings.
+    #[cfg(feature = "piston")]
+    fn scale(&self) -> usize {
+        self.settings.scale.unwrap_or(gfx::SCALE as usize)
+    }
+
+    // scale returns the size of a pixel in recordings. Without the window,
+    // they are as small as those of chip8 test.
+    #[cfg(not(feature = "piston"))]
+    fn scale(&self) -> usize {
+        self.settings.scale.unwrap_or(recorder::DEFAULT_SCALE)
+    }
+
+    // palette returns the colors to draw with.
+    fn palette(&self) -> Palette {
+        self.settings.palette.unwrap_or(Palette::DEFAULT)
+    }
+
+    // keymap returns where the keypad is on the keyboard.
+    fn keymap(&self) -> Keymap {
+        self.settings.keymap.unwrap_or(Keymap::DEFAULT)
+    }
+
+    // recorder creates the recorder asked for, if any.
+    fn recorder(&self) -> Result<Option<Recorder>, String> {
+        match &self.record {
+            Some(file) => Recorder::create(file, self.palette(), self.scale())
+                .map(Some)
+                .map_err(|e| format!("{}: {}", file, e)),
+            None => Ok(None),
+        }
+    }
+
+    // finish flushes the trace and writes the coverage and profile reports
+    // of a machine that stopped running.
+    fn finish(&self, c8: &mut Chip8) -> Result<(), String> {
+        if let (Some(file), Some(mut tracer)) = (&self.trace, c8.take_tracer())
+        {
+            tracer.flush().map_err(|e| format!("{}: {}", file, e))?;
+        }
+        let write = |file: &str, text: String| {
+            fs::write(file, text).map_err(|e| format!("{}: {}", file, e))
+        };
+        if let (Some(file), Some(coverage)) = (&self.coverage, c8.coverage()) {
+            let report = if file.ends_with(".html") {
+                coverage.heatmap_html(c8.rom())
+            } else {
+                coverage.annotated(c8.rom())
+            };
+            write(file, report)?;
+        }
+        if let (Some(file), Some(profiler)) = (&self.profile, c8.profiler()) {
+            let report = if file.ends_with(".json") {
+                if profiler.untraced > 0 {
+                    eprintln!(
+                        "{}: the last {} calls were left out",
+                        file, profiler.untraced
+                    );
+                }
+                profiler.chrome_trace()
+            } else {
+                profiler.report()
+            };
+            write(file, report)?;
+        }
+        Ok(())
+    }
+}
+
+// rom returns the single ROM or source file among the arguments.
+fn rom<'a>(rest: &'a [String], usage: &str) -> Result<&'a str, String> {
+    match rest {
+        [rom] => Ok(rom),
+        _ => Err(format!("usage: chip8 {}", usage)),
+    }
+}
+
+// run runs a ROM in a window or in the terminal until it is closed. It
+// returns 1 if the machine faulted.
+fn run(args: &[String]) -> Result<i32, String> {
+    let (mut options, rest) = Options::parse(args)?;
+    let mut c8 = options.machine(rom(&rest, "run [options] <rom>")?)?;
+    let fault = match options.glyphs {
+        Some(glyphs) => {
+            let mut terminal = tui::Terminal::new(glyphs);
+            terminal.palette = options.palette();
+            terminal.keymap = options.keymap();
+            terminal.bell = options.audio.enabled && options.audio.volume > 0.0;
+            terminal.speed = options.speed();
+            terminal.recorder = options.recorder()?;
+            terminal.run(&mut c8).map_err(|e| e.to_string())?
+        }
+        None => window(&options, &mut c8)?,
+    };
+    options.finish(&mut c8)?;
+    match fault {
+        Some(fault) => {
+            eprintln!("{}", fault);
+            Ok(1)
+        }
+        None => Ok(0),
+    }
+}
+
+// window runs a machine in a window until it is closed, returning the fault
+// that stopped it, if any.
+#[cfg(feature = "piston")]
+fn window(options: &Options, c8: &mut Chip8) -> Result<Option<Fault>, String> {
+    let title = options.settings.title.as_deref().unwrap_or(gfx::TITLE);
+    let mut display = gfx::Display::open(title, options.scale() as f64);
+    display.palette = options.palette();
+    display.keymap = options.keymap();
+    display.phosphor = Phosphor::new(options.settings.decay.unwrap_or(0));
+    display.speed = options.speed();
+    display.recorder = options.recorder()?;
+    Ok(display.run(c8))
+}
+
+// window fails without the piston feature.
+#[cfg(not(feature = "piston"))]
+fn window(_: &Options, _: &mut Chip8) -> Result<Option<Fault>, String> {
+    let message = "chip8 was built without the window, run it with --tui or \
+                   --braille, or build it with --features piston";
+    Err(String::from(message))
+}
+
+// asm assembles a source file into a ROM, named after the source unless
+// given with -o.
+fn asm(args: &[String]) -> Result<i32, String> {
+    let (options, rest) = Options::parse(args)?;
+    let source = rom(&rest, "asm <source> [-o <rom>]")?;
+    let text =
+        fs::read_to_string(source).map_err(|e| format!("{}: {}", source, e))?;
+    let bytes = Assembler::assemble_source(&text)
+        .map_err(|e| format!("{}: {}", source, e))?;
+    let output = options.output.unwrap_or_else(|| {
+        Path::new(source)
+            .with_extension("bin")
+            .to_string_lossy()
+            .into_owned()
+    });
+    fs::write(&output, &bytes).map_err(|e| format!("{}: {}", output, e))?;
+    println!("wrote {} bytes to {}", bytes.len(), output);
+    Ok(0)
+}
+
+// disasm disassembles a ROM to standard output, or to a file with -o.
+fn disasm(args: &[String]) -> Result<i32, String> {
+    let (options, rest) = Options::parse(args)?;
+    let file = rom(&rest, "disasm <rom> [-o <source>]")?;
+    let bytes = fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+    let listing = disassembler::disassemble(&bytes);
+    match options.output {
+        Some(output) => fs::write(&output, listing)
+            .map_err(|e| format!("{}: {}", output, e))?,
+        None => print!("{}", listing),
+    }
+    Ok(0)
+}
 
-    c8.load_rom("roms/PONG.bin").expect("Could not load ROM");
-    // c8.memory_dump(250);
-    c8.full_dump();
+// recompile writes a ROM as a Rust module.
+fn recompile(args: &[String]) -> Result<i32, String> {
+    let (options, rest) = Options::parse(args)?;
+    let file = rom(&rest, "recompile <rom> [-o <module.rs>]")?;
+    let bytes = fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+    let name = Path::new(file).file_name().unwrap_or_default();
+    let module = recompiler::recompile(&bytes, &name.to_string_lossy());
+    match options.output {
+        Some(output) => fs::write(&output, module)
+            .map_err(|e| format!("{}: {}", output, e))?,
+        None => print!("{}", module),
+    }
+    Ok(0)
+}
 
-    println!("$$$");
+// debug runs the debugger on standard input until it quits or input ends.
+fn debug(args: &[String]) -> Result<i32, String> {
+    let (mut options, rest) = Options::parse(args)?;
+    let mut c8 = options.machine(rom(&rest, "debug [options] <rom>")?)?;
+    let mut debugger = Debugger::new(options.speed());
+    println!("{}", debugger.command(&mut c8, "list 0x200 1").unwrap());
 
-    c8.run();
+    let stdin = io::stdin();
+    let mut last = String::new();
+    loop {
+        print!("(chip8) ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        if stdin
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            break;
+        }
+        if line.trim().is_empty() {
+            line = last.clone();
+        }
+        match debugger.command(&mut c8, &line) {
+            Some(output) if output.is_empty() => (),
+            Some(output) => println!("{}", output),
+            None => break,
+        }
+        last = line;
+    }
+    options.finish(&mut c8)?;
+    Ok(0)
+}
 
-    for i in 0x200..0x210 {
-        println!("memory @ 0x{:x}: 0x{:x}", i, c8.memory[i]);
+// dump runs a ROM headlessly for --frames frames and prints the memory and
+// registers. It returns 1 if the machine faulted.
+fn dump(args: &[String]) -> Result<i32, String> {
+    let (mut options, rest) = Options::parse(args)?;
+    let mut c8 = options.machine(rom(&rest, "dump [options] <rom>")?)?;
+    let mut debugger = Debugger::new(options.speed());
+    let mut code = 0;
+    for frame in 0..options.frames {
+        if let Err(fault) = c8.run_frame(options.speed()) {
+            eprintln!("fault in frame {}: {}", frame, fault);
+            code = 1;
+            break;
+        }
+    }
+    options.finish(&mut c8)?;
+    println!("{}", debugger::hexdump(c8.bus().bytes(), 0, MEM_SIZE));
+    println!("{}", debugger.command(&mut c8, "regs").unwrap());
+    Ok(code)
+}
+
+// script runs a ROM headlessly under a script until the script stops, or
+// for the given number of frames.
+#[cfg(feature = "scripting")]
+fn script(args: &[String]) -> Result<i32, String> {
+    let (mut options, rest) = Options::parse(args)?;
+    let (rom, file) = match rest.as_slice() {
+        [rom, file] => (rom, file),
+        _ => {
+            let usage = "usage: chip8 script [options] <rom> <script.rhai>";
+            return Err(String::from(usage));
+        }
+    };
+    let mut c8 = options.machine(rom)?;
+    let mut script = Script::load(file).map_err(|e| e.to_string())?;
+    script.screenshots(options.palette(), options.scale());
+    let mut result = script.attach(&mut c8);
+    while result.is_ok()
+        && !script.stopped()
+        && (options.frames == 0 || script.frames() < options.frames)
+    {
+        result = script.run_frame(&mut c8, options.speed());
+    }
+    options.finish(&mut c8)?;
+    match result {
+        Ok(()) => Ok(0),
+        Err(ErrScript::Fault(fault)) => {
+            eprintln!("fault in frame {}: {}", script.frames(), fault);
+            Ok(1)
+        }
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            Ok(1)
+        }
+    }
+}
+
+// script fails without the scripting feature.
+#[cfg(not(feature = "scripting"))]
+fn script(_: &[String]) -> Result<i32, String> {
+    let message = "chip8 was built without scripting, build it with \
+                   --features scripting";
+    Err(String::from(message))
+}
+
+// info describes the code and data of a ROM, and the instructions whose
+// behaviour depends on quirks.
+fn info(args: &[String]) -> Result<i32, String> {
+    let (_, rest) = Options::parse(args)?;
+    let file = rom(&rest, "info <rom>")?;
+    let bytes = fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+    let analysis = Analysis::new(&bytes);
+    let mut kinds: HashMap<&str, usize> = HashMap::new();
+    let mut instructions = 0;
+    for block in analysis.blocks.values() {
+        for (_, instr) in &block.instructions {
+            *kinds.entry(instr.metadata().opcode).or_insert(0) += 1;
+            instructions += 1;
+        }
+    }
+    let unreachable: usize = analysis
+        .unreachable()
+        .iter()
+        .map(|(s, e)| (e - s) as usize)
+        .sum();
+
+    println!(
+        "{}: {} bytes, sha1 {}",
+        file,
+        bytes.len(),
+        hex::encode(romdb::sha1(&bytes))
+    );
+    if let Some(game) = romdb::lookup(&bytes) {
+        println!(
+            "game: {} by {} for {}",
+            game.title, game.author, game.platform
+        );
+        println!("settings: quirks {}, speed {}", game.quirks, game.speed);
+        println!("keys: {}", game.keys);
+    } else {
+        println!("platform: {}", detect::detect(&bytes));
+    }
+    println!(
+        "code: {} instructions in {} blocks, {} subroutines",
+        instructions,
+        analysis.blocks.len(),
+        analysis.subroutines.len()
+    );
+    println!("data: {} bytes not reached as code", unreachable);
+    let addrs = |set: &BTreeSet<u16>| {
+        set.iter()
+            .map(|a| format!("0x{:03x}", a))
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+    if !analysis.indirect.is_empty() {
+        println!("indirect jumps: {}", addrs(&analysis.indirect));
+    }
+    if !analysis.invalid.is_empty() {
+        println!("invalid opcodes: {}", addrs(&analysis.invalid));
+    }
+
+    // The instructions each quirk changes
+    let quirks: [(&str, &[&str]); 5] = [
+        ("shift", &["8XY6", "8XYE"]),
+        ("load-store", &["FX55", "FX65"]),
+        ("jump", &["BNNN"]),
+        ("vf-reset", &["8XY1", "8XY2", "8XY3"]),
+        ("clip", &["DXYN"]),
+    ];
+    for (quirk, opcodes) in quirks.iter() {
+        let used: Vec<String> = opcodes
+            .iter()
+            .filter_map(|op| kinds.get(op).map(|n| format!("{} x{}", op, n)))
+            .collect();
+        if !used.is_empty() {
+            println!("quirk {}: {}", quirk, used.join(", "));
+        }
+    }
+    Ok(0)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let command = args.get(1).map_or("", |c| c.as_str());
+    let rest = if args.len() > 2 { &args[2..] } else { &[] };
+    let code = match command {
+        "run" => run(rest),
+        "asm" => asm(rest),
+        "disasm" => disasm(rest),
+        "recompile" => recompile(rest),
+        "debug" => debug(rest),
+        "dump" => dump(rest),
+        "info" => info(rest),
+        "script" => script(rest),
+        "test" => Ok(test(rest)),
+        "trace-diff" => Ok(trace_diff(rest)),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        "" => Err(String::from(USAGE)),
+        _ => Err(format!("unknown command '{}'\n{}", command, USAGE)),
+    };
+    match code {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
     }
 }
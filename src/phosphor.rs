@@ -0,0 +1,45 @@
+// Phosphor fades pixels out over a few frames after they turn off, like the
+// phosphor of a CRT. Games erase and redraw their sprites every frame, so
+// without it moving sprites flicker. Only what is drawn fades; the display of
+// the machine stays exact.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phosphor {
+    pub frames: usize, // How many frames a pixel fades over, 0 for none
+    levels: Vec<f32>,  // How lit every pixel is, from 0 to 1
+}
+
+impl Phosphor {
+    pub fn new(frames: usize) -> Self {
+        Self {
+            frames,
+            levels: Vec::new(),
+        }
+    }
+
+    // update lights the lit pixels of a new frame fully and dims the rest by
+    // one step. The pixels are given row by row.
+    pub fn update(&mut self, pixels: impl IntoIterator<Item = bool>) {
+        let step = 1.0 / (self.frames + 1) as f32;
+        let mut n = 0;
+        for (i, lit) in pixels.into_iter().enumerate() {
+            if i == self.levels.len() {
+                self.levels.push(0.0);
+            }
+            let level = &mut self.levels[i];
+            *level = if lit { 1.0 } else { (*level - step).max(0.0) };
+            n = i + 1;
+        }
+        self.levels.truncate(n);
+    }
+
+    // levels returns how lit every pixel is after the last update.
+    pub fn levels(&self) -> &[f32] {
+        &self.levels
+    }
+}
+
+impl Default for Phosphor {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,331 @@
+// The recompiler turns a ROM into a Rust module. Every basic block of
+// reachable code becomes a function, which goes straight on to the function
+// after it wherever that is known from the ROM: after jumps, calls, skips
+// and returns to the callers of a subroutine. A block that jumps back to its
+// own start loops. Elsewhere a dispatcher runs the block at the program
+// counter. What the recompiler cannot follow is left to the interpreter: the
+// targets of BNNN jumps, code that was never reached and code that the ROM
+// changed after it was loaded.
+use super::analysis::Analysis;
+use super::interpreter::chip8::MEM_SIZE;
+use super::interpreter::instruction::Instruction;
+use super::interpreter::metadata::{Access, Flow};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// MAX_WIDTH is the longest line rustfmt leaves alone, so that the module is
+// formatted as rustfmt would.
+const MAX_WIDTH: usize = 80;
+
+// ROM_WIDTH is the number of ROM bytes per line of the ROM array.
+const ROM_WIDTH: usize = 12;
+
+// Function is the code of a single recompiled function: a basic block, or
+// the part of one up to an instruction that writes to memory, which may
+// change the code after it, or that waits for a key.
+struct Function {
+    block: u16, // The start of the basic block it is part of
+    start: u16,
+    instructions: Vec<(u16, Instruction)>,
+}
+
+// recompile returns the source of a Rust module that runs a ROM, named
+// after the file it came from. The module depends on the chip8 crate.
+pub fn recompile(rom: &[u8], name: &str) -> String {
+    let analysis = Analysis::new(rom);
+    let mut functions = Vec::new();
+    for block in analysis.blocks.values() {
+        let mut function = Function {
+            block: block.start,
+            start: block.start,
+            instructions: Vec::new(),
+        };
+        for &(addr, instr) in &block.instructions {
+            function.instructions.push((addr, instr));
+            // Waiting for a key runs the same instruction again
+            let metadata = instr.metadata();
+            if metadata.memory == Access::Write || metadata.flow == Flow::Wait {
+                functions.push(function);
+                function = Function {
+                    block: block.start,
+                    start: addr + 2,
+                    instructions: Vec::new(),
+                };
+            }
+        }
+        if !function.instructions.is_empty() {
+            functions.push(function);
+        }
+    }
+    let lengths: BTreeMap<u16, usize> = functions
+        .iter()
+        .map(|function| (function.start, function.instructions.len()))
+        .collect();
+
+    // Subroutines return to the instructions after their calls
+    let mut returns: BTreeMap<u16, Vec<u16>> = BTreeMap::new();
+    for block in analysis.blocks.values() {
+        for &(addr, instr) in &block.instructions {
+            if instr.metadata().flow == Flow::Call {
+                returns.entry(instr.addr()).or_default().push(wrap(addr, 2));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Recompiled from {} by chip8 recompile.", name).unwrap();
+    write!(
+        out,
+        "\
+//
+// Load ROM into a machine and call run_frame instead of Chip8::run_frame:
+//
+//     let mut c8 = Chip8::new();
+//     c8.load_rom_bytes(&ROM)?;
+//     run_frame(&mut c8, 10)?;
+//
+// Every basic block is a function, which runs the block after it when that
+// is known from the ROM, and loops when it jumps back to its own start.
+// Elsewhere, such as after BNNN, the block at the program counter is looked
+// up. Blocks whose code was changed and addresses with no block are
+// interpreted.
+use chip8::interpreter::chip8::{{Chip8, Fault, PROGRAM_START}};
+use chip8::interpreter::instruction::Instruction::*;
+
+// Block is a recompiled basic block. It counts the instructions it runs off
+// what is left of the frame and returns what runs next.
+type Block = fn(&mut Chip8, &mut usize) -> Result<Next, Fault>;
+
+// Next is what runs after a block: a block and its number of instructions,
+// or the block at the program counter.
+enum Next {{
+    Block(usize, Block),
+    Dispatch,
+}}
+
+"
+    )
+    .unwrap();
+
+    writeln!(out, "// ROM is the ROM, to be loaded at PROGRAM_START.").unwrap();
+    let bytes: Vec<String> =
+        rom.iter().map(|b| format!("0x{:02x}", b)).collect();
+    let array = format!("pub const ROM: [u8; {}] = [", rom.len());
+    if array.len() + bytes.join(", ").len() + 2 <= MAX_WIDTH {
+        writeln!(out, "{}{}];\n", array, bytes.join(", ")).unwrap();
+    } else {
+        writeln!(out, "{}", array).unwrap();
+        for line in bytes.chunks(ROM_WIDTH) {
+            writeln!(out, "    {},", line.join(", ")).unwrap();
+        }
+        writeln!(out, "];\n").unwrap();
+    }
+
+    write!(
+        out,
+        "\
+// run_frame runs the given number of instructions and then counts the
+// timers down once, like Chip8::run_frame. A block that does not fit in
+// what is left of the frame, or whose code was changed, is interpreted
+// instead.
+pub fn run_frame(c8: &mut Chip8, instructions: usize) -> Result<(), Fault> {{
+    let mut left = instructions;
+    let mut next = Next::Dispatch;
+    while left > 0 {{
+        let found = match next {{
+            Next::Block(len, run) => Some((len, run)),
+            Next::Dispatch => block(c8),
+        }};
+        match found {{
+            Some((len, run)) if len <= left && unchanged(c8, len) => {{
+                next = run(c8, &mut left)?;
+            }}
+            _ => {{
+                c8.cycle()?;
+                left -= 1;
+                next = Next::Dispatch;
+            }}
+        }}
+    }}
+    c8.tick();
+    Ok(())
+}}
+
+// block returns the block at the program counter and its number of
+// instructions, if there is one.
+fn block(c8: &Chip8) -> Option<(usize, Block)> {{
+    let block: (usize, Block) = match c8.pc() {{
+"
+    )
+    .unwrap();
+    for function in &functions {
+        writeln!(
+            out,
+            "        0x{:03x} => ({}, block_{:03x}),",
+            function.start,
+            function.instructions.len(),
+            function.start
+        )
+        .unwrap();
+    }
+    write!(
+        out,
+        "        _ => return None,
+    }};
+    Some(block)
+}}
+
+// unchanged returns whether the len instructions at the program counter are
+// still those of the ROM.
+fn unchanged(c8: &Chip8, len: usize) -> bool {{
+    let start = c8.pc() as usize;
+    let end = start + 2 * len;
+    let rom = start - PROGRAM_START as usize..end - PROGRAM_START as usize;
+    c8.bus().bytes()[start..end] == ROM[rom]
+}}
+"
+    )
+    .unwrap();
+
+    for function in &functions {
+        writeln!(out).unwrap();
+        writeln!(out, "{}", comment(&analysis, function)).unwrap();
+        writeln!(
+            out,
+            "fn block_{:03x}(c8: &mut Chip8, left: &mut usize) \
+             -> Result<Next, Fault> {{",
+            function.start
+        )
+        .unwrap();
+        let len = function.instructions.len();
+        let (last, instr) = function.instructions[len - 1];
+        let looping = instr.metadata().flow == Flow::Jump
+            && instr.addr() == function.start;
+        let indent = if looping { "        " } else { "    " };
+        if looping {
+            writeln!(out, "    loop {{").unwrap();
+        }
+        for (addr, instr) in &function.instructions {
+            writeln!(
+                out,
+                "{}c8.execute({})?; // 0x{:03x}  {}",
+                indent,
+                expression(instr),
+                addr,
+                instr
+            )
+            .unwrap();
+        }
+        writeln!(out, "{}*left -= {};", indent, len).unwrap();
+        if looping {
+            write!(
+                out,
+                "        if *left < {} {{
+            return Ok(Next::Block({}, block_{:03x}));
+        }}
+    }}
+}}
+",
+                len, len, function.start
+            )
+            .unwrap();
+            continue;
+        }
+
+        // The program counter is known after jumps, calls and instructions
+        // that go on to the next one; it is one of several after skips,
+        // waits and returns.
+        let next = wrap(last, 2);
+        let (targets, known) = match instr.metadata().flow {
+            Flow::Next => (vec![next], true),
+            Flow::Jump | Flow::Call => (vec![instr.addr()], true),
+            Flow::Skip => (vec![next, wrap(last, 4)], false),
+            Flow::Wait => (vec![last, next], false),
+            Flow::Return => (
+                analysis
+                    .subroutines
+                    .iter()
+                    .filter(|(_, blocks)| blocks.contains(&function.block))
+                    .flat_map(|(entry, _)| returns.get(entry))
+                    .flatten()
+                    .copied()
+                    .collect(),
+                false,
+            ),
+            Flow::JumpIndirect => (vec![], false),
+        };
+        let mut targets: Vec<(u16, usize)> = targets
+            .into_iter()
+            .filter_map(|addr| lengths.get(&addr).map(|&len| (addr, len)))
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        match targets.as_slice() {
+            [] => writeln!(out, "    Ok(Next::Dispatch)").unwrap(),
+            &[(addr, len)] if known => writeln!(
+                out,
+                "    Ok(Next::Block({}, block_{:03x}))",
+                len, addr
+            )
+            .unwrap(),
+            _ => {
+                writeln!(out, "    Ok(match c8.pc() {{").unwrap();
+                for (addr, len) in &targets {
+                    writeln!(
+                        out,
+                        "        0x{:03x} => Next::Block({}, block_{:03x}),",
+                        addr, len, addr
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "        _ => Next::Dispatch,\n    }})").unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+// wrap returns the address offset bytes after addr, wrapping around memory
+// as the program counter does.
+fn wrap(addr: u16, offset: usize) -> u16 {
+    ((addr as usize + offset) % MEM_SIZE) as u16
+}
+
+// comment describes where a function is and where control goes after it.
+fn comment(analysis: &Analysis, function: &Function) -> String {
+    let (last, instr) = function.instructions[function.instructions.len() - 1];
+    let mut comment = format!("// block_{:03x}", function.start);
+    let subroutine = analysis.subroutines.contains_key(&function.start);
+    if subroutine {
+        comment += " is a subroutine and";
+    }
+    let next = wrap(last, 2);
+    let after = match instr.metadata().flow {
+        Flow::Next => format!("0x{:03x}", next),
+        Flow::Wait => format!("0x{:03x} once a key is pressed", next),
+        Flow::Skip => format!("0x{:03x} or 0x{:03x}", next, wrap(last, 4)),
+        Flow::Jump => format!("0x{:03x}", instr.addr()),
+        Flow::Call => {
+            format!("0x{:03x}, returning to 0x{:03x}", instr.addr(), next)
+        }
+        Flow::Return => String::from("the caller"),
+        Flow::JumpIndirect => format!("0x{:03x} plus a register", instr.addr()),
+    };
+    format!("{} goes on to {}.", comment, after)
+}
+
+// expression returns the Rust expression of an instruction, with its
+// operands in hex.
+fn expression(instr: &Instruction) -> String {
+    let debug = format!("{:?}", instr);
+    let (variant, operands) = match debug.find('(') {
+        Some(i) => (&debug[..i], &debug[i + 1..debug.len() - 1]),
+        None => return debug,
+    };
+    let operands: Vec<String> = operands
+        .split(", ")
+        .map(|n| format!("0x{:x}", n.parse::<u16>().unwrap_or(0)))
+        .collect();
+    format!("{}({})", variant, operands.join(", "))
+}
@@ -0,0 +1,335 @@
+use super::screenshot::{Image, Palette};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+// FPS is the frame rate of a recording, one frame per 60 Hz timer tick.
+pub const FPS: u64 = 60;
+
+// DEFAULT_SCALE is the size of a pixel in recordings, kept small so clips
+// are easy to share.
+pub const DEFAULT_SCALE: usize = 4;
+
+// MIN_DELAY is the shortest GIF frame delay, in centiseconds, that viewers
+// honour. Browsers slow shorter delays down to 10 centiseconds. A 60 Hz frame
+// lasts 1 or 2 centiseconds once rounded, so a display that changes every
+// frame is recorded at 2 centiseconds a frame, dropping about a third of
+// them; the time they were shown goes to the frame that replaces them.
+const MIN_DELAY: u64 = 2;
+
+// MAX_CODES is the size of the GIF LZW dictionary.
+const MAX_CODES: u16 = 4096;
+
+// MIN_CODE_SIZE is the LZW minimum code size of GIF images. Two colors only
+// need one bit, but GIF requires at least two.
+const MIN_CODE_SIZE: u8 = 2;
+
+// Format is the file format of a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Gif, // Animated GIF that loops forever
+    Y4m, // Uncompressed YUV4MPEG2 video at 60 fps
+}
+
+impl Format {
+    // from_filename picks a format from the extension of a file.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let lower = filename.to_lowercase();
+        if lower.ends_with(".gif") {
+            Some(Format::Gif)
+        } else if lower.ends_with(".y4m") {
+            Some(Format::Y4m)
+        } else {
+            None
+        }
+    }
+}
+
+// Pending is the last distinct GIF frame, written once the next one arrives
+// and its delay is known.
+struct Pending {
+    pixels: Vec<u8>,
+    start: u64, // The frame number it was first shown at
+}
+
+// Recorder writes the frames of a session to an animated GIF or a Y4M video.
+// Every call to frame is one 60 Hz frame. The header is written with the
+// first frame, as it holds the size of the display. GIFs keep the length of
+// a session but not every frame of it, see MIN_DELAY; Y4M keeps them all.
+pub struct Recorder {
+    out: Box<dyn Write>,
+    format: Format,
+    palette: Palette,
+    scale: usize,
+    size: Option<(usize, usize)>, // The scaled size, once known
+    frames: u64,
+    pending: Option<Pending>,
+}
+
+impl Recorder {
+    // new constructs a recorder writing to the given output.
+    pub fn new(
+        out: Box<dyn Write>,
+        format: Format,
+        palette: Palette,
+        scale: usize,
+    ) -> Self {
+        Self {
+            out,
+            format,
+            palette,
+            scale: scale.max(1),
+            size: None,
+            frames: 0,
+            pending: None,
+        }
+    }
+
+    // create constructs a recorder writing to a new file, picking the format
+    // from its extension: .gif or .y4m.
+    pub fn create(
+        filename: &str,
+        palette: Palette,
+        scale: usize,
+    ) -> io::Result<Self> {
+        let format = Format::from_filename(filename).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: unknown video format", filename),
+            )
+        })?;
+        let file = BufWriter::new(File::create(filename)?);
+        Ok(Self::new(Box::new(file), format, palette, scale))
+    }
+
+    // frames returns the number of frames recorded so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // frame records the display for one frame.
+    pub fn frame(&mut self, image: Image) -> io::Result<()> {
+        let size = (image.width * self.scale, image.height * self.scale);
+        match self.size {
+            None => {
+                self.size = Some(size);
+                self.header()?;
+            }
+            Some(s) if s != size => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the display changed size during a recording",
+                ))
+            }
+            _ => (),
+        }
+
+        let pixels = image.scaled(self.scale);
+        let frame = self.frames;
+        self.frames += 1;
+        match self.format {
+            Format::Y4m => self.y4m_frame(&pixels),
+            Format::Gif => {
+                let pending = match self.pending.take() {
+                    None => Pending {
+                        pixels,
+                        start: frame,
+                    },
+                    // Unchanged frames extend the delay of the last one
+                    Some(p) if p.pixels == pixels => p,
+                    // Frames too short to show are replaced by the next one,
+                    // which keeps the timing at the cost of dropping them
+                    Some(p) if delay(p.start, frame) < MIN_DELAY => Pending {
+                        pixels,
+                        start: p.start,
+                    },
+                    Some(p) => {
+                        self.gif_frame(&p.pixels, delay(p.start, frame))?;
+                        Pending {
+                            pixels,
+                            start: frame,
+                        }
+                    }
+                };
+                self.pending = Some(pending);
+                Ok(())
+            }
+        }
+    }
+
+    // finish writes the last frame and the end of the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == Format::Gif {
+            if self.size.is_none() {
+                // An empty recording is still a valid image
+                self.size = Some((0, 0));
+                self.header()?;
+            }
+            if let Some(p) = self.pending.take() {
+                let frames = self.frames.max(p.start + 1);
+                self.gif_frame(&p.pixels, delay(p.start, frames))?;
+            }
+            self.out.write_all(&[0x3B])?;
+        }
+        self.out.flush()
+    }
+
+    // header writes the start of the file.
+    fn header(&mut self) -> io::Result<()> {
+        let (w, h) = self.size.unwrap_or((0, 0));
+        match self.format {
+            Format::Y4m => writeln!(
+                self.out,
+                "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                w, h, FPS
+            ),
+            Format::Gif => {
+                let mut out = b"GIF89a".to_vec();
+                out.extend(&(w as u16).to_le_bytes());
+                out.extend(&(h as u16).to_le_bytes());
+                out.extend(&[0x80, 0, 0]); // A global table of 2 colors
+                out.extend(&self.palette.off);
+                out.extend(&self.palette.on);
+                // Loop forever
+                out.extend(&[0x21, 0xFF, 0x0B]);
+                out.extend(b"NETSCAPE2.0");
+                out.extend(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+                self.out.write_all(&out)
+            }
+        }
+    }
+
+    // y4m_frame writes a single video frame as three full-size planes.
+    fn y4m_frame(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let (off, on) = (yuv(self.palette.off), yuv(self.palette.on));
+        self.out.write_all(b"FRAME\n")?;
+        for (off, on) in off.iter().zip(&on) {
+            let data: Vec<u8> = pixels
+                .iter()
+                .map(|p| if *p == 1 { *on } else { *off })
+                .collect();
+            self.out.write_all(&data)?;
+        }
+        Ok(())
+    }
+
+    // gif_frame writes a single image with its delay in centiseconds.
+    fn gif_frame(&mut self, pixels: &[u8], delay: u64) -> io::Result<()> {
+        let (w, h) = self.size.unwrap_or((0, 0));
+        let delay = delay.min(u16::MAX as u64) as u16;
+        let mut out = vec![0x21, 0xF9, 0x04, 0x00];
+        out.extend(&delay.to_le_bytes());
+        out.extend(&[0x00, 0x00]);
+        out.push(0x2C);
+        out.extend(&[0, 0, 0, 0]);
+        out.extend(&(w as u16).to_le_bytes());
+        out.extend(&(h as u16).to_le_bytes());
+        out.push(0x00);
+        out.push(MIN_CODE_SIZE);
+        for block in lzw(pixels).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend(block);
+        }
+        out.push(0x00);
+        self.out.write_all(&out)
+    }
+}
+
+// delay returns the GIF delay, in centiseconds, between two frame numbers.
+// Rounding the start and end separately keeps the total time exact.
+fn delay(from: u64, to: u64) -> u64 {
+    let cs = |frame: u64| (frame * 100 + FPS / 2) / FPS;
+    cs(to) - cs(from)
+}
+
+// yuv converts an RGB color to limited range BT.601 Y'CbCr.
+fn yuv(c: [u8; 3]) -> [u8; 3] {
+    let (r, g, b) = (c[0] as i32, c[1] as i32, c[2] as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, u as u8, v as u8]
+}
+
+// Bits packs variable width codes, least significant bit first.
+struct Bits {
+    out: Vec<u8>,
+    acc: u32,
+    n: u32,
+}
+
+impl Bits {
+    // push appends a code of the given width.
+    fn push(&mut self, code: u16, width: u32) {
+        self.acc |= (code as u32) << self.n;
+        self.n += width;
+        while self.n >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.n -= 8;
+        }
+    }
+
+    // finish returns the packed bytes, padding the last one.
+    fn finish(mut self) -> Vec<u8> {
+        if self.n > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+// lzw compresses palette indices with the variable width LZW of GIF.
+fn lzw(pixels: &[u8]) -> Vec<u8> {
+    let clear: u16 = 1 << MIN_CODE_SIZE;
+    let end = clear + 1;
+    let mut bits = Bits {
+        out: Vec::new(),
+        acc: 0,
+        n: 0,
+    };
+    let mut dict: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut width = MIN_CODE_SIZE as u32 + 1;
+    bits.push(clear, width);
+
+    let mut prefix: Option<u16> = None;
+    for &pixel in pixels {
+        let p = match prefix {
+            None => {
+                prefix = Some(pixel as u16);
+                continue;
+            }
+            Some(p) => p,
+        };
+        if let Some(&code) = dict.get(&(p, pixel)) {
+            prefix = Some(code);
+            continue;
+        }
+        bits.push(p, width);
+        if next < MAX_CODES {
+            dict.insert((p, pixel), next);
+            next += 1;
+            // The decoder adds its entries one code late, so the width only
+            // grows once the entry before the newest one no longer fits
+            if next > 1 << width && width < 12 {
+                width += 1;
+            }
+        } else {
+            bits.push(clear, width);
+            dict.clear();
+            next = end + 1;
+            width = MIN_CODE_SIZE as u32 + 1;
+        }
+        prefix = Some(pixel as u16);
+    }
+    if let Some(p) = prefix {
+        bits.push(p, width);
+        // The decoder adds an entry for the last code too
+        if next == 1 << width && width < 12 {
+            width += 1;
+        }
+    }
+    bits.push(end, width);
+    bits.finish()
+}
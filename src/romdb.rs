@@ -0,0 +1,130 @@
+use super::interpreter::quirks::Quirks;
+use super::screenshot::Palette;
+use std::fmt;
+
+// Platform is the interpreter a ROM was written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Chip8,  // The original interpreter on the COSMAC VIP
+    Schip,  // SUPER-CHIP on the HP 48 calculators
+    XoChip, // XO-CHIP, the Octo extensions
+}
+
+impl Platform {
+    // quirks returns the quirks of the platform.
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::VIP,
+            Platform::Schip => Quirks::SCHIP,
+            Platform::XoChip => Quirks::default(),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "CHIP-8"),
+            Platform::Schip => write!(f, "SUPER-CHIP"),
+            Platform::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
+
+// Game is an entry of the ROM database: what a ROM is and the settings it
+// plays best with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Game {
+    pub sha1: &'static str, // The SHA-1 of the ROM, in lowercase hex
+    pub title: &'static str,
+    pub author: &'static str,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub speed: usize,       // Recommended instructions per frame
+    pub keys: &'static str, // What the keys do
+    pub palette: Option<Palette>, // Recommended colors, if any
+}
+
+// GAMES is the ROM database.
+pub const GAMES: [Game; 2] = [
+    Game {
+        sha1: "b232ef880bd6060fb45fa6effed7edf0ae95670e",
+        title: "Pong",
+        author: "Paul Vervalin",
+        platform: Platform::Chip8,
+        quirks: Quirks::VIP,
+        speed: 10,
+        keys: "1 and 4 move the left paddle, C and D the right one",
+        palette: None,
+    },
+    Game {
+        sha1: "5f518084744bf3cb8733f6e5454dfd1634320563",
+        title: "Tetris",
+        author: "Fran Dachille",
+        platform: Platform::Chip8,
+        quirks: Quirks::VIP,
+        speed: 15,
+        keys: "5 and 6 move the piece, 4 rotates it and 7 drops it",
+        palette: None,
+    },
+];
+
+// lookup returns the database entry of a ROM, if it has one.
+pub fn lookup(rom: &[u8]) -> Option<&'static Game> {
+    let hash = hex::encode(sha1(rom));
+    GAMES.iter().find(|game| game.sha1 == hash)
+}
+
+// sha1 returns the SHA-1 digest of data, as specified in FIPS 180-4.
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] =
+        [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // Pad with a 1 bit, zeros and the length in bits to a multiple of 64
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip(&[a, b, c, d, e]) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, h) in digest.chunks_mut(4).zip(&h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
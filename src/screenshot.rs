@@ -0,0 +1,332 @@
+use super::interpreter::chip8::{Chip8, ON};
+use super::interpreter::framebuffer::LoRes;
+use std::fs;
+use std::io;
+
+// Palette is the pair of colors a display is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub on: [u8; 3],  // The RGB color of lit pixels
+    pub off: [u8; 3], // The RGB color of the background
+}
+
+impl Palette {
+    // DEFAULT is black pixels on a white background, like the window.
+    pub const DEFAULT: Palette = Palette {
+        on: [0x00, 0x00, 0x00],
+        off: [0xFF, 0xFF, 0xFF],
+    };
+
+    // NAMED are the palettes that can be chosen by name.
+    #[rustfmt::skip]
+    pub const NAMED: [(&'static str, Palette); 6] = [
+        ("default", Palette::DEFAULT),
+        ("classic", Palette { on: [0xFF, 0xFF, 0xFF], off: [0x00, 0x00, 0x00] }),
+        ("green",   Palette { on: [0x33, 0xFF, 0x66], off: [0x00, 0x10, 0x00] }),
+        ("amber",   Palette { on: [0xFF, 0xB0, 0x00], off: [0x1A, 0x0F, 0x00] }),
+        ("lcd",     Palette { on: [0x0F, 0x38, 0x0F], off: [0x9B, 0xBC, 0x0F] }),
+        ("octo",    Palette { on: [0xFF, 0xCC, 0x00], off: [0x99, 0x66, 0x00] }),
+    ];
+
+    // parse parses a palette given by name or written as two RRGGBB colors,
+    // lit pixels first, e.g. "33ff66,000000".
+    pub fn parse(text: &str) -> Option<Self> {
+        let name = text.trim().to_lowercase();
+        if let Some((_, palette)) = Self::NAMED.iter().find(|(n, _)| *n == name)
+        {
+            return Some(*palette);
+        }
+        let color = |text: &str| -> Option<[u8; 3]> {
+            let text = text.trim().trim_start_matches('#');
+            let bytes = hex::decode(text).ok()?;
+            match bytes.as_slice() {
+                [r, g, b] => Some([*r, *g, *b]),
+                _ => None,
+            }
+        };
+        let mut colors = text.split(',');
+        let on = color(colors.next()?)?;
+        let off = color(colors.next()?)?;
+        match colors.next() {
+            Some(_) => None,
+            None => Some(Palette { on, off }),
+        }
+    }
+
+    // blend returns the color of a pixel lit to the given level, from 0 for
+    // the background to 1 for a lit pixel.
+    pub fn blend(&self, level: f32) -> [u8; 3] {
+        let level = level.clamp(0.0, 1.0);
+        let mut color = [0; 3];
+        for (c, (on, off)) in
+            color.iter_mut().zip(self.on.iter().zip(&self.off))
+        {
+            *c = (*off as f32 + (*on as f32 - *off as f32) * level).round()
+                as u8;
+        }
+        color
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::DEFAULT
+    }
+}
+
+// Image is a display to be saved.
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+    source: Source<'a>,
+    pub width: usize,
+    pub height: usize,
+}
+
+// Source is where the pixels of an image are.
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    Bytes(&'a [u8]),    // One byte per pixel, row by row
+    Display(&'a LoRes), // The display of a machine
+}
+
+impl<'a> Image<'a> {
+    // new returns an image of one byte per pixel, row by row, with ON for
+    // lit pixels.
+    pub fn new(pixels: &'a [u8], width: usize, height: usize) -> Self {
+        Self {
+            source: Source::Bytes(pixels),
+            width,
+            height,
+        }
+    }
+
+    // of returns the current display of a machine, at the size of its
+    // framebuffer.
+    pub fn of(c8: &'a Chip8) -> Self {
+        Self {
+            source: Source::Display(c8.display()),
+            width: LoRes::WIDTH,
+            height: LoRes::HEIGHT,
+        }
+    }
+
+    // scaled returns the scaled image row by row, with 1 for lit pixels and 0
+    // for the background.
+    pub fn scaled(&self, scale: usize) -> Vec<u8> {
+        let (w, h) = (self.width * scale, self.height * scale);
+        let mut out = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                out.push(self.lit(x, y, scale) as u8);
+            }
+        }
+        out
+    }
+
+    // pixel returns whether the pixel at (x, y) is on. Pixels outside the
+    // image are off.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        match self.source {
+            Source::Bytes(pixels) => pixels[y * self.width + x] == ON,
+            Source::Display(display) => display.pixel(x, y),
+        }
+    }
+
+    // lit returns whether the pixel at (x, y) of the scaled image is on.
+    fn lit(&self, x: usize, y: usize, scale: usize) -> bool {
+        self.pixel(x / scale, y / scale)
+    }
+}
+
+// save saves an image to a file, picking the format from the extension:
+// .png, or plain .pbm or .pgm.
+pub fn save(
+    filename: &str,
+    image: Image,
+    palette: &Palette,
+    scale: usize,
+) -> io::Result<()> {
+    let invalid = |what: &str| {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: {}", filename, what),
+        ))
+    };
+    if scale == 0 {
+        return invalid("the scale must be at least 1");
+    }
+    let lower = filename.to_lowercase();
+    let data = if lower.ends_with(".png") {
+        png(image, palette, scale)
+    } else if lower.ends_with(".pbm") {
+        pbm(image, scale)
+    } else if lower.ends_with(".pgm") {
+        pgm(image, palette, scale)
+    } else {
+        return invalid("unknown image format");
+    };
+    fs::write(filename, data)
+}
+
+// pbm encodes an image as a plain PBM, with lit pixels black.
+pub fn pbm(image: Image, scale: usize) -> Vec<u8> {
+    let (w, h) = (image.width * scale, image.height * scale);
+    let mut out = format!("P1\n{} {}\n", w, h);
+    for y in 0..h {
+        plain(&mut out, (0..w).map(|x| image.lit(x, y, scale) as u8));
+    }
+    out.into_bytes()
+}
+
+// pbm_raw encodes an image as a binary PBM, with lit pixels black.
+pub fn pbm_raw(image: Image, scale: usize) -> Vec<u8> {
+    let (w, h) = (image.width * scale, image.height * scale);
+    let mut out = format!("P4\n{} {}\n", w, h).into_bytes();
+    for y in 0..h {
+        // Each row is padded to a whole byte
+        let mut row = vec![0u8; w.div_ceil(8)];
+        for x in 0..w {
+            if image.lit(x, y, scale) {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        out.extend(row);
+    }
+    out
+}
+
+// pgm encodes an image as a plain PGM, using the luma of the palette.
+pub fn pgm(image: Image, palette: &Palette, scale: usize) -> Vec<u8> {
+    let (w, h) = (image.width * scale, image.height * scale);
+    let (on, off) = (luma(palette.on), luma(palette.off));
+    let mut out = format!("P2\n{} {}\n255\n", w, h);
+    for y in 0..h {
+        plain(
+            &mut out,
+            (0..w).map(|x| if image.lit(x, y, scale) { on } else { off }),
+        );
+    }
+    out.into_bytes()
+}
+
+// pgm_raw encodes an image as a binary PGM, using the luma of the palette.
+pub fn pgm_raw(image: Image, palette: &Palette, scale: usize) -> Vec<u8> {
+    let (w, h) = (image.width * scale, image.height * scale);
+    let (on, off) = (luma(palette.on), luma(palette.off));
+    let mut out = format!("P5\n{} {}\n255\n", w, h).into_bytes();
+    for y in 0..h {
+        for x in 0..w {
+            out.push(if image.lit(x, y, scale) { on } else { off });
+        }
+    }
+    out
+}
+
+// plain appends a row of a plain PBM or PGM, wrapping it so that no line is
+// longer than the 70 characters the formats allow.
+fn plain(out: &mut String, row: impl Iterator<Item = u8>) {
+    let mut line = 0;
+    for value in row {
+        let text = value.to_string();
+        if line > 0 && line + 1 + text.len() > 70 {
+            out.push('\n');
+            line = 0;
+        } else if line > 0 {
+            out.push(' ');
+            line += 1;
+        }
+        out.push_str(&text);
+        line += text.len();
+    }
+    out.push('\n');
+}
+
+// luma returns the grey level of an RGB color.
+fn luma(c: [u8; 3]) -> u8 {
+    ((299 * c[0] as u32 + 587 * c[1] as u32 + 114 * c[2] as u32) / 1000) as u8
+}
+
+// png encodes an image as an indexed PNG, with the palette as its two
+// colors. The image data is stored uncompressed.
+pub fn png(image: Image, palette: &Palette, scale: usize) -> Vec<u8> {
+    let (w, h) = (image.width * scale, image.height * scale);
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    let mut header = Vec::new();
+    header.extend(&(w as u32).to_be_bytes());
+    header.extend(&(h as u32).to_be_bytes());
+    header.extend(&[8, 3, 0, 0, 0]); // 8-bit indexed, no interlacing
+    chunk(&mut out, b"IHDR", &header);
+
+    let mut colors = Vec::new();
+    colors.extend(&palette.off);
+    colors.extend(&palette.on);
+    chunk(&mut out, b"PLTE", &colors);
+
+    // Every scanline starts with filter type 0
+    let mut raw = Vec::with_capacity((w + 1) * h);
+    for y in 0..h {
+        raw.push(0);
+        for x in 0..w {
+            raw.push(image.lit(x, y, scale) as u8);
+        }
+    }
+    chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+// chunk appends a PNG chunk.
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(&crc.to_be_bytes());
+}
+
+// zlib_stored wraps data in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        out.extend(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none() as u8;
+        let len = block.len() as u16;
+        out.push(last);
+        out.extend(&len.to_le_bytes());
+        out.extend(&(!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(&adler32(data).to_be_bytes());
+    out
+}
+
+// crc32 computes the CRC-32 used by PNG.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// adler32 computes the checksum that ends a zlib stream.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
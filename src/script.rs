@@ -0,0 +1,315 @@
+// Scripts drive a machine from Rhai, for automated tests, trainers and
+// tool-assisted runs. A script reads and writes the registers and memory,
+// presses keys, takes screenshots and runs callbacks at breakpoints and
+// after every frame:
+//
+//     poke(0x2f0, 9);                          // Nine lives
+//     breakpoint(0x21a, || set_reg(0, 0));     // Never lose one
+//     let shots = 0;
+//     on_frame(|| {
+//         if frames() % 60 == 0 {
+//             shots += 1;
+//             screenshot(`shot${shots}.png`);
+//         }
+//         if frames() == 600 { stop(); }
+//     });
+//
+// The top level runs once, when the script is attached to a machine.
+// Breakpoint callbacks run before the instruction at their address.
+use super::interpreter::chip8::{Chip8, Fault, MEM_SIZE, N_KEYS, N_REGISTERS};
+use super::screenshot::{self, Image, Palette};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Scope, AST, INT};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::rc::Rc;
+
+// ErrScript is returned when a script does not compile or fails, or when
+// the machine faults while it runs.
+#[derive(Debug)]
+pub enum ErrScript {
+    Script(String),
+    Fault(Fault),
+}
+
+impl fmt::Display for ErrScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrScript::Script(message) => write!(f, "{}", message),
+            ErrScript::Fault(fault) => write!(f, "{}", fault),
+        }
+    }
+}
+
+// Rhai is the result of a function called by a script.
+type Rhai<T> = Result<T, Box<EvalAltResult>>;
+
+// State is what a script shares with the functions it calls.
+struct State {
+    c8: Chip8, // The machine, lent to the script while it runs
+    breakpoints: BTreeMap<u16, FnPtr>, // Callbacks by address
+    on_frame: Option<FnPtr>, // Called after every frame
+    frames: usize, // Frames run since the script was attached
+    stopped: bool, // Whether the script called stop
+    palette: Palette, // The colors of screenshots
+    scale: usize, // The size of a pixel in screenshots
+}
+
+// Script is a compiled script and the callbacks it set up.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Rc<RefCell<State>>,
+}
+
+impl Script {
+    // new compiles a script.
+    pub fn new(source: &str) -> Result<Self, ErrScript> {
+        let state = Rc::new(RefCell::new(State {
+            c8: Chip8::new(),
+            breakpoints: BTreeMap::new(),
+            on_frame: None,
+            frames: 0,
+            stopped: false,
+            palette: Palette::DEFAULT,
+            scale: 1,
+        }));
+        let mut engine = Engine::new();
+        register(&mut engine, &state);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| ErrScript::Script(e.to_string()))?;
+        Ok(Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            state,
+        })
+    }
+
+    // load compiles the script in a file.
+    pub fn load(filename: &str) -> Result<Self, ErrScript> {
+        let source = fs::read_to_string(filename)
+            .map_err(|e| ErrScript::Script(format!("{}: {}", filename, e)))?;
+        Self::new(&source)
+            .map_err(|e| ErrScript::Script(format!("{}: {}", filename, e)))
+    }
+
+    // screenshots sets the colors and the size of a pixel of the
+    // screenshots the script takes.
+    pub fn screenshots(&mut self, palette: Palette, scale: usize) {
+        let mut state = self.state.borrow_mut();
+        state.palette = palette;
+        state.scale = scale;
+    }
+
+    // attach runs the top level of the script on a machine, which usually
+    // patches it and sets up callbacks.
+    pub fn attach(&mut self, c8: &mut Chip8) -> Result<(), ErrScript> {
+        self.with(c8, |engine, ast, scope| {
+            engine.run_ast_with_scope(scope, ast)
+        })
+    }
+
+    // run_frame runs a frame like Chip8::run_frame, calling the breakpoint
+    // callbacks on the way and the frame callback at the end. The frame is
+    // cut short when a breakpoint callback stops the script.
+    pub fn run_frame(
+        &mut self,
+        c8: &mut Chip8,
+        instructions: usize,
+    ) -> Result<(), ErrScript> {
+        if self.state.borrow().breakpoints.is_empty() {
+            c8.run_frame(instructions).map_err(ErrScript::Fault)?;
+        } else {
+            for _ in 0..instructions {
+                let breakpoint =
+                    self.state.borrow().breakpoints.get(&c8.pc()).cloned();
+                if let Some(callback) = breakpoint {
+                    self.call(c8, &callback)?;
+                    if self.stopped() {
+                        return Ok(());
+                    }
+                }
+                c8.cycle().map_err(ErrScript::Fault)?;
+            }
+            c8.tick();
+        }
+        self.state.borrow_mut().frames += 1;
+        let on_frame = self.state.borrow().on_frame.clone();
+        if let Some(callback) = on_frame {
+            self.call(c8, &callback)?;
+        }
+        Ok(())
+    }
+
+    // stopped returns whether the script called stop.
+    pub fn stopped(&self) -> bool {
+        self.state.borrow().stopped
+    }
+
+    // frames returns the number of frames run since the script was
+    // attached.
+    pub fn frames(&self) -> usize {
+        self.state.borrow().frames
+    }
+
+    // call calls a callback of the script, ignoring what it returns.
+    fn call(
+        &mut self,
+        c8: &mut Chip8,
+        callback: &FnPtr,
+    ) -> Result<(), ErrScript> {
+        self.with(c8, |engine, ast, _| {
+            callback.call::<Dynamic>(engine, ast, ()).map(|_| ())
+        })
+    }
+
+    // with runs f with the machine lent to the functions of the script,
+    // and gives it back after.
+    fn with<T>(
+        &mut self,
+        c8: &mut Chip8,
+        f: impl FnOnce(&Engine, &AST, &mut Scope<'static>) -> Rhai<T>,
+    ) -> Result<T, ErrScript> {
+        mem::swap(c8, &mut self.state.borrow_mut().c8);
+        let result = f(&self.engine, &self.ast, &mut self.scope);
+        mem::swap(c8, &mut self.state.borrow_mut().c8);
+        result.map_err(|e| ErrScript::Script(e.to_string()))
+    }
+}
+
+// register registers the functions scripts call:
+//
+//     reg(x), set_reg(x, value)   the V registers
+//     index(), set_index(value)   the I register
+//     pc(), set_pc(addr)          the program counter
+//     delay(), sound()            the timers
+//     peek(addr), poke(addr, byte)
+//     press(key), release(key)
+//     framebuffer_hash()          the hash of the display, as hex
+//     screenshot(file)            a .png, .pbm or .pgm of the display
+//     breakpoint(addr, callback), clear_breakpoint(addr)
+//     on_frame(callback)          called after every frame
+//     frames()                    the number of frames run
+//     stop()                      ends the run
+fn register(engine: &mut Engine, state: &Rc<RefCell<State>>) {
+    let s = Rc::clone(state);
+    engine.register_fn("reg", move |x: INT| -> Rhai<INT> {
+        Ok(s.borrow().c8.register(v(x)?) as INT)
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("set_reg", move |x: INT, value: INT| -> Rhai<()> {
+        s.borrow_mut().c8.set_register(v(x)?, byte(value)?);
+        Ok(())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("index", move || s.borrow().c8.index() as INT);
+    let s = Rc::clone(state);
+    engine.register_fn("set_index", move |value: INT| -> Rhai<()> {
+        s.borrow_mut().c8.set_index(word(value)?);
+        Ok(())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("pc", move || s.borrow().c8.pc() as INT);
+    let s = Rc::clone(state);
+    engine.register_fn("set_pc", move |addr: INT| -> Rhai<()> {
+        s.borrow_mut().c8.set_pc(address(addr)?);
+        Ok(())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("delay", move || s.borrow().c8.delay_timer() as INT);
+    let s = Rc::clone(state);
+    engine.register_fn("sound", move || s.borrow().c8.sound_timer() as INT);
+
+    let s = Rc::clone(state);
+    engine.register_fn("peek", move |addr: INT| -> Rhai<INT> {
+        Ok(s.borrow().c8.peek(address(addr)?) as INT)
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("poke", move |addr: INT, value: INT| -> Rhai<()> {
+        s.borrow_mut().c8.store(address(addr)?, byte(value)?);
+        Ok(())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("press", move |k: INT| -> Rhai<()> {
+        s.borrow_mut().c8.set_key(key(k)?, true);
+        Ok(())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("release", move |k: INT| -> Rhai<()> {
+        s.borrow_mut().c8.set_key(key(k)?, false);
+        Ok(())
+    });
+
+    let s = Rc::clone(state);
+    engine.register_fn("framebuffer_hash", move || {
+        format!("{:016x}", s.borrow().c8.framebuffer_hash())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("screenshot", move |filename: &str| -> Rhai<()> {
+        let state = s.borrow();
+        let image = Image::of(&state.c8);
+        screenshot::save(filename, image, &state.palette, state.scale)
+            .map_err(|e| e.to_string().into())
+    });
+
+    let s = Rc::clone(state);
+    engine.register_fn("breakpoint", move |addr: INT, callback: FnPtr| {
+        let addr = address(addr)?;
+        s.borrow_mut().breakpoints.insert(addr, callback);
+        Ok(()) as Rhai<()>
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("clear_breakpoint", move |addr: INT| -> Rhai<bool> {
+        Ok(s.borrow_mut().breakpoints.remove(&address(addr)?).is_some())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("on_frame", move |callback: FnPtr| {
+        s.borrow_mut().on_frame = Some(callback);
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("frames", move || s.borrow().frames as INT);
+    let s = Rc::clone(state);
+    engine.register_fn("stop", move || s.borrow_mut().stopped = true);
+}
+
+// v checks the number of a V register.
+fn v(x: INT) -> Rhai<usize> {
+    match usize::try_from(x) {
+        Ok(x) if x < N_REGISTERS => Ok(x),
+        _ => Err(format!("no register V{}", x).into()),
+    }
+}
+
+// address checks an address in memory.
+fn address(addr: INT) -> Rhai<u16> {
+    match u16::try_from(addr) {
+        Ok(addr) if (addr as usize) < MEM_SIZE => Ok(addr),
+        _ => Err(format!("address {:#x} is out of memory", addr).into()),
+    }
+}
+
+// byte checks a value that fits in a byte.
+fn byte(value: INT) -> Rhai<u8> {
+    u8::try_from(value)
+        .map_err(|_| format!("{} does not fit in a byte", value).into())
+}
+
+// word checks a value that fits in 16 bits.
+fn word(value: INT) -> Rhai<u16> {
+    u16::try_from(value)
+        .map_err(|_| format!("{} does not fit in 16 bits", value).into())
+}
+
+// key checks a key of the hex keypad.
+fn key(k: INT) -> Rhai<u8> {
+    match u8::try_from(k) {
+        Ok(k) if (k as usize) < N_KEYS => Ok(k),
+        _ => Err(format!("no key {}", k).into()),
+    }
+}
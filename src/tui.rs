@@ -0,0 +1,249 @@
+use super::interpreter::chip8::{Chip8, Fault, INSTRUCTIONS_PER_FRAME, N_KEYS};
+use super::keymap::Keymap;
+use super::recorder::Recorder;
+use super::screenshot::{Image, Palette};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Colors, Print, ResetColor, SetColors};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{cursor, execute, queue};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// FRAME is the duration of a single 60 Hz frame.
+const FRAME: Duration = Duration::from_micros(16_667);
+
+// KEY_HOLD is how many frames a key stays down after it is typed, on
+// terminals that only report key presses. A held key stays down through key
+// repeat.
+const KEY_HOLD: u32 = 15;
+
+// PANEL_GAP is the number of columns between the display and the registers.
+const PANEL_GAP: u16 = 2;
+
+// Glyphs is how pixels are packed into characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyphs {
+    HalfBlock, // 1x2 pixels per character, drawn with the block elements
+    Braille,   // 2x4 pixels per character, drawn with the braille patterns
+}
+
+// render draws an image as lines of text, one line per row of characters.
+pub fn render(image: Image, glyphs: Glyphs) -> Vec<String> {
+    match glyphs {
+        Glyphs::HalfBlock => (0..image.height)
+            .step_by(2)
+            .map(|y| {
+                (0..image.width)
+                    .map(|x| match (image.pixel(x, y), image.pixel(x, y + 1)) {
+                        (false, false) => ' ',
+                        (true, false) => '\u{2580}', // Upper half block
+                        (false, true) => '\u{2584}', // Lower half block
+                        (true, true) => '\u{2588}',  // Full block
+                    })
+                    .collect()
+            })
+            .collect(),
+        Glyphs::Braille => (0..image.height)
+            .step_by(4)
+            .map(|y| {
+                (0..image.width)
+                    .step_by(2)
+                    .map(|x| braille(&image, x, y))
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+// braille returns the braille pattern for the 2x4 pixels at (x, y).
+fn braille(image: &Image, x: usize, y: usize) -> char {
+    // The dot of every pixel, column by column
+    const DOTS: [[u32; 4]; 2] =
+        [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    let mut bits = 0;
+    for (dx, column) in DOTS.iter().enumerate() {
+        for (dy, dot) in column.iter().enumerate() {
+            if image.pixel(x + dx, y + dy) {
+                bits |= dot;
+            }
+        }
+    }
+    std::char::from_u32(0x2800 + bits).unwrap()
+}
+
+// panel returns the lines of the register panel.
+fn panel(c8: &Chip8) -> Vec<String> {
+    let mut lines = vec![
+        format!("PC {:03x}   I {:03x}", c8.pc(), c8.index()),
+        format!("DT {:3}  ST {:3}", c8.delay_timer(), c8.sound_timer()),
+        format!("SP {:3}", c8.stack().len()),
+    ];
+    for r in (0..16).step_by(2) {
+        lines.push(format!(
+            "V{:X} {:02x}    V{:X} {:02x}",
+            r,
+            c8.register(r),
+            r + 1,
+            c8.register(r + 1)
+        ));
+    }
+    lines.push(String::new());
+    lines.push(String::from("Esc quits"));
+    lines
+}
+
+// Terminal runs a machine in the terminal, for machines without a display.
+pub struct Terminal {
+    pub glyphs: Glyphs,
+    pub palette: Palette,
+    pub keymap: Keymap,
+    pub speed: usize,               // Instructions per frame
+    pub recorder: Option<Recorder>, // Records every frame, when set
+    pub bell: bool,                 // Rings the bell when a sound starts
+    held: [u32; N_KEYS],            // The frames each key stays down for
+    releases: bool,                 // Whether the terminal reports key releases
+}
+
+impl Terminal {
+    pub fn new(glyphs: Glyphs) -> Self {
+        Self {
+            glyphs,
+            palette: Palette::DEFAULT,
+            keymap: Keymap::DEFAULT,
+            speed: INSTRUCTIONS_PER_FRAME,
+            recorder: None,
+            bell: true,
+            held: [0; N_KEYS],
+            releases: false,
+        }
+    }
+
+    // run runs a machine in the terminal, one frame every 60th of a second,
+    // until Esc is pressed or the machine faults, returning the fault.
+    pub fn run(&mut self, c8: &mut Chip8) -> io::Result<Option<Fault>> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(
+            out,
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            Clear(ClearType::All)
+        )?;
+        // Terminals that do not answer the query cannot report releases
+        self.releases =
+            terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if self.releases {
+            let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            execute!(out, PushKeyboardEnhancementFlags(flags))?;
+        }
+        let result = self.run_frames(c8, &mut out);
+        // Restore the terminal even if drawing failed
+        if self.releases {
+            execute!(out, PopKeyboardEnhancementFlags)?;
+        }
+        let restored = execute!(
+            out,
+            ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        terminal::disable_raw_mode()?;
+        restored?;
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish()?;
+        }
+        result
+    }
+
+    // run_frames runs frames until Esc is pressed or the machine faults,
+    // returning the fault.
+    fn run_frames(
+        &mut self,
+        c8: &mut Chip8,
+        out: &mut impl Write,
+    ) -> io::Result<Option<Fault>> {
+        let mut next = Instant::now();
+        loop {
+            while event::poll(Duration::from_secs(0))? {
+                if let Event::Key(key) = event::read()? {
+                    match key.code {
+                        KeyCode::Esc => return Ok(None),
+                        KeyCode::Char('c')
+                            if key
+                                .modifiers
+                                .contains(KeyModifiers::CONTROL) =>
+                        {
+                            return Ok(None)
+                        }
+                        KeyCode::Char(c) => {
+                            if let Some(k) = self.keymap.key(c) {
+                                self.held[k as usize] = match key.kind {
+                                    KeyEventKind::Release => 0,
+                                    _ if self.releases => u32::MAX,
+                                    _ => KEY_HOLD,
+                                };
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            for (k, held) in self.held.iter_mut().enumerate() {
+                c8.set_key(k as u8, *held > 0);
+                *held = held.saturating_sub(1);
+            }
+
+            let silent = c8.sound_timer() == 0;
+            if let Err(fault) = c8.run_frame(self.speed) {
+                return Ok(Some(fault));
+            }
+            if self.bell && silent && c8.sound_timer() > 0 {
+                queue!(out, Print('\u{7}'))?;
+            }
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.frame(Image::of(c8))?;
+            }
+            self.draw(c8, out)?;
+
+            next += FRAME;
+            let now = Instant::now();
+            if next > now {
+                thread::sleep(next - now);
+            } else {
+                // Too slow to keep up, so stop trying to catch up
+                next = now;
+            }
+        }
+    }
+
+    // draw draws the display with the register panel to its right.
+    fn draw(&self, c8: &Chip8, out: &mut impl Write) -> io::Result<()> {
+        let rgb = |c: [u8; 3]| Color::Rgb {
+            r: c[0],
+            g: c[1],
+            b: c[2],
+        };
+        let colors = Colors::new(rgb(self.palette.on), rgb(self.palette.off));
+        let lines = render(Image::of(c8), self.glyphs);
+        let panel = panel(c8);
+        let width = lines.first().map_or(0, |l| l.chars().count()) as u16;
+
+        for row in 0..lines.len().max(panel.len()) {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            if let Some(line) = lines.get(row) {
+                queue!(out, SetColors(colors), Print(line), ResetColor)?;
+            }
+            queue!(
+                out,
+                cursor::MoveTo(width + PANEL_GAP, row as u16),
+                Print(panel.get(row).map_or("", |s| s.as_str())),
+                Clear(ClearType::UntilNewLine)
+            )?;
+        }
+        out.flush()
+    }
+}
@@ -0,0 +1,96 @@
+use chip8::analysis::{Analysis, Edge};
+use chip8::detect::detect;
+use chip8::romdb::Platform;
+
+#[rustfmt::skip]
+const ROM: [u8; 17] = [
+    0x22, 0x0A, // 0x200  CALL 0x20a
+    0x30, 0x01, // 0x202  SE V0, 0x01
+    0x12, 0x08, // 0x204  JP 0x208
+    0xB3, 0x00, // 0x206  JP V0, 0x300
+    0x12, 0x08, // 0x208  JP 0x208
+    0x60, 0x01, // 0x20a  LD V0, 0x01
+    0x00, 0xEE, // 0x20c  RET
+    0xFF, 0xFF, 0x3C, // 0x20e  Data
+];
+
+#[test]
+fn blocks() {
+    let analysis = Analysis::new(&ROM);
+    assert_eq!((analysis.start, analysis.end), (0x200, 0x211));
+    let starts: Vec<u16> = analysis.blocks.keys().cloned().collect();
+    assert_eq!(starts, [0x200, 0x202, 0x204, 0x206, 0x208, 0x20a]);
+
+    let successors = |start| analysis.blocks[&start].successors.clone();
+    assert_eq!(
+        successors(0x200),
+        [(0x20a, Edge::Call), (0x202, Edge::Next)]
+    );
+    assert_eq!(
+        successors(0x202),
+        [(0x204, Edge::Next), (0x206, Edge::Skip)]
+    );
+    assert_eq!(successors(0x204), [(0x208, Edge::Jump)]);
+    assert_eq!(successors(0x206), []);
+    assert_eq!(successors(0x208), [(0x208, Edge::Jump)]);
+    assert_eq!(successors(0x20a), []);
+
+    let subroutine = &analysis.blocks[&0x20a];
+    assert_eq!(subroutine.instructions.len(), 2);
+    assert_eq!(subroutine.end(), 0x20e);
+}
+
+#[test]
+fn subroutines_and_indirect_jumps() {
+    let analysis = Analysis::new(&ROM);
+    let subroutines: Vec<u16> = analysis.subroutines.keys().cloned().collect();
+    assert_eq!(subroutines, [0x20a]);
+    assert_eq!(analysis.subroutines[&0x20a].len(), 1);
+    assert_eq!(analysis.indirect.iter().collect::<Vec<_>>(), [&0x206]);
+    assert!(analysis.invalid.is_empty());
+}
+
+#[test]
+fn unreachable() {
+    let analysis = Analysis::new(&ROM);
+    assert_eq!(analysis.unreachable(), [(0x20e, 0x211)]);
+    assert!(analysis.is_code(0x20c));
+    assert!(analysis.is_reachable(0x20d));
+    assert!(!analysis.is_reachable(0x20e));
+
+    // Code that runs into bytes that do not decode
+    let analysis = Analysis::new(&[0x60, 0x01, 0xFF, 0xFF]);
+    assert_eq!(analysis.invalid.iter().collect::<Vec<_>>(), [&0x202]);
+    assert_eq!(analysis.unreachable(), [(0x202, 0x204)]);
+}
+
+#[test]
+fn to_dot() {
+    let dot = Analysis::new(&ROM).to_dot();
+    assert!(dot.starts_with("digraph cfg {\n"));
+    assert!(dot.ends_with("}\n"));
+    let lines = [
+        "    b20a [label=\"0x20a: LD V0, 0x01\\l0x20c: RET\\l\", \
+         peripheries=2];",
+        "    b200 -> b20a [style=bold, label=call];",
+        "    b200 -> b202;",
+        "    b202 -> b206 [style=dashed, label=skip];",
+        "    b208 -> b208 [label=jump];",
+    ];
+    for line in &lines {
+        assert!(dot.lines().any(|l| l == *line), "{} in\n{}", line, dot);
+    }
+}
+
+#[test]
+fn large_roms() {
+    // Falls through every address up to the end of memory, and wraps to 0
+    let analysis = Analysis::new(&vec![0x00; 0x10000]);
+    assert_eq!(analysis.end, 0x1000);
+    assert!(analysis.is_code(0xffe));
+    assert!(!analysis.is_code(0x1000));
+    assert!(analysis.invalid.contains(&0x000));
+    let last = analysis.blocks.values().last().unwrap();
+    assert_eq!(last.end(), 0x1000);
+    assert_eq!(detect(&vec![0x00; 0x10000]).platform, Platform::XoChip);
+}
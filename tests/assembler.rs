@@ -0,0 +1,51 @@
+use chip8::assembler::Assembler;
+use chip8::disassembler::disassemble;
+use chip8::interpreter::quirks::Quirks;
+use std::fs;
+
+#[test]
+fn round_trip() {
+    for rom in &["roms/PONG.bin", "roms/TETRIS.bin", "roms/TEST.bin"] {
+        let bytes = fs::read(rom).unwrap();
+        let source = disassemble(&bytes);
+        assert_eq!(
+            Assembler::assemble_source(&source).unwrap(),
+            bytes,
+            "{}",
+            rom
+        );
+    }
+}
+
+#[test]
+fn labels_and_data() {
+    let source = "
+        start: LD V0, 0b101 ; a comment
+               JP start
+        sprite:
+               DB 0xF0, 144
+               DW sprite
+    ";
+    assert_eq!(
+        Assembler::assemble_source(source).unwrap(),
+        vec![0x60, 0x05, 0x12, 0x00, 0xF0, 0x90, 0x02, 0x04]
+    );
+}
+
+#[test]
+fn errors() {
+    let err = Assembler::assemble_source("CLS\nLD V0, 0x100").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(Assembler::assemble_source("JP nowhere").is_err());
+    assert!(Assembler::assemble_source("a:\na:").is_err());
+}
+
+#[test]
+fn quirks() {
+    assert_eq!(Quirks::parse("none").unwrap(), Quirks::default());
+    assert_eq!(Quirks::parse("vip").unwrap(), Quirks::VIP);
+    let quirks = Quirks::parse("schip,-clip,vf-reset").unwrap();
+    assert!(quirks.shift && quirks.vf_reset && !quirks.clip);
+    assert_eq!(Quirks::parse(&quirks.to_string()).unwrap(), quirks);
+    assert!(Quirks::parse("turbo").is_err());
+}
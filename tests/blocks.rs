@@ -0,0 +1,121 @@
+use chip8::harness::{self, Manifest};
+use chip8::interpreter::chip8::Chip8;
+use chip8::interpreter::instruction::Instruction;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::convert::TryFrom;
+use std::fs;
+
+#[test]
+fn manifests_agree() {
+    for entry in fs::read_dir("tests/roms").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("manifest") {
+            continue;
+        }
+        let manifest = Manifest::load(&path.to_string_lossy()).unwrap();
+        let divergence = harness::differential(&manifest).unwrap();
+        assert_eq!(divergence, None, "{}", path.display());
+    }
+}
+
+// SELF_MODIFYING runs ADD V5, 0x01 at 0x206, then overwrites it with
+// ADD V5, 0x10 through FX55 and runs it again, leaving V5 at 0x11.
+#[rustfmt::skip]
+const SELF_MODIFYING: [u8; 20] = [
+    0xA2, 0x06, // 0x200  LD I, 0x206
+    0x60, 0x75, // 0x202  LD V0, 0x75
+    0x61, 0x10, // 0x204  LD V1, 0x10
+    0x75, 0x01, // 0x206  ADD V5, 0x01, rewritten
+    0x42, 0x01, // 0x208  SNE V2, 0x01
+    0x12, 0x12, // 0x20a  JP 0x212
+    0x72, 0x01, // 0x20c  ADD V2, 0x01
+    0xF1, 0x55, // 0x20e  LD [I], V1
+    0x12, 0x06, // 0x210  JP 0x206
+    0x12, 0x12, // 0x212  JP 0x212
+];
+
+#[test]
+fn self_modifying_code() {
+    let mut c8 = Chip8::new();
+    c8.enable_blocks();
+    c8.load_rom_bytes(&SELF_MODIFYING).unwrap();
+    c8.run_frame(20).unwrap();
+    assert_eq!(c8.register(5), 0x11);
+    assert_eq!(c8.pc(), 0x212);
+    assert_eq!(c8.blocks().unwrap().flushes, 1);
+
+    let mut interpreter = Chip8::new();
+    interpreter.load_rom_bytes(&SELF_MODIFYING).unwrap();
+    interpreter.run_frame(20).unwrap();
+    assert_eq!(c8.differences(&interpreter), Vec::<String>::new());
+}
+
+#[test]
+fn stops_mid_block() {
+    // LD V0, 1; ADD V0, 1; ADD V0, 1; JP 0x200, run a few instructions at
+    // a time
+    let rom = [0x60, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x00];
+    let mut c8 = Chip8::new();
+    c8.enable_blocks();
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.run_frame(2).unwrap();
+    assert_eq!((c8.register(0), c8.pc()), (2, 0x204));
+    c8.run_frame(3).unwrap();
+    assert_eq!((c8.register(0), c8.pc()), (1, 0x202));
+}
+
+#[test]
+fn random_programs_agree() {
+    let mut rng = StdRng::seed_from_u64(44);
+    for program in 0..200 {
+        // Random valid instructions, with the jumps and I kept inside the
+        // ROM so that they run for a while, store over code and draw it
+        let mut rom = Vec::new();
+        for r in &[0x60, 0x61, 0x62, 0x6F] {
+            rom.extend(&[*r, rng.gen()]);
+        }
+        while rom.len() < 256 {
+            let word: u16 = rng.gen();
+            // Few registers, VF among them, so that instructions feed
+            // each other and their flags
+            let reg = |r: u16| [0x0, 0x1, 0x2, 0xF][r as usize % 4];
+            let (x, y) = (reg(word >> 8), reg(word >> 4));
+            let word = match word >> 12 {
+                0x1 | 0x2 | 0xA | 0xB => word & 0xF0FE | 0x0200,
+                0x5 | 0x8 | 0x9 | 0xD => word & 0xF00F | x << 8 | y << 4,
+                0x0 => word,
+                _ => word & 0xF0FF | x << 8,
+            };
+            if Instruction::try_from(word).is_ok() {
+                rom.extend(&word.to_be_bytes());
+            }
+        }
+        let mut blocks = Chip8::new();
+        let mut interpreter = Chip8::new();
+        blocks.enable_blocks();
+        for c8 in [&mut blocks, &mut interpreter].iter_mut() {
+            c8.load_rom_bytes(&rom).unwrap();
+            c8.seed(program);
+        }
+        for frame in 0..30 {
+            let key = rng.gen_range(0, 16);
+            let pressed = rng.gen();
+            blocks.set_key(key, pressed);
+            interpreter.set_key(key, pressed);
+            let faults = (blocks.run_frame(25), interpreter.run_frame(25));
+            let differences = blocks.differences(&interpreter);
+            assert!(
+                differences.is_empty() && faults.0 == faults.1,
+                "program {} frame {}: {:?} {:?}",
+                program,
+                frame,
+                differences,
+                faults
+            );
+            if faults.0.is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use chip8::interpreter::bus::{Bus, Counted, Memory, Protected};
+use chip8::interpreter::chip8::{Chip8, Fault, MEM_SIZE};
+
+// Clock is a memory-mapped device: reading 0xfff returns how many times it
+// was read before.
+struct Clock {
+    memory: Memory,
+    ticks: u8,
+}
+
+impl Bus for Clock {
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0xfff => self.ticks,
+            _ => self.memory.peek(addr),
+        }
+    }
+
+    fn poke(&mut self, addr: u16, byte: u8) {
+        self.memory.poke(addr, byte)
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        let byte = self.peek(addr);
+        if addr == 0xfff {
+            self.ticks += 1;
+        }
+        byte
+    }
+}
+
+#[test]
+fn memory_mapped_device() {
+    // LD I, 0xfff; LD V0, [I]; LD I, 0xfff; LD V0, [I]; LD V1, V0
+    let rom = [0xAF, 0xFF, 0xF0, 0x65, 0xAF, 0xFF, 0xF0, 0x65, 0x81, 0x00];
+    let clock = Clock {
+        memory: Memory::new(),
+        ticks: 7,
+    };
+    let mut c8 = Chip8::with_bus(clock);
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.run_frame(5).unwrap();
+    assert_eq!(c8.register(1), 8);
+    assert_eq!(c8.bus().ticks, 9);
+    assert_eq!(c8.peek(0xfff), 9);
+}
+
+#[test]
+fn counts_accesses() {
+    // LD V0, 0x12; LD I, 0x300; LD [I], V0; DRW V0, V0, 2; JP 0x208
+    let rom = [0x60, 0x12, 0xA3, 0x00, 0xF0, 0x55, 0xD0, 0x02, 0x12, 0x08];
+    let mut c8 = Chip8::with_bus(Counted::new(Memory::new()));
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.run_frame(6).unwrap();
+    let bus = c8.bus();
+    // Loading the ROM is not counted, fetching it is, once while the
+    // decoded instruction is cached
+    assert_eq!(bus.writes[0x200], 0);
+    assert_eq!(bus.reads[0x200], 1);
+    assert_eq!(bus.reads[0x208], 1);
+    assert_eq!(bus.writes[0x300], 1);
+    // The store moved I past what it wrote, as on the VIP
+    assert_eq!(bus.reads[0x300..0x303], [0, 1, 1]);
+    assert_eq!(bus.writes.iter().sum::<u64>(), 1);
+
+    // Without the cache, every fetch reaches the bus
+    let mut c8 = Chip8::with_bus(Counted::new(Memory::new()));
+    c8.disable_cache();
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.run_frame(6).unwrap();
+    assert_eq!(c8.bus().reads[0x208], 2);
+}
+
+#[test]
+fn protected_regions() {
+    // LD I, 0x206; LD [I], V0; JP 0x202, writing past its code and moving
+    // I on
+    let rom = [0xA2, 0x06, 0xF0, 0x55, 0x12, 0x02, 0xFF, 0xFF];
+    let mut bus = Protected::new(Memory::new());
+    bus.protect(0x200..0x206);
+    let mut c8 = Chip8::with_bus(bus);
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.run_frame(2).unwrap();
+    c8.bus_mut().protect(0x206..0x208);
+    assert_eq!(c8.run_frame(2), Err(Fault::ProtectedWrite(0x207)));
+    assert_eq!(c8.pc(), 0x202);
+    assert_eq!(c8.peek(0x206), 0x00);
+    assert_eq!(c8.peek(0x207), 0xFF);
+}
+
+#[test]
+fn host_access() {
+    let mut c8 = Chip8::new();
+    c8.store(MEM_SIZE as u16 + 0x10, 0xAB);
+    assert_eq!(c8.peek(0x10), 0xAB);
+    assert_eq!(c8.bus().bytes()[0x10], 0xAB);
+}
@@ -0,0 +1,65 @@
+use chip8::interpreter::bus::Bus;
+use chip8::interpreter::chip8::Chip8;
+
+// SELF_MODIFYING runs ADD V5, 0x01 at 0x206, then overwrites it with
+// ADD V5, 0x10 through FX55 and runs it again, leaving V5 at 0x11.
+#[rustfmt::skip]
+const SELF_MODIFYING: [u8; 20] = [
+    0xA2, 0x06, // 0x200  LD I, 0x206
+    0x60, 0x75, // 0x202  LD V0, 0x75
+    0x61, 0x10, // 0x204  LD V1, 0x10
+    0x75, 0x01, // 0x206  ADD V5, 0x01, rewritten
+    0x42, 0x01, // 0x208  SNE V2, 0x01
+    0x12, 0x12, // 0x20a  JP 0x212
+    0x72, 0x01, // 0x20c  ADD V2, 0x01
+    0xF1, 0x55, // 0x20e  LD [I], V1
+    0x12, 0x06, // 0x210  JP 0x206
+    0x12, 0x12, // 0x212  JP 0x212
+];
+
+#[test]
+fn self_modifying_code() {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&SELF_MODIFYING).unwrap();
+    c8.run_frame(20).unwrap();
+    assert_eq!(c8.register(5), 0x11);
+    assert_eq!(c8.pc(), 0x212);
+}
+
+#[test]
+fn stores_and_reloads() {
+    // ADD V5, 0x01; JP 0x200
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&[0x75, 0x01, 0x12, 0x00]).unwrap();
+    c8.run_frame(2).unwrap();
+    assert_eq!(c8.register(5), 0x01);
+
+    // Writing the second byte of a decoded instruction replaces it
+    c8.store(0x201, 0x20);
+    c8.run_frame(2).unwrap();
+    assert_eq!(c8.register(5), 0x21);
+
+    // So does loading another ROM over it
+    c8.load_rom_bytes(&[0x75, 0x02]).unwrap();
+    c8.run_frame(2).unwrap();
+    assert_eq!(c8.register(5), 0x23);
+}
+
+#[test]
+fn bus_writes() {
+    // ADD V5, 0x01; JP 0x200
+    let rom = [0x75, 0x01, 0x12, 0x00];
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.run_frame(2).unwrap();
+
+    // Code changed on the bus is not seen while it is cached
+    c8.bus_mut().poke(0x201, 0x20);
+    c8.run_frame(2).unwrap();
+    assert_eq!(c8.register(5), 0x02);
+
+    // Unless there is no cache
+    c8.disable_cache();
+    c8.run_frame(2).unwrap();
+    assert_eq!(c8.register(5), 0x22);
+}
@@ -0,0 +1,65 @@
+use chip8::config::{Config, Settings};
+use chip8::interpreter::quirks::Quirks;
+use chip8::keymap::Keymap;
+use std::path::Path;
+
+const CONFIG: &str = r#"
+scale = 12
+speed = 10
+quirks = "schip"
+keymap = "x123qweasdzc4rfv"
+rom_dir = "roms"
+
+[audio]
+enabled = false
+volume = 0.5
+
+[roms."PONG.bin"]
+speed = 8
+
+[roms.5f518084744bf3cb8733f6e5454dfd1634320563]
+palette = "33ff66,000000"
+"#;
+
+#[test]
+fn parse() {
+    let config = Config::parse(CONFIG).unwrap();
+    assert_eq!(config.settings.scale, Some(12));
+    assert_eq!(config.settings.quirks, Some(Quirks::SCHIP));
+    assert_eq!(config.settings.keymap, Keymap::parse("x123qweasdzc4rfv"));
+    assert!(!config.audio.enabled);
+    assert_eq!(config.audio.volume, 0.5);
+    assert_eq!(config.find("PONG.bin"), Path::new("roms/PONG.bin"));
+
+    let pong = config.rom(Path::new("roms/PONG.bin"), "");
+    assert_eq!(pong.speed, Some(8));
+    assert_eq!(pong.palette, None);
+    let tetris = config.rom(
+        Path::new("t.ch8"),
+        "5f518084744bf3cb8733f6e5454dfd1634320563",
+    );
+    assert!(tetris.palette.is_some());
+}
+
+#[test]
+fn overrides() {
+    let config = Config::parse(CONFIG).unwrap();
+    let mut cli = Settings::default();
+    cli.parse("speed", "20").unwrap();
+    let settings = cli
+        .or(config.rom(Path::new("PONG.bin"), ""))
+        .or(config.settings);
+    assert_eq!(settings.speed, Some(20));
+    assert_eq!(settings.scale, Some(12));
+}
+
+#[test]
+fn errors() {
+    assert!(Config::parse("speed = 0").is_err());
+    assert!(Config::parse("sped = 10").is_err());
+    assert!(Config::parse("quirks = \"fast\"").is_err());
+    assert!(Config::parse("[audio]\nvolume = 2").is_err());
+    assert!(Config::parse("[roms]\nfoo = 1").is_err());
+    assert!(Config::parse("scale = ").is_err());
+    assert!(Settings::default().parse("scale", "big").is_err());
+}
@@ -0,0 +1,133 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::interpreter::coverage::{Coverage, Summary};
+use chip8::interpreter::instruction::Instruction;
+
+#[rustfmt::skip]
+const ROM: [u8; 21] = [
+    0xA2, 0x10, // 0x200  LD I, 0x210
+    0xD0, 0x02, // 0x202  DRW V0, V0, 2
+    0x30, 0x00, // 0x204  SE V0, 0x00
+    0xF1, 0x55, // 0x206  LD [I], V1, skipped
+    0xA2, 0x12, // 0x208  LD I, 0x212
+    0xF0, 0x33, // 0x20a  LD B, V0
+    0x12, 0x0C, // 0x20c  JP 0x20c
+    0x00, 0x00, // 0x20e  Padding
+    0x3C, 0x42, // 0x210  The sprite
+    0x00, 0x00, 0x00, // 0x212  The digits of V0
+];
+
+// covered returns the coverage of the first 10 instructions of ROM.
+fn covered() -> Coverage {
+    let mut coverage = Coverage::new();
+    coverage.record(0x200, &Instruction::IANNN(0x210), 0x000);
+    coverage.record(0x202, &Instruction::IDXYN(0, 0, 2), 0x210);
+    coverage.record(0x204, &Instruction::I3XNN(0, 0x00), 0x210);
+    coverage.record(0x208, &Instruction::IANNN(0x212), 0x210);
+    coverage.record(0x20a, &Instruction::IFX33(0), 0x212);
+    for _ in 0..5 {
+        coverage.record(0x20c, &Instruction::I1NNN(0x20c), 0x212);
+    }
+    coverage
+}
+
+// A machine records the coverage of every instruction it runs.
+#[test]
+fn machine() {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&ROM).unwrap();
+    c8.enable_coverage();
+    c8.run_frame(10).unwrap();
+    let (coverage, expected) = (c8.coverage().unwrap(), covered());
+    assert_eq!(coverage.executed, expected.executed);
+    assert_eq!(coverage.read, expected.read);
+    assert_eq!(coverage.written, expected.written);
+}
+
+#[test]
+fn counts() {
+    let coverage = covered();
+    let executed: Vec<u32> = coverage.executed[0x200..0x215].to_vec();
+    #[rustfmt::skip]
+    assert_eq!(executed, [
+        1, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0,
+    ]);
+    assert_eq!(coverage.read[0x210..0x214], [1, 1, 0, 0]);
+    assert_eq!(coverage.written[0x210..0x216], [0, 0, 1, 1, 1, 0]);
+    assert_eq!(coverage.read.iter().sum::<u32>(), 2);
+    assert_eq!(coverage.written.iter().sum::<u32>(), 3);
+}
+
+#[test]
+fn summary() {
+    // Six of the seven reachable instructions ran, and the rest is data
+    let summary = covered().summary(&ROM);
+    assert_eq!(
+        summary,
+        Summary {
+            reachable: 7,
+            executed: 6,
+            unknown: 0,
+            percent: 600.0 / 7.0,
+        }
+    );
+
+    // Code reached through BNNN is not known to the analysis
+    let rom = [0xB2, 0x04, 0xFF, 0xFF, 0x12, 0x04];
+    let mut coverage = Coverage::new();
+    coverage.record(0x200, &Instruction::IBNNN(0x204), 0);
+    coverage.record(0x204, &Instruction::I1NNN(0x204), 0);
+    coverage.record(0x204, &Instruction::I1NNN(0x204), 0);
+    let summary = coverage.summary(&rom);
+    assert_eq!((summary.reachable, summary.executed), (1, 1));
+    assert_eq!((summary.unknown, summary.percent), (1, 100.0));
+}
+
+#[test]
+fn annotated() {
+    let text = covered().annotated(&ROM);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "; 6/7 reachable instructions executed (85.7%)");
+    assert_eq!(lines[1], "> 0x200  a210  LD I, 0x210        ; 1");
+    assert_eq!(lines[4], "- 0x206  f155  LD [I], V1         ; 0");
+    assert_eq!(lines[7], "> 0x20c  120c  JP 0x20c           ; 5");
+    assert_eq!(lines[8], "  0x20e  00    DB 0x00            ; r0 w0");
+    assert_eq!(lines[10], "  0x210  3c    DB 0x3c            ; r1 w0");
+    assert_eq!(lines[12], "  0x212  00    DB 0x00            ; r0 w1");
+    assert_eq!(lines.len(), 15);
+}
+
+#[test]
+fn heatmaps() {
+    let coverage = covered();
+    assert_eq!(
+        coverage.heatmap_text(&ROM),
+        "0x200 |......  ....@@  rrwww|\n"
+    );
+
+    let html = coverage.heatmap_html(&ROM);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.ends_with("</table></body></html>\n"));
+    assert!(html.contains("<p>6/7 reachable instructions executed (85.7%)"));
+    let cells = [
+        "<td style=\"background:rgb(255,21,21)\" title=\"0x20c: x5 r0 w0\">12</td>",
+        "<td style=\"background:#fff\" title=\"0x206: x0 r0 w0\">f1</td>",
+        "<td style=\"background:#cce0ff\" title=\"0x210: x0 r1 w0\">3c</td>",
+        "<td style=\"background:#ccf5cc\" title=\"0x212: x0 r0 w1\">00</td>",
+    ];
+    for cell in &cells {
+        assert!(html.contains(cell), "{} in\n{}", cell, html);
+    }
+}
+
+#[test]
+fn oversized_roms() {
+    // The bytes past the end of memory are never loaded, so they are left
+    // out of every report
+    let rom = vec![0x00; 0x1200];
+    let coverage = Coverage::new();
+    let summary = coverage.summary(&rom);
+    assert_eq!((summary.reachable, summary.executed), (0x700, 0));
+    assert_eq!(coverage.annotated(&rom).lines().count(), 1 + 0x700);
+    assert_eq!(coverage.heatmap_text(&rom).lines().count(), 0xE00 / 32);
+    assert!(!coverage.heatmap_html(&rom).contains("0x1000"));
+}
@@ -0,0 +1,167 @@
+use chip8::debugger::{self, Debugger};
+use chip8::interpreter::chip8::Chip8;
+
+#[rustfmt::skip]
+const ROM: [u8; 14] = [
+    0x60, 0x00, // 0x200  LD V0, 0x00
+    0x22, 0x0A, // 0x202  CALL 0x20a
+    0x70, 0x01, // 0x204  ADD V0, 0x01
+    0x12, 0x02, // 0x206  JP 0x202
+    0xFF, 0xFF, // 0x208  Data
+    0x71, 0x01, // 0x20a  ADD V1, 0x01
+    0x00, 0xEE, // 0x20c  RET
+];
+
+// HUGE is larger than any count of instructions or frames.
+const HUGE: &str = "18446744073709551615";
+
+// debugger loads ROM and returns a debugger for it, at 10 instructions per
+// frame.
+fn debugger() -> (Debugger, Chip8) {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&ROM).unwrap();
+    (Debugger::new(10), c8)
+}
+
+// run runs a debugger command and returns its output.
+fn run(debugger: &mut Debugger, c8: &mut Chip8, line: &str) -> String {
+    debugger.command(c8, line).unwrap()
+}
+
+#[test]
+fn breakpoints() {
+    let (mut d, mut c8) = debugger();
+    assert_eq!(run(&mut d, &mut c8, "break"), "");
+    assert_eq!(run(&mut d, &mut c8, "b 0x204"), "breakpoint at 0x204");
+    assert_eq!(run(&mut d, &mut c8, "break 522"), "breakpoint at 0x20a");
+    assert_eq!(run(&mut d, &mut c8, "break"), "0x204\n0x20a");
+    assert_eq!(run(&mut d, &mut c8, "d 0x20a"), "deleted 0x20a");
+    assert_eq!(run(&mut d, &mut c8, "d 0x20a"), "no breakpoint at 0x20a");
+
+    // Addresses must be in memory
+    assert_eq!(
+        run(&mut d, &mut c8, "break 0x1000"),
+        "0x1000 is outside memory"
+    );
+    let huge = format!("break {}", HUGE);
+    assert_eq!(
+        run(&mut d, &mut c8, &huge),
+        "0xffffffffffffffff is outside memory"
+    );
+    assert_eq!(
+        run(&mut d, &mut c8, "break 99999999999999999999"),
+        "invalid number '99999999999999999999'"
+    );
+    assert_eq!(run(&mut d, &mut c8, "break"), "0x204");
+}
+
+#[test]
+fn step() {
+    let (mut d, mut c8) = debugger();
+    assert_eq!(run(&mut d, &mut c8, "step"), "> 0x202  220a  CALL 0x20a");
+    assert_eq!(run(&mut d, &mut c8, "s 2"), "> 0x20c  00ee  RET");
+    assert_eq!(c8.register(1), 1);
+
+    // Steps stop at the first fault, however many were asked for
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&[0x00, 0xEE]).unwrap();
+    assert_eq!(
+        run(&mut d, &mut c8, &format!("step {}", HUGE)),
+        "fault: stack underflow returning from 0x200"
+    );
+}
+
+#[test]
+fn continue_to_breakpoints() {
+    let (mut d, mut c8) = debugger();
+    run(&mut d, &mut c8, "break 0x204");
+    assert_eq!(
+        run(&mut d, &mut c8, "continue"),
+        "breakpoint\n> 0x204  7001  ADD V0, 0x01"
+    );
+    assert_eq!(
+        run(&mut d, &mut c8, "c 1"),
+        "breakpoint\n> 0x204  7001  ADD V0, 0x01"
+    );
+    assert_eq!(c8.register(0), 1);
+
+    // Without a breakpoint, continue stops after the frames
+    run(&mut d, &mut c8, "delete 0x204");
+    assert_eq!(run(&mut d, &mut c8, "c 1"), "> 0x204  7001  ADD V0, 0x01");
+
+    // Frames that overflow into instructions are refused
+    let huge = format!("continue {}", HUGE);
+    assert_eq!(
+        run(&mut d, &mut c8, &huge),
+        format!("too many frames '{}'", HUGE)
+    );
+    assert_eq!(c8.pc(), 0x204);
+}
+
+#[test]
+fn next() {
+    let (mut d, mut c8) = debugger();
+    assert_eq!(run(&mut d, &mut c8, "next"), "> 0x202  220a  CALL 0x20a");
+
+    // The whole subroutine runs
+    assert_eq!(run(&mut d, &mut c8, "n"), "> 0x204  7001  ADD V0, 0x01");
+    assert_eq!((c8.register(1), c8.stack().len()), (1, 0));
+
+    // Unless it hits a breakpoint
+    run(&mut d, &mut c8, "n");
+    run(&mut d, &mut c8, "n");
+    run(&mut d, &mut c8, "break 0x20c");
+    assert_eq!(
+        run(&mut d, &mut c8, "next"),
+        "breakpoint\n> 0x20c  00ee  RET"
+    );
+}
+
+#[test]
+fn list() {
+    let (mut d, mut c8) = debugger();
+    assert_eq!(
+        run(&mut d, &mut c8, "list"),
+        "> 0x200  6000  LD V0, 0x00\n  \
+           0x202  220a  CALL 0x20a\n  \
+           0x204  7001  ADD V0, 0x01\n  \
+           0x206  1202  JP 0x202\n  \
+           0x208  ffff  ???\n  \
+           0x20a  7101  ADD V1, 0x01\n  \
+           0x20c  00ee  RET\n  \
+           0x20e  0000  SYS 0x000"
+    );
+    assert_eq!(
+        run(&mut d, &mut c8, "l 0x204 2"),
+        "  0x204  7001  ADD V0, 0x01\n  0x206  1202  JP 0x202"
+    );
+
+    // Listings wrap around memory, and list all of it at most
+    let lines = run(&mut d, &mut c8, "list 0xffe 2");
+    assert!(lines.ends_with("\n  0x000  f090  ???"), "{}", lines);
+    let lines = run(&mut d, &mut c8, &format!("list 0 {}", HUGE));
+    assert_eq!(lines.lines().count(), 0x800);
+    assert_eq!(
+        run(&mut d, &mut c8, "list 0x1000"),
+        "0x1000 is outside memory"
+    );
+}
+
+#[test]
+fn hexdump() {
+    let (mut d, mut c8) = debugger();
+    assert_eq!(
+        run(&mut d, &mut c8, "mem 0x200 16"),
+        "0x200  60 00 22 0a 70 01 12 02 ff ff 71 01 00 ee 00 00  \
+         `.\".p.....q....."
+    );
+    let dump = run(&mut d, &mut c8, "m 0x204 2");
+    assert_eq!(dump, format!("0x204  {:<47}  p.", "70 01"));
+
+    // Lengths stop at the end of memory
+    let dump = run(&mut d, &mut c8, &format!("mem 0xff0 {}", HUGE));
+    assert_eq!(dump.lines().count(), 1);
+    assert!(dump.starts_with("0xff0  00 00"), "{}", dump);
+    assert_eq!(run(&mut d, &mut c8, "m 0x1000"), "0x1000 is outside memory");
+    assert_eq!(debugger::hexdump(&[0; 16], usize::MAX, usize::MAX), "");
+}
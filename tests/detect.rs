@@ -0,0 +1,62 @@
+use chip8::detect::{detect, Confidence};
+use chip8::romdb::Platform;
+
+#[test]
+fn chip8() {
+    // LD V0, 1; JP 0x202
+    let detection = detect(&[0x60, 0x01, 0x12, 0x02]);
+    assert_eq!(detection.platform, Platform::Chip8);
+    assert_eq!(detection.confidence, Confidence::High);
+    assert!(detection.evidence.is_empty());
+}
+
+#[test]
+fn extensions() {
+    // HIGH; JP 0x202
+    let detection = detect(&[0x00, 0xFF, 0x12, 0x02]);
+    assert_eq!(detection.platform, Platform::Schip);
+    assert_eq!(detection.confidence, Confidence::High);
+
+    // LD I, long 0x0300
+    let detection = detect(&[0xF0, 0x00, 0x03, 0x00]);
+    assert_eq!(detection.platform, Platform::XoChip);
+    assert_eq!(detection.quirks, Platform::XoChip.quirks());
+
+    // DRW V0, V1, 0 draws nothing on CHIP-8
+    let detection = detect(&[0xD0, 0x10, 0x12, 0x02]);
+    assert_eq!(detection.platform, Platform::Schip);
+    assert_eq!(detection.confidence, Confidence::Medium);
+}
+
+#[test]
+fn data_and_size() {
+    // JP 0x200 followed by data that looks like 5XY2
+    let detection = detect(&[0x12, 0x00, 0x51, 0x22]);
+    assert_eq!(detection.platform, Platform::Chip8);
+    assert_eq!(detection.evidence.len(), 1);
+    assert_eq!(detection.evidence[0].confidence, Confidence::Low);
+
+    let detection = detect(&vec![0x12; 0xE02]);
+    assert_eq!(detection.platform, Platform::XoChip);
+    assert_eq!(detection.confidence, Confidence::High);
+}
+
+#[test]
+fn odd_alignment() {
+    // JP 0x203; HIGH at 0x203 after a byte of padding; JP 0x205
+    let detection = detect(&[0x12, 0x03, 0xFF, 0x00, 0xFF, 0x12, 0x05]);
+    assert_eq!(detection.platform, Platform::Schip);
+    assert_eq!(detection.confidence, Confidence::High);
+    assert_eq!(
+        detection.evidence[0].reason,
+        "00ff high resolution in code at 0x203"
+    );
+
+    // JP 0x203; LD V0, 0x00; LD VB, DT; JP 0x207, where the bytes at 0x204
+    // only look like 00FB from an even address
+    let rom = [0x12, 0x03, 0x00, 0x60, 0x00, 0xFB, 0x07, 0x12, 0x07];
+    let detection = detect(&rom);
+    assert_eq!(detection.platform, Platform::Chip8);
+    assert_eq!(detection.confidence, Confidence::High);
+    assert!(detection.evidence.is_empty(), "{}", detection);
+}
@@ -0,0 +1,75 @@
+use chip8::assembler::Assembler;
+use chip8::disassembler::disassemble;
+
+const SOURCE: &str = "
+    start:  CALL draw
+            SE V0, 0x01
+            JP start
+    loop:   JP loop
+    draw:   LD I, sprite
+            DRW V0, V1, 2
+            RET
+    sprite: DB 0x3C, 0x42, 0xFF
+";
+
+#[test]
+fn listing() {
+    let rom = Assembler::assemble_source(SOURCE).unwrap();
+    let listing = disassemble(&rom);
+    let expected = [
+        "; 17 bytes",
+        "L200:",
+        "    CALL sub_208                             ; 0x200  2208",
+        "    SE V0, 0x01                              ; 0x202  3001",
+        "    JP L200                                  ; 0x204  1200",
+        "L206:",
+        "    JP L206                                  ; 0x206  1206",
+        "sub_208:",
+        "    LD I, 0x20e                              ; 0x208  a20e",
+        "    DRW V0, V1, 2                            ; 0x20a  d012",
+        "    RET                                      ; 0x20c  00ee",
+        "    DB 0x3c, 0x42, 0xff                      ; 0x20e  3c42ff",
+    ];
+    assert_eq!(listing.lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn round_trip() {
+    // Assembling the listing gives back the ROM it was made from
+    let rom = Assembler::assemble_source(SOURCE).unwrap();
+    let listing = disassemble(&rom);
+    assert_eq!(Assembler::assemble_source(&listing).unwrap(), rom);
+    assert_eq!(
+        disassemble(&Assembler::assemble_source(&listing).unwrap()),
+        listing
+    );
+}
+
+#[test]
+fn data() {
+    // Unreachable bytes are written 8 to a line, and so are opcodes that
+    // do not decode
+    let mut rom = vec![0x12, 0x0C]; // JP 0x20c
+    rom.extend_from_slice(&[0xAA; 10]);
+    rom.extend_from_slice(&[0x60, 0x01, 0xFF, 0xFF, 0x00]);
+    let listing = disassemble(&rom);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines[2], "    DB 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa ; 0x202  aaaaaaaaaaaaaaaa");
+    assert_eq!(
+        lines[3],
+        "    DB 0xaa, 0xaa                            ; 0x20a  aaaa"
+    );
+    assert_eq!(lines[4], "L20c:");
+    assert_eq!(
+        lines[5],
+        "    LD V0, 0x01                              ; 0x20c  6001"
+    );
+    assert_eq!(
+        lines[6],
+        "    DB 0xff, 0xff, 0x00                      ; 0x20e  ffff00"
+    );
+    assert_eq!(lines.len(), 7);
+    assert_eq!(Assembler::assemble_source(&listing).unwrap(), rom);
+
+    assert_eq!(disassemble(&[]), "; 0 bytes\n");
+}
@@ -0,0 +1,108 @@
+use chip8::interpreter::chip8::{Chip8, Fault};
+use chip8::interpreter::events::Event;
+use chip8::interpreter::instruction::Instruction;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[rustfmt::skip]
+const ROM: [u8; 16] = [
+    0x00, 0xE0, // 0x200  CLS
+    0x60, 0x05, // 0x202  LD V0, 0x05
+    0xF0, 0x18, // 0x204  LD ST, V0
+    0xA3, 0x00, // 0x206  LD I, 0x300
+    0xF0, 0x55, // 0x208  LD [I], V0
+    0xD0, 0x01, // 0x20a  DRW V0, V0, 1
+    0xF1, 0x0A, // 0x20c  LD V1, K
+    0x00, 0xEE, // 0x20e  RET, with nothing to return to
+];
+
+// record adds a hook that records every event but Executed, and returns
+// the events with the number of instructions executed.
+fn record(c8: &mut Chip8) -> Rc<RefCell<(Vec<Event>, usize)>> {
+    let events = Rc::new(RefCell::new((Vec::new(), 0)));
+    let recorded = Rc::clone(&events);
+    c8.add_hook(move |event| {
+        let mut recorded = recorded.borrow_mut();
+        match event {
+            Event::Executed { .. } => recorded.1 += 1,
+            _ => recorded.0.push(*event),
+        }
+    });
+    events
+}
+
+#[test]
+fn events() {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&ROM).unwrap();
+    c8.watch(0x300..0x301);
+    let events = record(&mut c8);
+
+    c8.run_frame(10).unwrap();
+    assert_eq!(
+        events.borrow().0,
+        [
+            Event::DisplayCleared,
+            Event::SoundStarted,
+            Event::MemoryWritten {
+                addr: 0x300,
+                byte: 0x05
+            },
+            Event::SpriteDrawn {
+                x: 5,
+                y: 5,
+                rows: 1,
+                collision: false
+            },
+            Event::WaitingForKey { register: 1 },
+            Event::FrameCompleted,
+        ]
+    );
+    assert_eq!(events.borrow().1, 10);
+
+    // The sound stops when the timer runs out, while still waiting
+    events.borrow_mut().0.clear();
+    for _ in 0..4 {
+        c8.run_frame(1).unwrap();
+    }
+    assert_eq!(
+        events.borrow().0[3..],
+        [Event::SoundStopped, Event::FrameCompleted]
+    );
+
+    events.borrow_mut().0.clear();
+    c8.set_key(3, true);
+    let fault = Fault::StackUnderflow(0x20e);
+    assert_eq!(c8.run_frame(10), Err(fault));
+    assert_eq!(events.borrow().0, [Event::Faulted(fault)]);
+    assert_eq!(c8.register(1), 3);
+}
+
+#[test]
+fn executed() {
+    let mut c8 = Chip8::new();
+    c8.enable_blocks();
+    c8.load_rom_bytes(&ROM).unwrap();
+    let executed = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&executed);
+    let id = c8.add_hook(move |event| {
+        if let Event::Executed { pc, instruction } = event {
+            recorded.borrow_mut().push((*pc, *instruction));
+        }
+    });
+
+    // Hooks see every instruction, even with blocks enabled
+    c8.run_frame(2).unwrap();
+    assert_eq!(
+        *executed.borrow(),
+        [
+            (0x200, Instruction::I00E0),
+            (0x202, Instruction::I6XNN(0, 5))
+        ]
+    );
+
+    assert!(c8.remove_hook(id));
+    assert!(!c8.remove_hook(id));
+    c8.run_frame(2).unwrap();
+    assert_eq!(executed.borrow().len(), 2);
+}
@@ -0,0 +1,238 @@
+use chip8::arithmetic;
+use chip8::interpreter::chip8::{Chip8, Fault, KEYS, N_KEYS};
+use chip8::interpreter::quirks::Quirks;
+
+// run runs a ROM for a number of instructions with every quirk off.
+fn run(rom: &[u8], instructions: usize) -> Chip8 {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(rom).unwrap();
+    c8.quirks = Quirks::default();
+    for _ in 0..instructions {
+        c8.cycle().unwrap();
+    }
+    c8
+}
+
+#[test]
+fn oversized_roms() {
+    let mut c8 = Chip8::new();
+    assert!(c8.load_rom_bytes(&vec![0x00; 0xE01]).is_err());
+    assert!(c8.load_rom_bytes(&vec![0x00; 0xE00]).is_ok());
+}
+
+#[test]
+fn frames_tick_the_timers() {
+    // LD V0, 3; LD DT, V0, then a frame later LD V1, DT
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&[0x60, 0x03, 0xF0, 0x15, 0xF1, 0x07])
+        .unwrap();
+    c8.run_frame(2).unwrap();
+    c8.run_frame(1).unwrap();
+    assert_eq!(c8.register(1), 2);
+}
+
+#[test]
+fn keys() {
+    // LD V0, 5; SKP V0; LD V1, 1; SKNP V0; LD V2, 1
+    let rom = [0x60, 0x05, 0xE0, 0x9E, 0x61, 0x01, 0xE0, 0xA1, 0x62, 0x01];
+    let c8 = run(&rom, 4);
+    assert_eq!((c8.register(1), c8.register(2)), (1, 0));
+
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.set_key(0x5, true);
+    for _ in 0..4 {
+        c8.cycle().unwrap();
+    }
+    assert_eq!((c8.register(1), c8.register(2)), (0, 1));
+}
+
+#[test]
+fn seeded_random_numbers() {
+    // RND V0, 0xff; RND V1, 0x0f
+    let random = |seed| {
+        let mut c8 = Chip8::new();
+        c8.load_rom_bytes(&[0xC0, 0xFF, 0xC1, 0x0F]).unwrap();
+        c8.seed(seed);
+        c8.run_frame(2).unwrap();
+        (c8.register(0), c8.register(1))
+    };
+    assert_eq!(random(1), random(1));
+    assert!(random(1).1 <= 0x0F);
+}
+
+#[test]
+fn stack_holds_addresses() {
+    // Returning from a call at 0x2fe goes to 0x300, past 8 bits
+    let mut rom = vec![0; 0x112];
+    rom[0x000..0x002].copy_from_slice(&[0x12, 0xFE]); // 0x200  JP 0x2fe
+    rom[0x0FE..0x100].copy_from_slice(&[0x23, 0x10]); // 0x2fe  CALL 0x310
+    rom[0x100..0x102].copy_from_slice(&[0x60, 0x42]); // 0x300  LD V0, 0x42
+    rom[0x110..0x112].copy_from_slice(&[0x00, 0xEE]); // 0x310  RET
+    let c8 = run(&rom, 4);
+    assert_eq!(c8.register(0), 0x42);
+}
+
+#[test]
+fn stack_faults() {
+    // CALL 0x200 forever overflows the 12 levels of the stack
+    let mut c8 = run(&[0x22, 0x00], 12);
+    assert_eq!(c8.cycle(), Err(Fault::StackOverflow(0x200)));
+
+    // RET with nothing on the stack underflows it
+    let mut c8 = run(&[0x00, 0xEE], 0);
+    assert_eq!(c8.cycle(), Err(Fault::StackUnderflow(0x200)));
+}
+
+#[test]
+fn arithmetic_wraps() {
+    // LD V0, 0xff; ADD V0, 0x02 wraps and leaves VF alone
+    let c8 = run(&[0x60, 0xFF, 0x70, 0x02], 2);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x01, 0));
+
+    // LD V0, 0xff; LD V1, 0x02; ADD V0, V1 wraps and carries
+    let c8 = run(&[0x60, 0xFF, 0x61, 0x02, 0x80, 0x14], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x01, 1));
+
+    // LD V0, 0x01; LD V1, 0x02; SUB V0, V1 wraps and borrows
+    let c8 = run(&[0x60, 0x01, 0x61, 0x02, 0x80, 0x15], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0xFF, 0));
+
+    // LD I, 0xfff; LD V0, 0xf0; 256 * ADD I, V0 leaves I at 0xffff, and
+    // LD V0, 0x02; ADD I, V0 wraps it around 16 bits
+    let mut rom = vec![0xAF, 0xFF, 0x60, 0xF0];
+    for _ in 0..256 {
+        rom.extend_from_slice(&[0xF0, 0x1E]);
+    }
+    rom.extend_from_slice(&[0x60, 0x02, 0xF0, 0x1E]);
+    let mut c8 = run(&rom, 258);
+    assert_eq!(c8.index(), 0xFFFF);
+    c8.cycle().unwrap();
+    c8.cycle().unwrap();
+    assert_eq!(c8.index(), 0x0001);
+}
+
+#[test]
+fn subtract_borrows_only_below() {
+    // LD V0, 5; LD V1, 5; SUB V0, V1 does not borrow
+    let c8 = run(&[0x60, 0x05, 0x61, 0x05, 0x80, 0x15], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x00, 1));
+
+    // LD V0, 3; LD V1, 5; SUB V0, V1 does
+    let c8 = run(&[0x60, 0x03, 0x61, 0x05, 0x80, 0x15], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0xFE, 0));
+
+    assert_eq!(arithmetic::check_borrow(&5, &5), 1);
+    assert_eq!(arithmetic::check_borrow(&6, &5), 1);
+    assert_eq!(arithmetic::check_borrow(&4, &5), 0);
+}
+
+#[test]
+fn reverse_subtract_borrows_when_vy_is_below() {
+    // LD V0, 3; LD V1, 5; SUBN V0, V1 is 5 - 3, with no borrow
+    let c8 = run(&[0x60, 0x03, 0x61, 0x05, 0x80, 0x17], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x02, 1));
+
+    // LD V0, 5; LD V1, 3; SUBN V0, V1 is 3 - 5, which borrows
+    let c8 = run(&[0x60, 0x05, 0x61, 0x03, 0x80, 0x17], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0xFE, 0));
+
+    // LD V0, 4; LD V1, 4; SUBN V0, V1 does not borrow
+    let c8 = run(&[0x60, 0x04, 0x61, 0x04, 0x80, 0x17], 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x00, 1));
+}
+
+#[test]
+fn wait_for_key() {
+    // LD V3, K waits by running again until a key is down, then LD V4, 1
+    let mut c8 = run(&[0xF3, 0x0A, 0x64, 0x01], 5);
+    assert_eq!(c8.register(4), 0);
+    c8.set_key(0x7, true);
+    c8.cycle().unwrap();
+    assert_eq!(c8.register(3), 0x7);
+    c8.cycle().unwrap();
+    assert_eq!(c8.register(4), 1);
+}
+
+#[test]
+fn jump_with_offset_lands_on_target() {
+    // LD V0, 4; JP V0, 0x204 goes to 0x208, not past it
+    let rom = [0x60, 0x04, 0xB2, 0x04, 0, 0, 0, 0, 0x61, 0x01, 0x62, 0x01];
+    let c8 = run(&rom, 3);
+    assert_eq!((c8.register(1), c8.register(2)), (1, 0));
+}
+
+#[test]
+fn font_sprites() {
+    // LD V0, 0x0a; LD F, V0 points I at the sprite of A
+    let c8 = run(&[0x60, 0x0A, 0xF0, 0x29], 2);
+    assert_eq!(c8.index(), 0x0A * 5);
+    let sprite: Vec<u8> = (0..5).map(|i| c8.peek(c8.index() + i)).collect();
+    assert_eq!(sprite, [0xF0, 0x90, 0xF0, 0x90, 0x90]);
+
+    // Only the low nibble of VX counts
+    let c8 = run(&[0x60, 0x37, 0xF0, 0x29], 2);
+    assert_eq!(c8.index(), 0x07 * 5);
+}
+
+// lit returns the pixels that are on, as (x, y), row by row.
+fn lit(c8: &Chip8) -> Vec<(usize, usize)> {
+    c8.display().lit().collect()
+}
+
+#[test]
+fn draw_collisions() {
+    // LD VF, 1; LD I, 0 points at the sprite of 0; DRW V0, V0, 5 twice
+    let mut c8 = run(&[0x6F, 0x01, 0xA0, 0x00, 0xD0, 0x05, 0xD0, 0x05], 3);
+    assert_eq!(c8.register(0xF), 0);
+    assert!(lit(&c8).contains(&(0, 0)));
+    c8.cycle().unwrap();
+    assert_eq!(c8.register(0xF), 1);
+    assert_eq!(lit(&c8), []);
+}
+
+#[test]
+fn draw_wraps() {
+    // LD V0, 62; LD V1, 31; LD I, 0; DRW V0, V1, 2 wraps around both edges
+    let c8 = run(&[0x60, 62, 0x61, 31, 0xA0, 0x00, 0xD0, 0x12], 4);
+    let wrapped = [(1, 0), (62, 0), (0, 31), (1, 31), (62, 31), (63, 31)];
+    assert_eq!(lit(&c8), wrapped);
+    assert_eq!(c8.register(0xF), 0);
+}
+
+#[test]
+fn keypad_layout() {
+    // Every key of the hex keypad is on it once
+    let mut keys = KEYS.to_vec();
+    keys.sort();
+    assert_eq!(keys, (0..N_KEYS as u8).collect::<Vec<u8>>());
+
+    // Row by row, as on the COSMAC VIP
+    assert_eq!(KEYS[..4], [0x1, 0x2, 0x3, 0xC]);
+    assert_eq!(KEYS[8..12], [0x7, 0x8, 0x9, 0xE]);
+}
+
+#[test]
+fn memory_wraps() {
+    // LD I, 0xfff; LD V0, 0xf0; 256 * ADD I, V0 leaves I at 0xffff, where
+    // LD [I], V0 stores at the end of memory and wraps I around
+    let mut rom = vec![0xAF, 0xFF, 0x60, 0xF0];
+    rom.extend([0xF0, 0x1E].repeat(256));
+    rom.extend_from_slice(&[0xF0, 0x55]);
+    let c8 = run(&rom, 259);
+    assert_eq!(c8.index(), 0x0000);
+    assert_eq!(c8.peek(0xFFF), 0xF0);
+
+    // So does LD V1, [I] from 0xfffe, after LD I, 0xffe and 256 * ADD I, V0
+    rom.extend_from_slice(&[0xAF, 0xFE]);
+    rom.extend([0xF0, 0x1E].repeat(256));
+    rom.extend_from_slice(&[0xF1, 0x65]);
+    let c8 = run(&rom, 517);
+    assert_eq!(c8.index(), 0x0000);
+    assert_eq!(c8.register(1), 0xF0);
+
+    // LD B, V0 at the end of memory writes its last digits at the start
+    let c8 = run(&[0xAF, 0xFF, 0x60, 0xF0, 0xF0, 0x33], 3);
+    assert_eq!(c8.peek(0xFFF), 2);
+    assert_eq!((c8.peek(0), c8.peek(1)), (4, 0));
+}
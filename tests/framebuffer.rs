@@ -0,0 +1,92 @@
+use chip8::interpreter::framebuffer::{Edge, HiRes, LoRes};
+
+// naive_blit draws a sprite one pixel at a time, as a reference.
+fn naive_blit(
+    pixels: &mut [bool],
+    (w, h): (usize, usize),
+    (x, y): (usize, usize),
+    sprite: &[u8],
+    edge: Edge,
+) -> bool {
+    let mut collision = false;
+    for (row, byte) in sprite.iter().enumerate() {
+        for bit in 0..8 {
+            let (px, py) = (x % w + bit, y % h + row);
+            if edge == Edge::Clip && (px >= w || py >= h) {
+                continue;
+            }
+            if byte & (0x80 >> bit) != 0 {
+                let i = (py % h) * w + px % w;
+                collision |= pixels[i];
+                pixels[i] = !pixels[i];
+            }
+        }
+    }
+    collision
+}
+
+#[test]
+fn matches_reference() {
+    let mut seed: u32 = 1;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed
+    };
+    for &edge in &[Edge::Clip, Edge::Wrap] {
+        let mut display = LoRes::new();
+        let mut pixels = vec![false; 64 * 32];
+        for _ in 0..500 {
+            let (x, y) = (next() as usize % 256, next() as usize % 256);
+            let sprite: Vec<u8> =
+                (0..next() % 16).map(|_| next() as u8).collect();
+            let expected =
+                naive_blit(&mut pixels, (64, 32), (x, y), &sprite, edge);
+            assert_eq!(
+                display.blit(x, y, sprite.iter().copied(), edge),
+                expected
+            );
+            assert!(display.pixels().eq(pixels.iter().copied()));
+        }
+    }
+}
+
+#[test]
+fn edges() {
+    let mut display = LoRes::new();
+    assert!(!display.blit(60, 30, vec![0xFF, 0xFF, 0xFF], Edge::Clip));
+    assert_eq!(display.lit().count(), 8);
+    assert!(display.pixel(63, 31) && !display.pixel(0, 31));
+
+    display.clear();
+    display.blit(60, 31, vec![0xFF, 0xFF], Edge::Wrap);
+    assert_eq!(display.lit().count(), 16);
+    assert!(display.pixel(3, 0) && display.pixel(63, 31));
+    assert!(display.blit(124, 63, vec![0x80], Edge::Wrap));
+    assert!(!display.pixel(60, 31));
+}
+
+#[test]
+fn hi_res() {
+    let mut display = HiRes::new();
+    assert_eq!((HiRes::WIDTH, HiRes::HEIGHT), (128, 64));
+    display.blit(124, 0, vec![0xFF], Edge::Wrap);
+    assert_eq!(
+        display.lit().collect::<Vec<_>>(),
+        vec![
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (3, 0),
+            (124, 0),
+            (125, 0),
+            (126, 0),
+            (127, 0)
+        ]
+    );
+    display.set(127, 0, false);
+    display.set(5, 63, true);
+    assert_eq!(display.rows()[0], 0xF << 124 | 0xE);
+    assert!(display.pixel(5, 63));
+}
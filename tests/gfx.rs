@@ -0,0 +1,11 @@
+#![cfg(feature = "piston")]
+
+use chip8::gfx::square;
+
+#[test]
+fn squares() {
+    // Columns go across the window and rows down it
+    assert_eq!(square(0, 0, 20.0), [0.0, 0.0, 20.0, 20.0]);
+    assert_eq!(square(63, 1, 20.0), [1260.0, 20.0, 20.0, 20.0]);
+    assert_eq!(square(2, 31, 10.0), [20.0, 310.0, 10.0, 10.0]);
+}
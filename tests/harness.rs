@@ -0,0 +1,34 @@
+#[cfg(feature = "rand")]
+use chip8::harness;
+use chip8::harness::Manifest;
+#[cfg(feature = "rand")]
+use std::fs;
+
+// Every manifest in tests/roms passes. Their seeds were recorded with the
+// generator of rand.
+#[cfg(feature = "rand")]
+#[test]
+fn rom_manifests() {
+    let mut ran = 0;
+    for entry in fs::read_dir("tests/roms").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("manifest") {
+            continue;
+        }
+        let filename = path.to_string_lossy();
+        let manifest = Manifest::load(&filename).unwrap();
+        let outcome = harness::run(&manifest).unwrap();
+        assert!(outcome.passed(), "{}: {:?}", filename, outcome);
+        ran += 1;
+    }
+    assert!(ran > 0);
+}
+
+#[test]
+fn manifest_errors() {
+    let dir = std::path::Path::new("");
+    let err = Manifest::parse("rom a.bin\nfrobnicate 3\n", dir).unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(Manifest::parse("frames 3\n", dir).is_err());
+    assert!(Manifest::parse("rom a.bin\nexpect VG 1\n", dir).is_err());
+}
@@ -0,0 +1,155 @@
+use chip8::interpreter::instruction::{ErrUnsupportedInstruction, Instruction};
+use chip8::interpreter::metadata::{Access, Flow};
+use chip8::interpreter::quirks::Quirks;
+use std::convert::TryFrom;
+
+// Every opcode either fails to decode or encodes back to itself.
+#[test]
+fn round_trip_all_opcodes() {
+    let mut decoded = 0;
+    for opcode in 0..=u16::MAX {
+        match Instruction::try_from(opcode) {
+            Ok(instr) => {
+                assert_eq!(
+                    instr.encode(),
+                    opcode,
+                    "0x{:04x} decoded to {:?}",
+                    opcode,
+                    instr
+                );
+                assert_eq!(Instruction::try_from(instr.encode()), Ok(instr));
+                decoded += 1;
+            }
+            Err(e) => assert_eq!(e, ErrUnsupportedInstruction(opcode)),
+        }
+    }
+
+    // 0NNN, 1NNN, 2NNN, ANNN, BNNN: 5 * 4096
+    // 3XNN, 4XNN, 6XNN, 7XNN, CXNN: 5 * 4096
+    // DXYN: 4096
+    // 5XY0, 9XY0, 8XY_ (9 variants): 11 * 256
+    // EX__ (2 variants), FX__ (9 variants): 11 * 16
+    let expected = 11 * 4096 + 11 * 256 + 11 * 16;
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn decode_rejects_unknown_opcodes() {
+    for &opcode in &[0x5121, 0x8AB8, 0x912F, 0xE1FF, 0xF2FF] {
+        assert_eq!(
+            Instruction::try_from(opcode),
+            Err(ErrUnsupportedInstruction(opcode))
+        );
+    }
+}
+
+#[test]
+fn encode_known_instructions() {
+    assert_eq!(Instruction::I00E0.encode(), 0x00E0);
+    assert_eq!(Instruction::I6XNN(0xA, 0x02).encode(), 0x6A02);
+    assert_eq!(Instruction::IDXYN(0xA, 0xB, 6).encode(), 0xDAB6);
+    assert_eq!(Instruction::IEXA1(0x0).encode(), 0xE0A1);
+    assert_eq!(Instruction::IFX33(0xE).encode(), 0xFE33);
+}
+
+// Every decoded instruction matches the opcode pattern of its metadata entry.
+#[test]
+fn metadata_matches_opcode_pattern() {
+    for opcode in 0..=u16::MAX {
+        if let Ok(instr) = Instruction::try_from(opcode) {
+            let hex = format!("{:04X}", opcode);
+            let pattern = instr.metadata().opcode;
+            for (h, p) in hex.chars().zip(pattern.chars()) {
+                assert!("XYN".contains(p) || h == p, "{} vs {}", hex, pattern);
+            }
+        }
+    }
+}
+
+#[test]
+fn metadata_registers_and_display() {
+    let add = Instruction::I8XY4(0x3, 0x5);
+    assert_eq!(add.reads().v, (1 << 0x3) | (1 << 0x5));
+    assert_eq!(add.writes().v, (1 << 0x3) | (1 << 0xF));
+    assert_eq!(add.to_string(), "ADD V3, V5");
+
+    let store = Instruction::IFX55(0x2);
+    assert_eq!(store.reads().v, 0b111);
+    assert!(store.reads().i && store.writes().i);
+    assert_eq!(store.metadata().memory, Access::Write);
+    assert_eq!(store.to_string(), "LD [I], V2");
+
+    assert_eq!(Instruction::IANNN(0x2EA).to_string(), "LD I, 0x2ea");
+    assert_eq!(Instruction::I2NNN(0x2D4).metadata().flow, Flow::Call);
+    assert!(Instruction::I3XNN(0, 0).metadata().flow.branches());
+    assert_eq!(Instruction::I6XNN(0, 0).metadata().cycles, 6);
+}
+
+// The registers an instruction uses follow the quirks.
+#[test]
+fn metadata_with_quirks() {
+    let none = Quirks::default();
+    let schip = Quirks::SCHIP;
+
+    let shift = Instruction::I8XY6(0x3, 0x5);
+    assert_eq!(shift.reads_with(&none).v, 1 << 0x5);
+    assert_eq!(shift.reads_with(&schip).v, 1 << 0x3);
+
+    let or = Instruction::I8XY1(0x3, 0x5);
+    assert_eq!(or.writes_with(&none).v, 1 << 0x3);
+    assert_eq!(or.writes_with(&Quirks::VIP).v, (1 << 0x3) | (1 << 0xF));
+
+    let jump = Instruction::IBNNN(0x2A0);
+    assert_eq!(jump.reads_with(&none).v, 1);
+    assert_eq!(jump.reads_with(&schip).v, 1 << 0x2);
+
+    let store = Instruction::IFX55(0x2);
+    assert!(store.writes_with(&none).i);
+    assert!(!store.writes_with(&schip).i);
+    let load = Instruction::IFX65(0x2);
+    assert_eq!(load.writes_with(&schip).v, 0b111);
+    assert!(!load.writes_with(&schip).i);
+
+    assert_eq!(jump.metadata_with(&schip).cycles, jump.metadata().cycles);
+}
+
+// 0NNN, a call to a machine code routine, decodes instead of failing.
+#[test]
+fn decode_0nnn() {
+    assert_eq!(Instruction::try_from(0x0000), Ok(Instruction::I0NNN(0x000)));
+    assert_eq!(Instruction::try_from(0x02A0), Ok(Instruction::I0NNN(0x2A0)));
+    assert_eq!(Instruction::try_from(0x00E0), Ok(Instruction::I00E0));
+    assert_eq!(Instruction::try_from(0x00EE), Ok(Instruction::I00EE));
+}
+
+// EX9E and EXA1 are told apart by their whole low byte, not its low nibble.
+#[test]
+fn decode_ex9e_exa1() {
+    assert_eq!(Instruction::try_from(0xE39E), Ok(Instruction::IEX9E(0x3)));
+    assert_eq!(Instruction::try_from(0xE3A1), Ok(Instruction::IEXA1(0x3)));
+    for &opcode in &[0xE30E, 0xE3FE, 0xE301, 0xE391] {
+        assert_eq!(
+            Instruction::try_from(opcode),
+            Err(ErrUnsupportedInstruction(opcode))
+        );
+    }
+}
+
+// 5XY0 and 9XY0 need their last nibble to be 0.
+#[test]
+fn decode_5xy0_9xy0() {
+    assert_eq!(
+        Instruction::try_from(0x5AB0),
+        Ok(Instruction::I5XY0(0xA, 0xB))
+    );
+    assert_eq!(
+        Instruction::try_from(0x9AB0),
+        Ok(Instruction::I9XY0(0xA, 0xB))
+    );
+    for &opcode in &[0x5AB1, 0x9ABF] {
+        assert_eq!(
+            Instruction::try_from(opcode),
+            Err(ErrUnsupportedInstruction(opcode))
+        );
+    }
+}
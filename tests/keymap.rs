@@ -0,0 +1,31 @@
+use chip8::keymap::Keymap;
+
+#[test]
+fn parse() {
+    assert_eq!(Keymap::parse("1234qwerasdfzxcv"), Some(Keymap::DEFAULT));
+    assert_eq!(Keymap::parse("1234QWERasdfZXCV"), Some(Keymap::DEFAULT));
+    let keymap = Keymap::parse("7890uiopjkl;m,./").unwrap();
+    assert_eq!(keymap.0[15], '/');
+}
+
+#[test]
+fn rejected() {
+    // Keys are 16 distinct characters, whatever their case
+    assert_eq!(Keymap::parse(""), None);
+    assert_eq!(Keymap::parse("1234qwerasdfzxc"), None);
+    assert_eq!(Keymap::parse("1234qwerasdfzxcvb"), None);
+    assert_eq!(Keymap::parse("1234qwerasdfzxc1"), None);
+    assert_eq!(Keymap::parse("1234qwerasdfzxcQ"), None);
+}
+
+#[test]
+fn keys() {
+    // The keyboard is laid out like the keypad, row by row
+    let keymap = Keymap::default();
+    assert_eq!(keymap.key('1'), Some(0x1));
+    assert_eq!(keymap.key('r'), Some(0xD));
+    assert_eq!(keymap.key('S'), Some(0x8));
+    assert_eq!(keymap.key('x'), Some(0x0));
+    assert_eq!(keymap.key('v'), Some(0xF));
+    assert_eq!(keymap.key('p'), None);
+}
@@ -0,0 +1,31 @@
+use chip8::phosphor::Phosphor;
+use chip8::screenshot::Palette;
+
+#[test]
+fn decay() {
+    let mut phosphor = Phosphor::new(3);
+    phosphor.update(vec![true, false]);
+    assert_eq!(phosphor.levels(), &[1.0, 0.0]);
+    let mut faded = Vec::new();
+    for _ in 0..4 {
+        phosphor.update(vec![false, false]);
+        faded.push(phosphor.levels()[0]);
+    }
+    assert_eq!(faded, vec![0.75, 0.5, 0.25, 0.0]);
+
+    // Without decay pixels go out at once
+    let mut phosphor = Phosphor::new(0);
+    phosphor.update(vec![true]);
+    phosphor.update(vec![false]);
+    assert_eq!(phosphor.levels(), &[0.0]);
+}
+
+#[test]
+fn palettes() {
+    let green = Palette::parse("Green").unwrap();
+    assert_eq!(green, Palette::parse("33ff66,001000").unwrap());
+    assert_eq!(green.blend(1.0), green.on);
+    assert_eq!(green.blend(0.0), green.off);
+    assert_eq!(Palette::DEFAULT.blend(0.5), [0x80; 3]);
+    assert!(Palette::parse("mauve").is_none());
+}
@@ -0,0 +1,117 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::interpreter::instruction::Instruction;
+use chip8::interpreter::profiler::{Profiler, Subroutine, MAX_EVENTS};
+
+// profiled returns the profile of the first 10 instructions of
+//
+//   0x200  CALL 0x206
+//   0x202  CALL 0x206
+//   0x204  JP 0x204
+//   0x206  LD V0, 0x01
+//   0x208  RET
+//
+// both calls, then the jump 4 times. CALL, RET and JP take 105 us, LD 27 us.
+fn profiled() -> Profiler {
+    let mut profiler = Profiler::new();
+    for &pc in &[0x200, 0x202] {
+        profiler.record(pc, &Instruction::I2NNN(0x206));
+        profiler.record(0x206, &Instruction::I6XNN(0, 0x01));
+        profiler.record(0x208, &Instruction::I00EE);
+    }
+    for _ in 0..4 {
+        profiler.record(0x204, &Instruction::I1NNN(0x204));
+    }
+    profiler
+}
+
+// A machine profiles every instruction it runs.
+#[test]
+fn machine() {
+    let rom = [0x22, 0x06, 0x22, 0x06, 0x12, 0x04, 0x60, 0x01, 0x00, 0xEE];
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&rom).unwrap();
+    c8.enable_profiler();
+    c8.run_frame(10).unwrap();
+    let (profiler, expected) = (c8.profiler().unwrap(), profiled());
+    assert_eq!(profiler.report(), expected.report());
+    assert_eq!(profiler.chrome_trace(), expected.chrome_trace());
+}
+
+#[test]
+fn counts() {
+    let profiler = profiled();
+    assert_eq!(profiler.micros, 894);
+    assert_eq!(profiler.by_address[0x204], 4);
+    assert_eq!(profiler.by_address[0x206], 2);
+    assert_eq!(profiler.by_kind[Instruction::I2NNN(0).id()], 2);
+    assert_eq!(profiler.by_kind[Instruction::I00EE.id()], 2);
+    assert_eq!(
+        profiler.subroutines[&0x206],
+        Subroutine {
+            calls: 2,
+            inclusive: 264,
+            exclusive: 264,
+        }
+    );
+    assert_eq!(profiler.subroutines[&0x200].exclusive, 630);
+}
+
+#[test]
+fn report() {
+    let report = profiled().report();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "10 instructions, 894 us");
+    // The hottest address first
+    assert_eq!(lines[4], "  0x204        4  40.00%  JP 0x204");
+    let expected = [
+        "  1NNN  JP              4        420  46.98%",
+        "  0x200        1        894        630  70.47%",
+        "  0x206        2        264        264  29.53%",
+    ];
+    for line in &expected {
+        assert!(lines.contains(line), "{} in\n{}", line, report);
+    }
+}
+
+#[test]
+fn chrome_trace() {
+    let trace = profiled().chrome_trace();
+    let event = |ph: &str, entry: u16, ts: u64| {
+        format!(
+            "{{\"name\":\"sub_{:03x}\",\"cat\":\"chip8\",\"ph\":\"{}\",\"ts\":{},\"pid\":1,\"tid\":1}}",
+            entry, ph, ts
+        )
+    };
+    let events = [
+        event("B", 0x200, 0),
+        event("B", 0x206, 105),
+        event("E", 0x206, 237),
+        event("B", 0x206, 342),
+        event("E", 0x206, 474),
+        event("E", 0x200, 894), // Still running
+    ];
+    assert_eq!(
+        trace,
+        format!("{{\"traceEvents\":[\n{}\n]}}\n", events.join(",\n"))
+    );
+}
+
+#[test]
+fn bounded_events() {
+    // Calls past MAX_EVENTS are counted but left out of the trace, and
+    // every call in it returns
+    let mut profiler = Profiler::new();
+    let calls = MAX_EVENTS / 2 + 10;
+    for _ in 0..calls {
+        profiler.record(0x200, &Instruction::I2NNN(0x300));
+        profiler.record(0x300, &Instruction::I00EE);
+    }
+    profiler.record(0x200, &Instruction::I2NNN(0x300));
+    assert_eq!(profiler.subroutines[&0x300].calls, calls as u64 + 1);
+    assert_eq!(profiler.untraced, 11);
+
+    let trace = profiler.chrome_trace();
+    let begins = trace.matches("\"ph\":\"B\"").count();
+    assert_eq!(begins, MAX_EVENTS / 2 + 1);
+    assert_eq!(trace.matches("\"ph\":\"E\"").count(), begins);
+}
@@ -0,0 +1,108 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::interpreter::quirks::Quirks;
+
+// run runs a ROM for a number of instructions with the given list of quirks.
+fn run(rom: &[u8], quirks: &str, instructions: usize) -> Chip8 {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(rom).unwrap();
+    c8.quirks = Quirks::parse(quirks).unwrap();
+    for _ in 0..instructions {
+        c8.cycle().unwrap();
+    }
+    c8
+}
+
+#[test]
+fn display() {
+    assert_eq!(Quirks::default().to_string(), "none");
+    assert_eq!(Quirks::VIP.to_string(), "vf-reset,clip");
+    assert_eq!(Quirks::SCHIP.to_string(), "shift,load-store,jump,clip");
+    let jump = Quirks {
+        jump: true,
+        ..Quirks::default()
+    };
+    assert_eq!(jump.to_string(), "jump");
+}
+
+#[test]
+fn round_trip() {
+    // Every combination of quirks parses back from its name
+    for bits in 0..32 {
+        let quirks = Quirks {
+            shift: bits & 1 != 0,
+            load_store: bits & 2 != 0,
+            jump: bits & 4 != 0,
+            vf_reset: bits & 8 != 0,
+            clip: bits & 16 != 0,
+        };
+        assert_eq!(Quirks::parse(&quirks.to_string()), Ok(quirks));
+    }
+}
+
+#[test]
+fn parse() {
+    assert_eq!(Quirks::parse(""), Ok(Quirks::default()));
+    assert_eq!(
+        Quirks::parse(" schip , -jump"),
+        Quirks::parse("shift,load-store,clip")
+    );
+    assert_eq!(Quirks::parse("vip,+shift,none"), Ok(Quirks::default()));
+    assert_eq!(Quirks::parse("clip,-clip"), Ok(Quirks::default()));
+
+    // Presets cannot be turned off
+    let err = Quirks::parse("schip,-vip").unwrap_err();
+    assert_eq!(err.0, "-vip");
+    assert!(err
+        .to_string()
+        .starts_with("unknown quirk '-vip', expected"));
+    assert!(Quirks::parse("Clip").is_err());
+}
+
+#[test]
+fn shift() {
+    // LD V0, 0x01; LD V1, 0x04; SHR V0, V1
+    let rom = [0x60, 0x01, 0x61, 0x04, 0x80, 0x16];
+    let c8 = run(&rom, "none", 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x02, 0));
+    let c8 = run(&rom, "shift", 3);
+    assert_eq!((c8.register(0), c8.register(0xF)), (0x00, 1));
+}
+
+#[test]
+fn load_store() {
+    // LD I, 0x300; LD [I], V1
+    let rom = [0xA3, 0x00, 0xF1, 0x55];
+    assert_eq!(run(&rom, "none", 2).index(), 0x302);
+    assert_eq!(run(&rom, "load-store", 2).index(), 0x300);
+}
+
+#[test]
+fn jump() {
+    // LD V0, 0x04; LD V2, 0x08; JP V0, 0x200
+    let rom = [0x60, 0x04, 0x62, 0x08, 0xB2, 0x00];
+    assert_eq!(run(&rom, "none", 3).pc(), 0x204);
+    assert_eq!(run(&rom, "jump", 3).pc(), 0x208);
+}
+
+#[test]
+fn vf_reset() {
+    // LD VF, 0x01; OR V0, V1
+    let rom = [0x6F, 0x01, 0x80, 0x11];
+    assert_eq!(run(&rom, "none", 2).register(0xF), 1);
+    assert_eq!(run(&rom, "vf-reset", 2).register(0xF), 0);
+}
+
+// lit returns the pixels that are on, as (x, y), row by row.
+fn lit(c8: &Chip8) -> Vec<(usize, usize)> {
+    c8.display().lit().collect()
+}
+
+#[test]
+fn clip() {
+    // LD V0, 62; LD I, 0; DRW V0, V0, 1 draws the top of a 0 at (62, 30)
+    let rom = [0x60, 62, 0xA0, 0x00, 0xD0, 0x01];
+    let c8 = run(&rom, "none", 3);
+    assert_eq!(lit(&c8), [(0, 30), (1, 30), (62, 30), (63, 30)]);
+    let c8 = run(&rom, "clip", 3);
+    assert_eq!(lit(&c8), [(62, 30), (63, 30)]);
+}
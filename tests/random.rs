@@ -0,0 +1,44 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::interpreter::random::Random;
+
+#[rustfmt::skip]
+const RANDOM: [u8; 4] = [
+    0xC0, 0xFF, // 0x200  RND V0, 0xff
+    0x12, 0x00, // 0x202  JP 0x200
+];
+
+// bytes returns the first n bytes of a generator.
+fn bytes(random: &mut Random, n: usize) -> Vec<u8> {
+    (0..n).map(|_| random.byte()).collect()
+}
+
+#[test]
+fn seeded() {
+    let first = bytes(&mut Random::seeded(1), 1000);
+    assert_eq!(first, bytes(&mut Random::seeded(1), 1000));
+    assert_ne!(first, bytes(&mut Random::seeded(2), 1000));
+
+    // The bytes are spread over every value
+    let mut seen = [false; 256];
+    for byte in &first {
+        seen[*byte as usize] = true;
+    }
+    assert!(seen.iter().filter(|seen| **seen).count() > 240);
+}
+
+#[test]
+fn seeded_machines_agree() {
+    let run = |seed| {
+        let mut c8 = Chip8::new();
+        c8.load_rom_bytes(&RANDOM).unwrap();
+        c8.seed(seed);
+        (0..100)
+            .map(|_| {
+                c8.run_frame(2).unwrap();
+                c8.register(0)
+            })
+            .collect::<Vec<u8>>()
+    };
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+}
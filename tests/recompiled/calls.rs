@@ -0,0 +1,146 @@
+// Recompiled from calls.ch8 by chip8 recompile.
+//
+// Load ROM into a machine and call run_frame instead of Chip8::run_frame:
+//
+//     let mut c8 = Chip8::new();
+//     c8.load_rom_bytes(&ROM)?;
+//     run_frame(&mut c8, 10)?;
+//
+// Every basic block is a function, which runs the block after it when that
+// is known from the ROM, and loops when it jumps back to its own start.
+// Elsewhere, such as after BNNN, the block at the program counter is looked
+// up. Blocks whose code was changed and addresses with no block are
+// interpreted.
+use chip8::interpreter::chip8::{Chip8, Fault, PROGRAM_START};
+use chip8::interpreter::instruction::Instruction::*;
+
+// Block is a recompiled basic block. It counts the instructions it runs off
+// what is left of the frame and returns what runs next.
+type Block = fn(&mut Chip8, &mut usize) -> Result<Next, Fault>;
+
+// Next is what runs after a block: a block and its number of instructions,
+// or the block at the program counter.
+enum Next {
+    Block(usize, Block),
+    Dispatch,
+}
+
+// ROM is the ROM, to be loaded at PROGRAM_START.
+pub const ROM: [u8; 16] = [
+    0x60, 0x00, 0x22, 0x0c, 0x22, 0x0c, 0x30, 0x04, 0x12, 0x02, 0x12, 0x0a,
+    0x70, 0x01, 0x00, 0xee,
+];
+
+// run_frame runs the given number of instructions and then counts the
+// timers down once, like Chip8::run_frame. A block that does not fit in
+// what is left of the frame, or whose code was changed, is interpreted
+// instead.
+pub fn run_frame(c8: &mut Chip8, instructions: usize) -> Result<(), Fault> {
+    let mut left = instructions;
+    let mut next = Next::Dispatch;
+    while left > 0 {
+        let found = match next {
+            Next::Block(len, run) => Some((len, run)),
+            Next::Dispatch => block(c8),
+        };
+        match found {
+            Some((len, run)) if len <= left && unchanged(c8, len) => {
+                next = run(c8, &mut left)?;
+            }
+            _ => {
+                c8.cycle()?;
+                left -= 1;
+                next = Next::Dispatch;
+            }
+        }
+    }
+    c8.tick();
+    Ok(())
+}
+
+// block returns the block at the program counter and its number of
+// instructions, if there is one.
+fn block(c8: &Chip8) -> Option<(usize, Block)> {
+    let block: (usize, Block) = match c8.pc() {
+        0x200 => (1, block_200),
+        0x202 => (1, block_202),
+        0x204 => (1, block_204),
+        0x206 => (1, block_206),
+        0x208 => (1, block_208),
+        0x20a => (1, block_20a),
+        0x20c => (2, block_20c),
+        _ => return None,
+    };
+    Some(block)
+}
+
+// unchanged returns whether the len instructions at the program counter are
+// still those of the ROM.
+fn unchanged(c8: &Chip8, len: usize) -> bool {
+    let start = c8.pc() as usize;
+    let end = start + 2 * len;
+    let rom = start - PROGRAM_START as usize..end - PROGRAM_START as usize;
+    c8.bus().bytes()[start..end] == ROM[rom]
+}
+
+// block_200 goes on to 0x202.
+fn block_200(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x0))?; // 0x200  LD V0, 0x00
+    *left -= 1;
+    Ok(Next::Block(1, block_202))
+}
+
+// block_202 goes on to 0x20c, returning to 0x204.
+fn block_202(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I2NNN(0x20c))?; // 0x202  CALL 0x20c
+    *left -= 1;
+    Ok(Next::Block(2, block_20c))
+}
+
+// block_204 goes on to 0x20c, returning to 0x206.
+fn block_204(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I2NNN(0x20c))?; // 0x204  CALL 0x20c
+    *left -= 1;
+    Ok(Next::Block(2, block_20c))
+}
+
+// block_206 goes on to 0x208 or 0x20a.
+fn block_206(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I3XNN(0x0, 0x4))?; // 0x206  SE V0, 0x04
+    *left -= 1;
+    Ok(match c8.pc() {
+        0x208 => Next::Block(1, block_208),
+        0x20a => Next::Block(1, block_20a),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_208 goes on to 0x202.
+fn block_208(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x202))?; // 0x208  JP 0x202
+    *left -= 1;
+    Ok(Next::Block(1, block_202))
+}
+
+// block_20a goes on to 0x20a.
+fn block_20a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    loop {
+        c8.execute(I1NNN(0x20a))?; // 0x20a  JP 0x20a
+        *left -= 1;
+        if *left < 1 {
+            return Ok(Next::Block(1, block_20a));
+        }
+    }
+}
+
+// block_20c is a subroutine and goes on to the caller.
+fn block_20c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0x0, 0x1))?; // 0x20c  ADD V0, 0x01
+    c8.execute(I00EE)?; // 0x20e  RET
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x204 => Next::Block(1, block_204),
+        0x206 => Next::Block(1, block_206),
+        _ => Next::Dispatch,
+    })
+}
@@ -0,0 +1,640 @@
+// Recompiled from PONG.bin by chip8 recompile.
+//
+// Load ROM into a machine and call run_frame instead of Chip8::run_frame:
+//
+//     let mut c8 = Chip8::new();
+//     c8.load_rom_bytes(&ROM)?;
+//     run_frame(&mut c8, 10)?;
+//
+// Every basic block is a function, which runs the block after it when that
+// is known from the ROM, and loops when it jumps back to its own start.
+// Elsewhere, such as after BNNN, the block at the program counter is looked
+// up. Blocks whose code was changed and addresses with no block are
+// interpreted.
+use chip8::interpreter::chip8::{Chip8, Fault, PROGRAM_START};
+use chip8::interpreter::instruction::Instruction::*;
+
+// Block is a recompiled basic block. It counts the instructions it runs off
+// what is left of the frame and returns what runs next.
+type Block = fn(&mut Chip8, &mut usize) -> Result<Next, Fault>;
+
+// Next is what runs after a block: a block and its number of instructions,
+// or the block at the program counter.
+enum Next {
+    Block(usize, Block),
+    Dispatch,
+}
+
+// ROM is the ROM, to be loaded at PROGRAM_START.
+pub const ROM: [u8; 246] = [
+    0x6a, 0x02, 0x6b, 0x0c, 0x6c, 0x3f, 0x6d, 0x0c, 0xa2, 0xea, 0xda, 0xb6,
+    0xdc, 0xd6, 0x6e, 0x00, 0x22, 0xd4, 0x66, 0x03, 0x68, 0x02, 0x60, 0x60,
+    0xf0, 0x15, 0xf0, 0x07, 0x30, 0x00, 0x12, 0x1a, 0xc7, 0x17, 0x77, 0x08,
+    0x69, 0xff, 0xa2, 0xf0, 0xd6, 0x71, 0xa2, 0xea, 0xda, 0xb6, 0xdc, 0xd6,
+    0x60, 0x01, 0xe0, 0xa1, 0x7b, 0xfe, 0x60, 0x04, 0xe0, 0xa1, 0x7b, 0x02,
+    0x60, 0x1f, 0x8b, 0x02, 0xda, 0xb6, 0x60, 0x0c, 0xe0, 0xa1, 0x7d, 0xfe,
+    0x60, 0x0d, 0xe0, 0xa1, 0x7d, 0x02, 0x60, 0x1f, 0x8d, 0x02, 0xdc, 0xd6,
+    0xa2, 0xf0, 0xd6, 0x71, 0x86, 0x84, 0x87, 0x94, 0x60, 0x3f, 0x86, 0x02,
+    0x61, 0x1f, 0x87, 0x12, 0x46, 0x02, 0x12, 0x78, 0x46, 0x3f, 0x12, 0x82,
+    0x47, 0x1f, 0x69, 0xff, 0x47, 0x00, 0x69, 0x01, 0xd6, 0x71, 0x12, 0x2a,
+    0x68, 0x02, 0x63, 0x01, 0x80, 0x70, 0x80, 0xb5, 0x12, 0x8a, 0x68, 0xfe,
+    0x63, 0x0a, 0x80, 0x70, 0x80, 0xd5, 0x3f, 0x01, 0x12, 0xa2, 0x61, 0x02,
+    0x80, 0x15, 0x3f, 0x01, 0x12, 0xba, 0x80, 0x15, 0x3f, 0x01, 0x12, 0xc8,
+    0x80, 0x15, 0x3f, 0x01, 0x12, 0xc2, 0x60, 0x20, 0xf0, 0x18, 0x22, 0xd4,
+    0x8e, 0x34, 0x22, 0xd4, 0x66, 0x3e, 0x33, 0x01, 0x66, 0x03, 0x68, 0xfe,
+    0x33, 0x01, 0x68, 0x02, 0x12, 0x16, 0x79, 0xff, 0x49, 0xfe, 0x69, 0xff,
+    0x12, 0xc8, 0x79, 0x01, 0x49, 0x02, 0x69, 0x01, 0x60, 0x04, 0xf0, 0x18,
+    0x76, 0x01, 0x46, 0x40, 0x76, 0xfe, 0x12, 0x6c, 0xa2, 0xf2, 0xfe, 0x33,
+    0xf2, 0x65, 0xf1, 0x29, 0x64, 0x14, 0x65, 0x00, 0xd4, 0x55, 0x74, 0x15,
+    0xf2, 0x29, 0xd4, 0x55, 0x00, 0xee, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+    0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+// run_frame runs the given number of instructions and then counts the
+// timers down once, like Chip8::run_frame. A block that does not fit in
+// what is left of the frame, or whose code was changed, is interpreted
+// instead.
+pub fn run_frame(c8: &mut Chip8, instructions: usize) -> Result<(), Fault> {
+    let mut left = instructions;
+    let mut next = Next::Dispatch;
+    while left > 0 {
+        let found = match next {
+            Next::Block(len, run) => Some((len, run)),
+            Next::Dispatch => block(c8),
+        };
+        match found {
+            Some((len, run)) if len <= left && unchanged(c8, len) => {
+                next = run(c8, &mut left)?;
+            }
+            _ => {
+                c8.cycle()?;
+                left -= 1;
+                next = Next::Dispatch;
+            }
+        }
+    }
+    c8.tick();
+    Ok(())
+}
+
+// block returns the block at the program counter and its number of
+// instructions, if there is one.
+fn block(c8: &Chip8) -> Option<(usize, Block)> {
+    let block: (usize, Block) = match c8.pc() {
+        0x200 => (9, block_200),
+        0x212 => (2, block_212),
+        0x216 => (2, block_216),
+        0x21a => (2, block_21a),
+        0x21e => (1, block_21e),
+        0x220 => (5, block_220),
+        0x22a => (5, block_22a),
+        0x234 => (1, block_234),
+        0x236 => (2, block_236),
+        0x23a => (1, block_23a),
+        0x23c => (5, block_23c),
+        0x246 => (1, block_246),
+        0x248 => (2, block_248),
+        0x24c => (1, block_24c),
+        0x24e => (12, block_24e),
+        0x266 => (1, block_266),
+        0x268 => (1, block_268),
+        0x26a => (1, block_26a),
+        0x26c => (1, block_26c),
+        0x26e => (1, block_26e),
+        0x270 => (1, block_270),
+        0x272 => (1, block_272),
+        0x274 => (2, block_274),
+        0x278 => (5, block_278),
+        0x282 => (4, block_282),
+        0x28a => (1, block_28a),
+        0x28c => (1, block_28c),
+        0x28e => (3, block_28e),
+        0x294 => (1, block_294),
+        0x296 => (2, block_296),
+        0x29a => (1, block_29a),
+        0x29c => (2, block_29c),
+        0x2a0 => (1, block_2a0),
+        0x2a2 => (3, block_2a2),
+        0x2a8 => (2, block_2a8),
+        0x2ac => (2, block_2ac),
+        0x2b0 => (1, block_2b0),
+        0x2b2 => (2, block_2b2),
+        0x2b6 => (1, block_2b6),
+        0x2b8 => (1, block_2b8),
+        0x2ba => (2, block_2ba),
+        0x2be => (1, block_2be),
+        0x2c0 => (1, block_2c0),
+        0x2c2 => (2, block_2c2),
+        0x2c6 => (1, block_2c6),
+        0x2c8 => (4, block_2c8),
+        0x2d0 => (1, block_2d0),
+        0x2d2 => (1, block_2d2),
+        0x2d4 => (2, block_2d4),
+        0x2d8 => (9, block_2d8),
+        _ => return None,
+    };
+    Some(block)
+}
+
+// unchanged returns whether the len instructions at the program counter are
+// still those of the ROM.
+fn unchanged(c8: &Chip8, len: usize) -> bool {
+    let start = c8.pc() as usize;
+    let end = start + 2 * len;
+    let rom = start - PROGRAM_START as usize..end - PROGRAM_START as usize;
+    c8.bus().bytes()[start..end] == ROM[rom]
+}
+
+// block_200 goes on to 0x2d4, returning to 0x212.
+fn block_200(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0xa, 0x2))?; // 0x200  LD VA, 0x02
+    c8.execute(I6XNN(0xb, 0xc))?; // 0x202  LD VB, 0x0c
+    c8.execute(I6XNN(0xc, 0x3f))?; // 0x204  LD VC, 0x3f
+    c8.execute(I6XNN(0xd, 0xc))?; // 0x206  LD VD, 0x0c
+    c8.execute(IANNN(0x2ea))?; // 0x208  LD I, 0x2ea
+    c8.execute(IDXYN(0xa, 0xb, 0x6))?; // 0x20a  DRW VA, VB, 6
+    c8.execute(IDXYN(0xc, 0xd, 0x6))?; // 0x20c  DRW VC, VD, 6
+    c8.execute(I6XNN(0xe, 0x0))?; // 0x20e  LD VE, 0x00
+    c8.execute(I2NNN(0x2d4))?; // 0x210  CALL 0x2d4
+    *left -= 9;
+    Ok(Next::Block(2, block_2d4))
+}
+
+// block_212 goes on to 0x216.
+fn block_212(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x6, 0x3))?; // 0x212  LD V6, 0x03
+    c8.execute(I6XNN(0x8, 0x2))?; // 0x214  LD V8, 0x02
+    *left -= 2;
+    Ok(Next::Block(2, block_216))
+}
+
+// block_216 goes on to 0x21a.
+fn block_216(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x60))?; // 0x216  LD V0, 0x60
+    c8.execute(IFX15(0x0))?; // 0x218  LD DT, V0
+    *left -= 2;
+    Ok(Next::Block(2, block_21a))
+}
+
+// block_21a goes on to 0x21e or 0x220.
+fn block_21a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(IFX07(0x0))?; // 0x21a  LD V0, DT
+    c8.execute(I3XNN(0x0, 0x0))?; // 0x21c  SE V0, 0x00
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x21e => Next::Block(1, block_21e),
+        0x220 => Next::Block(5, block_220),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_21e goes on to 0x21a.
+fn block_21e(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x21a))?; // 0x21e  JP 0x21a
+    *left -= 1;
+    Ok(Next::Block(2, block_21a))
+}
+
+// block_220 goes on to 0x22a.
+fn block_220(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(ICXNN(0x7, 0x17))?; // 0x220  RND V7, 0x17
+    c8.execute(I7XNN(0x7, 0x8))?; // 0x222  ADD V7, 0x08
+    c8.execute(I6XNN(0x9, 0xff))?; // 0x224  LD V9, 0xff
+    c8.execute(IANNN(0x2f0))?; // 0x226  LD I, 0x2f0
+    c8.execute(IDXYN(0x6, 0x7, 0x1))?; // 0x228  DRW V6, V7, 1
+    *left -= 5;
+    Ok(Next::Block(5, block_22a))
+}
+
+// block_22a goes on to 0x234 or 0x236.
+fn block_22a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(IANNN(0x2ea))?; // 0x22a  LD I, 0x2ea
+    c8.execute(IDXYN(0xa, 0xb, 0x6))?; // 0x22c  DRW VA, VB, 6
+    c8.execute(IDXYN(0xc, 0xd, 0x6))?; // 0x22e  DRW VC, VD, 6
+    c8.execute(I6XNN(0x0, 0x1))?; // 0x230  LD V0, 0x01
+    c8.execute(IEXA1(0x0))?; // 0x232  SKNP V0
+    *left -= 5;
+    Ok(match c8.pc() {
+        0x234 => Next::Block(1, block_234),
+        0x236 => Next::Block(2, block_236),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_234 goes on to 0x236.
+fn block_234(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0xb, 0xfe))?; // 0x234  ADD VB, 0xfe
+    *left -= 1;
+    Ok(Next::Block(2, block_236))
+}
+
+// block_236 goes on to 0x23a or 0x23c.
+fn block_236(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x4))?; // 0x236  LD V0, 0x04
+    c8.execute(IEXA1(0x0))?; // 0x238  SKNP V0
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x23a => Next::Block(1, block_23a),
+        0x23c => Next::Block(5, block_23c),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_23a goes on to 0x23c.
+fn block_23a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0xb, 0x2))?; // 0x23a  ADD VB, 0x02
+    *left -= 1;
+    Ok(Next::Block(5, block_23c))
+}
+
+// block_23c goes on to 0x246 or 0x248.
+fn block_23c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x1f))?; // 0x23c  LD V0, 0x1f
+    c8.execute(I8XY2(0xb, 0x0))?; // 0x23e  AND VB, V0
+    c8.execute(IDXYN(0xa, 0xb, 0x6))?; // 0x240  DRW VA, VB, 6
+    c8.execute(I6XNN(0x0, 0xc))?; // 0x242  LD V0, 0x0c
+    c8.execute(IEXA1(0x0))?; // 0x244  SKNP V0
+    *left -= 5;
+    Ok(match c8.pc() {
+        0x246 => Next::Block(1, block_246),
+        0x248 => Next::Block(2, block_248),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_246 goes on to 0x248.
+fn block_246(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0xd, 0xfe))?; // 0x246  ADD VD, 0xfe
+    *left -= 1;
+    Ok(Next::Block(2, block_248))
+}
+
+// block_248 goes on to 0x24c or 0x24e.
+fn block_248(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0xd))?; // 0x248  LD V0, 0x0d
+    c8.execute(IEXA1(0x0))?; // 0x24a  SKNP V0
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x24c => Next::Block(1, block_24c),
+        0x24e => Next::Block(12, block_24e),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_24c goes on to 0x24e.
+fn block_24c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0xd, 0x2))?; // 0x24c  ADD VD, 0x02
+    *left -= 1;
+    Ok(Next::Block(12, block_24e))
+}
+
+// block_24e goes on to 0x266 or 0x268.
+fn block_24e(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x1f))?; // 0x24e  LD V0, 0x1f
+    c8.execute(I8XY2(0xd, 0x0))?; // 0x250  AND VD, V0
+    c8.execute(IDXYN(0xc, 0xd, 0x6))?; // 0x252  DRW VC, VD, 6
+    c8.execute(IANNN(0x2f0))?; // 0x254  LD I, 0x2f0
+    c8.execute(IDXYN(0x6, 0x7, 0x1))?; // 0x256  DRW V6, V7, 1
+    c8.execute(I8XY4(0x6, 0x8))?; // 0x258  ADD V6, V8
+    c8.execute(I8XY4(0x7, 0x9))?; // 0x25a  ADD V7, V9
+    c8.execute(I6XNN(0x0, 0x3f))?; // 0x25c  LD V0, 0x3f
+    c8.execute(I8XY2(0x6, 0x0))?; // 0x25e  AND V6, V0
+    c8.execute(I6XNN(0x1, 0x1f))?; // 0x260  LD V1, 0x1f
+    c8.execute(I8XY2(0x7, 0x1))?; // 0x262  AND V7, V1
+    c8.execute(I4XNN(0x6, 0x2))?; // 0x264  SNE V6, 0x02
+    *left -= 12;
+    Ok(match c8.pc() {
+        0x266 => Next::Block(1, block_266),
+        0x268 => Next::Block(1, block_268),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_266 goes on to 0x278.
+fn block_266(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x278))?; // 0x266  JP 0x278
+    *left -= 1;
+    Ok(Next::Block(5, block_278))
+}
+
+// block_268 goes on to 0x26a or 0x26c.
+fn block_268(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I4XNN(0x6, 0x3f))?; // 0x268  SNE V6, 0x3f
+    *left -= 1;
+    Ok(match c8.pc() {
+        0x26a => Next::Block(1, block_26a),
+        0x26c => Next::Block(1, block_26c),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_26a goes on to 0x282.
+fn block_26a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x282))?; // 0x26a  JP 0x282
+    *left -= 1;
+    Ok(Next::Block(4, block_282))
+}
+
+// block_26c goes on to 0x26e or 0x270.
+fn block_26c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I4XNN(0x7, 0x1f))?; // 0x26c  SNE V7, 0x1f
+    *left -= 1;
+    Ok(match c8.pc() {
+        0x26e => Next::Block(1, block_26e),
+        0x270 => Next::Block(1, block_270),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_26e goes on to 0x270.
+fn block_26e(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x9, 0xff))?; // 0x26e  LD V9, 0xff
+    *left -= 1;
+    Ok(Next::Block(1, block_270))
+}
+
+// block_270 goes on to 0x272 or 0x274.
+fn block_270(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I4XNN(0x7, 0x0))?; // 0x270  SNE V7, 0x00
+    *left -= 1;
+    Ok(match c8.pc() {
+        0x272 => Next::Block(1, block_272),
+        0x274 => Next::Block(2, block_274),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_272 goes on to 0x274.
+fn block_272(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x9, 0x1))?; // 0x272  LD V9, 0x01
+    *left -= 1;
+    Ok(Next::Block(2, block_274))
+}
+
+// block_274 goes on to 0x22a.
+fn block_274(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(IDXYN(0x6, 0x7, 0x1))?; // 0x274  DRW V6, V7, 1
+    c8.execute(I1NNN(0x22a))?; // 0x276  JP 0x22a
+    *left -= 2;
+    Ok(Next::Block(5, block_22a))
+}
+
+// block_278 goes on to 0x28a.
+fn block_278(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x8, 0x2))?; // 0x278  LD V8, 0x02
+    c8.execute(I6XNN(0x3, 0x1))?; // 0x27a  LD V3, 0x01
+    c8.execute(I8XY0(0x0, 0x7))?; // 0x27c  LD V0, V7
+    c8.execute(I8XY5(0x0, 0xb))?; // 0x27e  SUB V0, VB
+    c8.execute(I1NNN(0x28a))?; // 0x280  JP 0x28a
+    *left -= 5;
+    Ok(Next::Block(1, block_28a))
+}
+
+// block_282 goes on to 0x28a.
+fn block_282(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x8, 0xfe))?; // 0x282  LD V8, 0xfe
+    c8.execute(I6XNN(0x3, 0xa))?; // 0x284  LD V3, 0x0a
+    c8.execute(I8XY0(0x0, 0x7))?; // 0x286  LD V0, V7
+    c8.execute(I8XY5(0x0, 0xd))?; // 0x288  SUB V0, VD
+    *left -= 4;
+    Ok(Next::Block(1, block_28a))
+}
+
+// block_28a goes on to 0x28c or 0x28e.
+fn block_28a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I3XNN(0xf, 0x1))?; // 0x28a  SE VF, 0x01
+    *left -= 1;
+    Ok(match c8.pc() {
+        0x28c => Next::Block(1, block_28c),
+        0x28e => Next::Block(3, block_28e),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_28c goes on to 0x2a2.
+fn block_28c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x2a2))?; // 0x28c  JP 0x2a2
+    *left -= 1;
+    Ok(Next::Block(3, block_2a2))
+}
+
+// block_28e goes on to 0x294 or 0x296.
+fn block_28e(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x1, 0x2))?; // 0x28e  LD V1, 0x02
+    c8.execute(I8XY5(0x0, 0x1))?; // 0x290  SUB V0, V1
+    c8.execute(I3XNN(0xf, 0x1))?; // 0x292  SE VF, 0x01
+    *left -= 3;
+    Ok(match c8.pc() {
+        0x294 => Next::Block(1, block_294),
+        0x296 => Next::Block(2, block_296),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_294 goes on to 0x2ba.
+fn block_294(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x2ba))?; // 0x294  JP 0x2ba
+    *left -= 1;
+    Ok(Next::Block(2, block_2ba))
+}
+
+// block_296 goes on to 0x29a or 0x29c.
+fn block_296(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I8XY5(0x0, 0x1))?; // 0x296  SUB V0, V1
+    c8.execute(I3XNN(0xf, 0x1))?; // 0x298  SE VF, 0x01
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x29a => Next::Block(1, block_29a),
+        0x29c => Next::Block(2, block_29c),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_29a goes on to 0x2c8.
+fn block_29a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x2c8))?; // 0x29a  JP 0x2c8
+    *left -= 1;
+    Ok(Next::Block(4, block_2c8))
+}
+
+// block_29c goes on to 0x2a0 or 0x2a2.
+fn block_29c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I8XY5(0x0, 0x1))?; // 0x29c  SUB V0, V1
+    c8.execute(I3XNN(0xf, 0x1))?; // 0x29e  SE VF, 0x01
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x2a0 => Next::Block(1, block_2a0),
+        0x2a2 => Next::Block(3, block_2a2),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_2a0 goes on to 0x2c2.
+fn block_2a0(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x2c2))?; // 0x2a0  JP 0x2c2
+    *left -= 1;
+    Ok(Next::Block(2, block_2c2))
+}
+
+// block_2a2 goes on to 0x2d4, returning to 0x2a8.
+fn block_2a2(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x20))?; // 0x2a2  LD V0, 0x20
+    c8.execute(IFX18(0x0))?; // 0x2a4  LD ST, V0
+    c8.execute(I2NNN(0x2d4))?; // 0x2a6  CALL 0x2d4
+    *left -= 3;
+    Ok(Next::Block(2, block_2d4))
+}
+
+// block_2a8 goes on to 0x2d4, returning to 0x2ac.
+fn block_2a8(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I8XY4(0xe, 0x3))?; // 0x2a8  ADD VE, V3
+    c8.execute(I2NNN(0x2d4))?; // 0x2aa  CALL 0x2d4
+    *left -= 2;
+    Ok(Next::Block(2, block_2d4))
+}
+
+// block_2ac goes on to 0x2b0 or 0x2b2.
+fn block_2ac(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x6, 0x3e))?; // 0x2ac  LD V6, 0x3e
+    c8.execute(I3XNN(0x3, 0x1))?; // 0x2ae  SE V3, 0x01
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x2b0 => Next::Block(1, block_2b0),
+        0x2b2 => Next::Block(2, block_2b2),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_2b0 goes on to 0x2b2.
+fn block_2b0(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x6, 0x3))?; // 0x2b0  LD V6, 0x03
+    *left -= 1;
+    Ok(Next::Block(2, block_2b2))
+}
+
+// block_2b2 goes on to 0x2b6 or 0x2b8.
+fn block_2b2(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x8, 0xfe))?; // 0x2b2  LD V8, 0xfe
+    c8.execute(I3XNN(0x3, 0x1))?; // 0x2b4  SE V3, 0x01
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x2b6 => Next::Block(1, block_2b6),
+        0x2b8 => Next::Block(1, block_2b8),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_2b6 goes on to 0x2b8.
+fn block_2b6(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x8, 0x2))?; // 0x2b6  LD V8, 0x02
+    *left -= 1;
+    Ok(Next::Block(1, block_2b8))
+}
+
+// block_2b8 goes on to 0x216.
+fn block_2b8(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x216))?; // 0x2b8  JP 0x216
+    *left -= 1;
+    Ok(Next::Block(2, block_216))
+}
+
+// block_2ba goes on to 0x2be or 0x2c0.
+fn block_2ba(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0x9, 0xff))?; // 0x2ba  ADD V9, 0xff
+    c8.execute(I4XNN(0x9, 0xfe))?; // 0x2bc  SNE V9, 0xfe
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x2be => Next::Block(1, block_2be),
+        0x2c0 => Next::Block(1, block_2c0),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_2be goes on to 0x2c0.
+fn block_2be(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x9, 0xff))?; // 0x2be  LD V9, 0xff
+    *left -= 1;
+    Ok(Next::Block(1, block_2c0))
+}
+
+// block_2c0 goes on to 0x2c8.
+fn block_2c0(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x2c8))?; // 0x2c0  JP 0x2c8
+    *left -= 1;
+    Ok(Next::Block(4, block_2c8))
+}
+
+// block_2c2 goes on to 0x2c6 or 0x2c8.
+fn block_2c2(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0x9, 0x1))?; // 0x2c2  ADD V9, 0x01
+    c8.execute(I4XNN(0x9, 0x2))?; // 0x2c4  SNE V9, 0x02
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x2c6 => Next::Block(1, block_2c6),
+        0x2c8 => Next::Block(4, block_2c8),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_2c6 goes on to 0x2c8.
+fn block_2c6(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x9, 0x1))?; // 0x2c6  LD V9, 0x01
+    *left -= 1;
+    Ok(Next::Block(4, block_2c8))
+}
+
+// block_2c8 goes on to 0x2d0 or 0x2d2.
+fn block_2c8(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x4))?; // 0x2c8  LD V0, 0x04
+    c8.execute(IFX18(0x0))?; // 0x2ca  LD ST, V0
+    c8.execute(I7XNN(0x6, 0x1))?; // 0x2cc  ADD V6, 0x01
+    c8.execute(I4XNN(0x6, 0x40))?; // 0x2ce  SNE V6, 0x40
+    *left -= 4;
+    Ok(match c8.pc() {
+        0x2d0 => Next::Block(1, block_2d0),
+        0x2d2 => Next::Block(1, block_2d2),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_2d0 goes on to 0x2d2.
+fn block_2d0(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0x6, 0xfe))?; // 0x2d0  ADD V6, 0xfe
+    *left -= 1;
+    Ok(Next::Block(1, block_2d2))
+}
+
+// block_2d2 goes on to 0x26c.
+fn block_2d2(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x26c))?; // 0x2d2  JP 0x26c
+    *left -= 1;
+    Ok(Next::Block(1, block_26c))
+}
+
+// block_2d4 is a subroutine and goes on to 0x2d8.
+fn block_2d4(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(IANNN(0x2f2))?; // 0x2d4  LD I, 0x2f2
+    c8.execute(IFX33(0xe))?; // 0x2d6  LD B, VE
+    *left -= 2;
+    Ok(Next::Block(9, block_2d8))
+}
+
+// block_2d8 goes on to the caller.
+fn block_2d8(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(IFX65(0x2))?; // 0x2d8  LD V2, [I]
+    c8.execute(IFX29(0x1))?; // 0x2da  LD F, V1
+    c8.execute(I6XNN(0x4, 0x14))?; // 0x2dc  LD V4, 0x14
+    c8.execute(I6XNN(0x5, 0x0))?; // 0x2de  LD V5, 0x00
+    c8.execute(IDXYN(0x4, 0x5, 0x5))?; // 0x2e0  DRW V4, V5, 5
+    c8.execute(I7XNN(0x4, 0x15))?; // 0x2e2  ADD V4, 0x15
+    c8.execute(IFX29(0x2))?; // 0x2e4  LD F, V2
+    c8.execute(IDXYN(0x4, 0x5, 0x5))?; // 0x2e6  DRW V4, V5, 5
+    c8.execute(I00EE)?; // 0x2e8  RET
+    *left -= 9;
+    Ok(match c8.pc() {
+        0x212 => Next::Block(2, block_212),
+        0x2a8 => Next::Block(2, block_2a8),
+        0x2ac => Next::Block(2, block_2ac),
+        _ => Next::Dispatch,
+    })
+}
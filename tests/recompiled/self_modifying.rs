@@ -0,0 +1,135 @@
+// Recompiled from self_modifying.ch8 by chip8 recompile.
+//
+// Load ROM into a machine and call run_frame instead of Chip8::run_frame:
+//
+//     let mut c8 = Chip8::new();
+//     c8.load_rom_bytes(&ROM)?;
+//     run_frame(&mut c8, 10)?;
+//
+// Every basic block is a function, which runs the block after it when that
+// is known from the ROM, and loops when it jumps back to its own start.
+// Elsewhere, such as after BNNN, the block at the program counter is looked
+// up. Blocks whose code was changed and addresses with no block are
+// interpreted.
+use chip8::interpreter::chip8::{Chip8, Fault, PROGRAM_START};
+use chip8::interpreter::instruction::Instruction::*;
+
+// Block is a recompiled basic block. It counts the instructions it runs off
+// what is left of the frame and returns what runs next.
+type Block = fn(&mut Chip8, &mut usize) -> Result<Next, Fault>;
+
+// Next is what runs after a block: a block and its number of instructions,
+// or the block at the program counter.
+enum Next {
+    Block(usize, Block),
+    Dispatch,
+}
+
+// ROM is the ROM, to be loaded at PROGRAM_START.
+pub const ROM: [u8; 26] = [
+    0xa2, 0x06, 0x60, 0x75, 0x61, 0x10, 0x75, 0x01, 0x42, 0x01, 0x12, 0x12,
+    0x72, 0x01, 0xf1, 0x55, 0x12, 0x06, 0x60, 0x04, 0xb2, 0x12, 0x76, 0x01,
+    0x12, 0x18,
+];
+
+// run_frame runs the given number of instructions and then counts the
+// timers down once, like Chip8::run_frame. A block that does not fit in
+// what is left of the frame, or whose code was changed, is interpreted
+// instead.
+pub fn run_frame(c8: &mut Chip8, instructions: usize) -> Result<(), Fault> {
+    let mut left = instructions;
+    let mut next = Next::Dispatch;
+    while left > 0 {
+        let found = match next {
+            Next::Block(len, run) => Some((len, run)),
+            Next::Dispatch => block(c8),
+        };
+        match found {
+            Some((len, run)) if len <= left && unchanged(c8, len) => {
+                next = run(c8, &mut left)?;
+            }
+            _ => {
+                c8.cycle()?;
+                left -= 1;
+                next = Next::Dispatch;
+            }
+        }
+    }
+    c8.tick();
+    Ok(())
+}
+
+// block returns the block at the program counter and its number of
+// instructions, if there is one.
+fn block(c8: &Chip8) -> Option<(usize, Block)> {
+    let block: (usize, Block) = match c8.pc() {
+        0x200 => (3, block_200),
+        0x206 => (2, block_206),
+        0x20a => (1, block_20a),
+        0x20c => (2, block_20c),
+        0x210 => (1, block_210),
+        0x212 => (2, block_212),
+        _ => return None,
+    };
+    Some(block)
+}
+
+// unchanged returns whether the len instructions at the program counter are
+// still those of the ROM.
+fn unchanged(c8: &Chip8, len: usize) -> bool {
+    let start = c8.pc() as usize;
+    let end = start + 2 * len;
+    let rom = start - PROGRAM_START as usize..end - PROGRAM_START as usize;
+    c8.bus().bytes()[start..end] == ROM[rom]
+}
+
+// block_200 goes on to 0x206.
+fn block_200(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(IANNN(0x206))?; // 0x200  LD I, 0x206
+    c8.execute(I6XNN(0x0, 0x75))?; // 0x202  LD V0, 0x75
+    c8.execute(I6XNN(0x1, 0x10))?; // 0x204  LD V1, 0x10
+    *left -= 3;
+    Ok(Next::Block(2, block_206))
+}
+
+// block_206 goes on to 0x20a or 0x20c.
+fn block_206(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0x5, 0x1))?; // 0x206  ADD V5, 0x01
+    c8.execute(I4XNN(0x2, 0x1))?; // 0x208  SNE V2, 0x01
+    *left -= 2;
+    Ok(match c8.pc() {
+        0x20a => Next::Block(1, block_20a),
+        0x20c => Next::Block(2, block_20c),
+        _ => Next::Dispatch,
+    })
+}
+
+// block_20a goes on to 0x212.
+fn block_20a(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x212))?; // 0x20a  JP 0x212
+    *left -= 1;
+    Ok(Next::Block(2, block_212))
+}
+
+// block_20c goes on to 0x210.
+fn block_20c(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I7XNN(0x2, 0x1))?; // 0x20c  ADD V2, 0x01
+    c8.execute(IFX55(0x1))?; // 0x20e  LD [I], V1
+    *left -= 2;
+    Ok(Next::Block(1, block_210))
+}
+
+// block_210 goes on to 0x206.
+fn block_210(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I1NNN(0x206))?; // 0x210  JP 0x206
+    *left -= 1;
+    Ok(Next::Block(2, block_206))
+}
+
+// block_212 goes on to 0x212 plus a register.
+fn block_212(c8: &mut Chip8, left: &mut usize) -> Result<Next, Fault> {
+    c8.execute(I6XNN(0x0, 0x4))?; // 0x212  LD V0, 0x04
+    c8.execute(IBNNN(0x212))?; // 0x214  JP V0, 0x212
+    *left -= 2;
+    Ok(Next::Dispatch)
+}
@@ -0,0 +1,90 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::recompiler;
+
+mod recompiled {
+    pub mod calls;
+    pub mod pong;
+    pub mod self_modifying;
+}
+use recompiled::{calls, pong, self_modifying};
+
+// The recompiled modules are what chip8 recompile writes today.
+#[test]
+fn modules_are_current() {
+    assert_eq!(
+        recompiler::recompile(&pong::ROM, "PONG.bin"),
+        include_str!("recompiled/pong.rs")
+    );
+    assert_eq!(
+        recompiler::recompile(&self_modifying::ROM, "self_modifying.ch8"),
+        include_str!("recompiled/self_modifying.rs")
+    );
+    assert_eq!(
+        recompiler::recompile(&calls::ROM, "calls.ch8"),
+        include_str!("recompiled/calls.rs")
+    );
+}
+
+#[test]
+fn pong_runs_like_the_interpreter() {
+    let mut recompiled = Chip8::new();
+    let mut interpreter = Chip8::new();
+    for c8 in [&mut recompiled, &mut interpreter].iter_mut() {
+        c8.load_rom_bytes(&pong::ROM).unwrap();
+        c8.seed(1);
+    }
+    for frame in 0..600 {
+        // Move the left paddle up and down
+        let pressed = frame % 120 < 60;
+        recompiled.set_key(0x1, pressed);
+        interpreter.set_key(0x1, pressed);
+        pong::run_frame(&mut recompiled, 10).unwrap();
+        interpreter.run_frame(10).unwrap();
+        let differences = recompiled.differences(&interpreter);
+        assert!(differences.is_empty(), "frame {}: {:?}", frame, differences);
+    }
+}
+
+// The ROM rewrites ADD V5, 0x01 at 0x206 into ADD V5, 0x10, which the
+// interpreter then runs instead of the recompiled block. It ends with a BNNN
+// jump to an address the recompiler never saw.
+#[test]
+fn falls_back_to_the_interpreter() {
+    let mut recompiled = Chip8::new();
+    let mut interpreter = Chip8::new();
+    for c8 in [&mut recompiled, &mut interpreter].iter_mut() {
+        c8.load_rom_bytes(&self_modifying::ROM).unwrap();
+    }
+    for speed in &[1, 2, 3, 5, 7] {
+        self_modifying::run_frame(&mut recompiled, *speed).unwrap();
+        interpreter.run_frame(*speed).unwrap();
+        assert_eq!(recompiled.differences(&interpreter), Vec::<String>::new());
+    }
+    assert_eq!(recompiled.register(5), 0x11);
+    assert_eq!(recompiled.register(6), 0x01);
+    assert_eq!(recompiled.pc(), 0x218);
+}
+
+// The ROM calls a subroutine from two places until V0 is 4, then loops on
+// a jump to itself. Blocks go straight on to each other, returning to the
+// right caller, and the loop stops where the frame ends.
+#[test]
+fn follows_calls_and_loops() {
+    let module = recompiler::recompile(&calls::ROM, "calls.ch8");
+    assert!(module.contains("    Ok(Next::Block(2, block_20c))\n"));
+    assert!(module.contains("        0x206 => Next::Block(1, block_206),\n"));
+    assert!(module.contains("    loop {\n"));
+
+    let mut recompiled = Chip8::new();
+    let mut interpreter = Chip8::new();
+    for c8 in [&mut recompiled, &mut interpreter].iter_mut() {
+        c8.load_rom_bytes(&calls::ROM).unwrap();
+    }
+    for speed in &[1, 2, 3, 5, 7, 20] {
+        calls::run_frame(&mut recompiled, *speed).unwrap();
+        interpreter.run_frame(*speed).unwrap();
+        assert_eq!(recompiled.differences(&interpreter), Vec::<String>::new());
+    }
+    assert_eq!(recompiled.register(0), 4);
+    assert_eq!(recompiled.pc(), 0x20a);
+}
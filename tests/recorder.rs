@@ -0,0 +1,225 @@
+use chip8::recorder::{Format, Recorder};
+use chip8::screenshot::{Image, Palette};
+use std::env;
+use std::fs;
+
+// record records frames to a file and reads it back.
+fn record(name: &str, scale: usize, frames: &[Image]) -> Vec<u8> {
+    let file = env::temp_dir().join(name);
+    let file = file.to_str().unwrap();
+    let mut recorder = Recorder::create(file, Palette::DEFAULT, scale).unwrap();
+    for frame in frames {
+        recorder.frame(*frame).unwrap();
+    }
+    assert_eq!(recorder.frames(), frames.len() as u64);
+    recorder.finish().unwrap();
+    let data = fs::read(file).unwrap();
+    fs::remove_file(file).ok();
+    data
+}
+
+// lzw_decode decodes the variable width LZW of a GIF image.
+fn lzw_decode(min_code_size: u8, data: &[u8]) -> Vec<u8> {
+    let clear = 1usize << min_code_size;
+    let reset = || -> Vec<Vec<u8>> {
+        let mut dict: Vec<Vec<u8>> =
+            (0..clear).map(|i| vec![i as u8]).collect();
+        dict.extend(vec![vec![], vec![]]); // Clear and end
+        dict
+    };
+    let mut dict = reset();
+    let mut width = min_code_size as usize + 1;
+    let (mut out, mut prev): (Vec<u8>, Option<Vec<u8>>) = (Vec::new(), None);
+    let mut bit = 0;
+    loop {
+        let mut code = 0;
+        for i in 0..width {
+            let b = (data[(bit + i) / 8] >> ((bit + i) % 8)) & 1;
+            code |= (b as usize) << i;
+        }
+        bit += width;
+        if code == clear {
+            dict = reset();
+            width = min_code_size as usize + 1;
+            prev = None;
+            continue;
+        }
+        if code == clear + 1 {
+            break;
+        }
+        let entry = match (dict.get(code), &prev) {
+            (Some(entry), _) => entry.clone(),
+            (None, Some(p)) => [&p[..], &p[..1]].concat(),
+            (None, None) => panic!("code {} before any entry", code),
+        };
+        out.extend(&entry);
+        if let Some(p) = prev {
+            if dict.len() < 4096 {
+                dict.push([&p[..], &entry[..1]].concat());
+            }
+        }
+        if dict.len() == 1 << width && width < 12 {
+            width += 1;
+        }
+        prev = Some(entry);
+    }
+    // Only the padding of the last byte is left
+    assert_eq!(bit.div_ceil(8), data.len());
+    out
+}
+
+// Gif is a decoded animated GIF.
+struct Gif {
+    size: (usize, usize),
+    colors: Vec<u8>,
+    frames: Vec<(u16, Vec<u8>)>, // The delay and pixels of each frame
+}
+
+// decode decodes an animated GIF as written by the recorder.
+fn decode(data: &[u8]) -> Gif {
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+    assert_eq!(&data[..6], b"GIF89a");
+    let size = (u16_at(6) as usize, u16_at(8) as usize);
+    assert_eq!(data[10..13], [0x80, 0, 0]);
+    let colors = data[13..19].to_vec();
+    assert_eq!(&data[19..22], [0x21, 0xFF, 0x0B]);
+    assert_eq!(&data[22..33], b"NETSCAPE2.0");
+    assert_eq!(data[33..38], [0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    let mut frames = Vec::new();
+    let mut i = 38;
+    while data[i] != 0x3B {
+        assert_eq!(data[i..i + 4], [0x21, 0xF9, 0x04, 0x00]);
+        let delay = u16_at(i + 4);
+        assert_eq!(data[i + 6..i + 12], [0x00, 0x00, 0x2C, 0, 0, 0]);
+        assert_eq!(data[i + 12], 0);
+        assert_eq!((u16_at(i + 13) as usize, u16_at(i + 15) as usize), size);
+        assert_eq!(data[i + 17], 0x00);
+        let min_code_size = data[i + 18];
+        i += 19;
+        let mut lzw = Vec::new();
+        while data[i] != 0 {
+            let len = data[i] as usize;
+            lzw.extend_from_slice(&data[i + 1..i + 1 + len]);
+            i += 1 + len;
+        }
+        i += 1;
+        frames.push((delay, lzw_decode(min_code_size, &lzw)));
+    }
+    assert_eq!(i, data.len() - 1);
+    Gif {
+        size,
+        colors,
+        frames,
+    }
+}
+
+#[rustfmt::skip]
+const SQUARE: [u8; 6] = [
+    1, 1, 0,
+    1, 1, 0,
+];
+
+#[rustfmt::skip]
+const DOT: [u8; 6] = [
+    0, 0, 0,
+    0, 0, 1,
+];
+
+#[test]
+fn formats() {
+    assert_eq!(Format::from_filename("a.GIF"), Some(Format::Gif));
+    assert_eq!(Format::from_filename("a.y4m"), Some(Format::Y4m));
+    assert_eq!(Format::from_filename("a.mp4"), None);
+    assert!(
+        Recorder::create("chip8-recording.mp4", Palette::DEFAULT, 1).is_err()
+    );
+}
+
+#[test]
+fn gif_frames() {
+    let (square, dot) = (Image::new(&SQUARE, 3, 2), Image::new(&DOT, 3, 2));
+    let data = record("chip8-recording.gif", 2, &[square, square, dot]);
+    let gif = decode(&data);
+    assert_eq!(gif.size, (6, 4));
+    assert_eq!(gif.colors, [0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00]);
+
+    // The repeated frame is merged, and frames keep their scaled pixels
+    assert_eq!(gif.frames.len(), 2);
+    assert_eq!(gif.frames[0], (3, square.scaled(2)));
+    assert_eq!(gif.frames[1], (2, dot.scaled(2)));
+}
+
+#[test]
+fn gif_lzw() {
+    // Noise fills the dictionary, which is cleared and started again
+    let mut seed = 1u32;
+    let noise: Vec<u8> = (0..512 * 256)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as u8 & 1
+        })
+        .collect();
+    let image = Image::new(&noise, 512, 256);
+    let gif = decode(&record("chip8-recording-noise.gif", 1, &[image]));
+    assert_eq!(gif.frames, [(2, image.scaled(1))]);
+
+    // And a blank image is a few long runs
+    let blank = [0; 64 * 32];
+    let image = Image::new(&blank, 64, 32);
+    let gif = decode(&record("chip8-recording-blank.gif", 4, &[image]));
+    assert_eq!(gif.frames, [(2, image.scaled(4))]);
+}
+
+#[test]
+fn gif_timing() {
+    // A second of frames changing at 60 Hz keeps its length, but frames
+    // shorter than 2 centiseconds, one in three, are left out
+    let (square, dot) = (Image::new(&SQUARE, 3, 2), Image::new(&DOT, 3, 2));
+    let frames: Vec<Image> = (0..60)
+        .map(|i| if i % 2 == 0 { square } else { dot })
+        .collect();
+    let gif = decode(&record("chip8-recording-60hz.gif", 1, &frames));
+    let delays: Vec<u16> = gif.frames.iter().map(|(d, _)| *d).collect();
+    assert_eq!(delays.iter().sum::<u16>(), 100);
+    assert!(delays.iter().all(|d| *d >= 2), "{:?}", delays);
+    assert_eq!(gif.frames.len(), 40);
+
+    // An empty recording is still a valid image
+    let gif = decode(&record("chip8-recording-empty.gif", 1, &[]));
+    assert_eq!((gif.size, gif.frames.len()), ((0, 0), 0));
+}
+
+#[test]
+fn y4m() {
+    let (square, dot) = (Image::new(&SQUARE, 3, 2), Image::new(&DOT, 3, 2));
+    let data = record("chip8-recording.y4m", 2, &[square, square, dot]);
+    let header = b"YUV4MPEG2 W6 H4 F60:1 Ip A1:1 C444\n";
+    assert_eq!(data[..header.len()], header[..]);
+
+    // Every frame is kept, as three planes of black and white
+    let frames: Vec<&[u8]> = data[header.len()..]
+        .chunks(b"FRAME\n".len() + 3 * 24)
+        .collect();
+    assert_eq!(frames.len(), 3);
+    for (frame, image) in frames.iter().zip(&[square, square, dot]) {
+        assert_eq!(&frame[..6], b"FRAME\n");
+        let luma: Vec<u8> = image
+            .scaled(2)
+            .iter()
+            .map(|p| if *p == 1 { 16 } else { 235 })
+            .collect();
+        assert_eq!(frame[6..30], luma[..]);
+        assert_eq!(frame[30..], [128; 48][..]);
+    }
+}
+
+#[test]
+fn size_changes() {
+    let file = env::temp_dir().join("chip8-recording-sizes.y4m");
+    let file = file.to_str().unwrap();
+    let mut recorder = Recorder::create(file, Palette::DEFAULT, 1).unwrap();
+    recorder.frame(Image::new(&SQUARE, 3, 2)).unwrap();
+    assert!(recorder.frame(Image::new(&SQUARE, 2, 3)).is_err());
+    fs::remove_file(file).ok();
+}
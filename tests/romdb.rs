@@ -0,0 +1,40 @@
+use chip8::interpreter::chip8::{Chip8, INSTRUCTIONS_PER_FRAME};
+use chip8::interpreter::quirks::Quirks;
+use chip8::romdb::{self, Platform};
+use chip8::screenshot::Palette;
+use std::fs;
+
+#[test]
+fn sha1() {
+    let hex = |data: &[u8]| hex::encode(romdb::sha1(data));
+    assert_eq!(hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(
+        hex(&[b'a'; 1000]),
+        "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+    );
+}
+
+#[test]
+fn lookup() {
+    let pong = fs::read("roms/PONG.bin").unwrap();
+    let game = romdb::lookup(&pong).unwrap();
+    assert_eq!(game.title, "Pong");
+    assert_eq!(game.platform, Platform::Chip8);
+    assert!(romdb::lookup(&pong[1..]).is_none());
+}
+
+#[test]
+fn recommended_settings() {
+    let mut c8 = Chip8::new();
+    c8.load_rom("roms/TETRIS.bin").unwrap();
+    assert_eq!(c8.game.unwrap().title, "Tetris");
+    assert_eq!(c8.quirks, Quirks::VIP);
+    assert_eq!(c8.recommended_speed(), 15);
+    assert_eq!(c8.recommended_palette(), Palette::DEFAULT);
+
+    // Unknown ROMs run at the default speed
+    c8.load_rom_bytes(&[0x12, 0x00]).unwrap();
+    assert!(c8.game.is_none());
+    assert_eq!(c8.recommended_speed(), INSTRUCTIONS_PER_FRAME);
+}
@@ -0,0 +1,191 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::screenshot::{self, Image, Palette};
+use std::convert::TryInto;
+use std::env;
+
+// PIXELS is a 3x2 image with a lit pixel in each corner but one.
+#[rustfmt::skip]
+const PIXELS: [u8; 6] = [
+    1, 0, 1,
+    1, 0, 0,
+];
+
+const GREEN: Palette = Palette {
+    on: [0x33, 0xFF, 0x66],
+    off: [0x00, 0x10, 0x00],
+};
+
+#[test]
+fn checksums() {
+    assert_eq!(screenshot::crc32(b""), 0);
+    assert_eq!(screenshot::crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(screenshot::crc32(b"IEND"), 0xAE42_6082);
+    assert_eq!(screenshot::adler32(b""), 1);
+    assert_eq!(screenshot::adler32(b"Wikipedia"), 0x11E6_0398);
+    assert_eq!(screenshot::adler32(&[0xFF; 6000]), 0xA497_59EA);
+}
+
+// chunks splits a PNG into its chunks, checking their CRCs.
+fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+    assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+    let mut chunks = Vec::new();
+    let mut rest = &png[8..];
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let body = &rest[4..8 + len];
+        let crc =
+            u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+        assert_eq!(screenshot::crc32(body), crc);
+        let kind = String::from_utf8(body[..4].to_vec()).unwrap();
+        chunks.push((kind, body[4..].to_vec()));
+        rest = &rest[12 + len..];
+    }
+    chunks
+}
+
+// inflate_stored decodes a zlib stream of uncompressed deflate blocks,
+// checking its Adler-32, and returns the data and the number of blocks.
+fn inflate_stored(stream: &[u8]) -> (Vec<u8>, usize) {
+    assert_eq!(stream[..2], [0x78, 0x01]);
+    assert_eq!(u16::from_be_bytes([stream[0], stream[1]]) % 31, 0);
+    let (mut data, mut blocks) = (Vec::new(), 0);
+    let mut rest = &stream[2..];
+    loop {
+        let last = rest[0];
+        let len = u16::from_le_bytes([rest[1], rest[2]]);
+        let nlen = u16::from_le_bytes([rest[3], rest[4]]);
+        assert_eq!(nlen, !len);
+        data.extend_from_slice(&rest[5..5 + len as usize]);
+        rest = &rest[5 + len as usize..];
+        blocks += 1;
+        if last == 1 {
+            break;
+        }
+        assert_eq!(last, 0);
+    }
+    let adler = u32::from_be_bytes(rest.try_into().unwrap());
+    assert_eq!(adler, screenshot::adler32(&data));
+    (data, blocks)
+}
+
+#[test]
+fn png() {
+    let png = screenshot::png(Image::new(&PIXELS, 3, 2), &GREEN, 2);
+    let chunks = chunks(&png);
+    let kinds: Vec<&str> = chunks.iter().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(kinds, ["IHDR", "PLTE", "IDAT", "IEND"]);
+
+    // 6x4, 8-bit indexed, with the background first
+    assert_eq!(chunks[0].1, [0, 0, 0, 6, 0, 0, 0, 4, 8, 3, 0, 0, 0]);
+    assert_eq!(chunks[1].1, [0x00, 0x10, 0x00, 0x33, 0xFF, 0x66]);
+    assert!(chunks[3].1.is_empty());
+
+    // Every scanline starts with filter type 0
+    let (raw, blocks) = inflate_stored(&chunks[2].1);
+    #[rustfmt::skip]
+    assert_eq!(raw, [
+        0, 1, 1, 0, 0, 1, 1,
+        0, 1, 1, 0, 0, 1, 1,
+        0, 1, 1, 0, 0, 0, 0,
+        0, 1, 1, 0, 0, 0, 0,
+    ]);
+    assert_eq!(blocks, 1);
+}
+
+#[test]
+fn png_blocks() {
+    // 384x192 pixels and their filter bytes need two stored blocks
+    let pixels = [1; 64 * 32];
+    let png = screenshot::png(Image::new(&pixels, 64, 32), &GREEN, 6);
+    let (raw, blocks) = inflate_stored(&chunks(&png)[2].1);
+    assert_eq!(raw.len(), 385 * 192);
+    assert_eq!(blocks, 2);
+    assert!(raw
+        .chunks(385)
+        .all(|row| row[0] == 0 && row[1..] == [1; 384]));
+}
+
+#[test]
+fn pbm() {
+    let pbm = screenshot::pbm(Image::new(&PIXELS, 3, 2), 3);
+    let text = String::from_utf8(pbm).unwrap();
+    assert_eq!(
+        text,
+        "P1\n9 6\n\
+         1 1 1 0 0 0 1 1 1\n\
+         1 1 1 0 0 0 1 1 1\n\
+         1 1 1 0 0 0 1 1 1\n\
+         1 1 1 0 0 0 0 0 0\n\
+         1 1 1 0 0 0 0 0 0\n\
+         1 1 1 0 0 0 0 0 0\n"
+    );
+
+    // Lines are at most 70 characters long
+    let pixels = [1; 64 * 32];
+    let pbm = screenshot::pbm(Image::new(&pixels, 64, 32), 2);
+    let text = String::from_utf8(pbm).unwrap();
+    assert!(text.lines().all(|line| line.len() <= 70));
+    let pixels: usize =
+        text.lines().skip(2).map(|l| l.matches('1').count()).sum();
+    assert_eq!(pixels, 128 * 64);
+}
+
+#[test]
+fn pbm_raw() {
+    // Rows of 9 pixels are padded to 2 bytes
+    let pbm = screenshot::pbm_raw(Image::new(&PIXELS, 3, 2), 3);
+    let header = b"P4\n9 6\n";
+    assert_eq!(pbm[..header.len()], header[..]);
+    #[rustfmt::skip]
+    assert_eq!(pbm[header.len()..], [
+        0b1110_0011, 0b1000_0000,
+        0b1110_0011, 0b1000_0000,
+        0b1110_0011, 0b1000_0000,
+        0b1110_0000, 0b0000_0000,
+        0b1110_0000, 0b0000_0000,
+        0b1110_0000, 0b0000_0000,
+    ]);
+}
+
+#[test]
+fn pgm() {
+    let pgm = screenshot::pgm(Image::new(&PIXELS, 3, 2), &GREEN, 1);
+    let text = String::from_utf8(pgm).unwrap();
+    assert_eq!(text, "P2\n3 2\n255\n176 9 176\n176 9 9\n");
+
+    let pgm = screenshot::pgm_raw(Image::new(&PIXELS, 3, 2), &GREEN, 1);
+    let header = b"P5\n3 2\n255\n";
+    assert_eq!(pgm[..header.len()], header[..]);
+    assert_eq!(pgm[header.len()..], [176, 9, 176, 176, 9, 9]);
+}
+
+#[test]
+fn save() {
+    let image = Image::new(&PIXELS, 3, 2);
+    let file = env::temp_dir().join("chip8-screenshot.PBM");
+    let file = file.to_str().unwrap();
+    screenshot::save(file, image, &GREEN, 3).unwrap();
+    assert_eq!(std::fs::read(file).unwrap(), screenshot::pbm(image, 3));
+    std::fs::remove_file(file).ok();
+
+    assert!(screenshot::save("chip8-screenshot.bmp", image, &GREEN, 3).is_err());
+    assert!(screenshot::save(file, image, &GREEN, 0).is_err());
+    assert!(std::fs::metadata(file).is_err());
+}
+
+#[test]
+fn displays() {
+    // LD V0, 60; LD I, 0; DRW V0, V0, 1 draws the top of a 0 at (60, 60),
+    // which wraps to (60, 28)
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&[0x60, 60, 0xA0, 0x00, 0xD0, 0x01])
+        .unwrap();
+    c8.run_frame(3).unwrap();
+    let image = Image::of(&c8);
+    assert_eq!((image.width, image.height), (64, 32));
+    let lit: Vec<(usize, usize)> = (0..32)
+        .flat_map(|y| (0..64).map(move |x| (x, y)))
+        .filter(|(x, y)| image.pixel(*x, *y))
+        .collect();
+    assert_eq!(lit, [(60, 28), (61, 28), (62, 28), (63, 28)]);
+}
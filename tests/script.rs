@@ -0,0 +1,134 @@
+#![cfg(feature = "scripting")]
+
+use chip8::interpreter::chip8::Chip8;
+use chip8::screenshot::Palette;
+use chip8::script::{ErrScript, Script};
+use std::env;
+use std::fs;
+
+#[rustfmt::skip]
+const COUNTER: [u8; 6] = [
+    0x60, 0x00, // 0x200  LD V0, 0x00
+    0x70, 0x01, // 0x202  ADD V0, 0x01
+    0x12, 0x02, // 0x204  JP 0x202
+];
+
+#[rustfmt::skip]
+const KEY: [u8; 4] = [
+    0xF1, 0x0A, // 0x200  LD V1, K
+    0x12, 0x02, // 0x202  JP 0x202
+];
+
+// machine returns a machine with a ROM loaded.
+fn machine(rom: &[u8]) -> Chip8 {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(rom).unwrap();
+    c8
+}
+
+#[test]
+fn registers_and_memory() {
+    let mut c8 = machine(&COUNTER);
+    let mut script = Script::new(
+        r#"
+        set_reg(3, 0x42);
+        set_index(0x300);
+        poke(0x300, reg(3) + 1);
+        if peek(0x300) != 0x43 || index() != 0x300 {
+            throw "the machine did not change";
+        }
+        set_pc(0x202);
+        "#,
+    )
+    .unwrap();
+    script.attach(&mut c8).unwrap();
+    assert_eq!(c8.register(3), 0x42);
+    assert_eq!(c8.index(), 0x300);
+    assert_eq!(c8.peek(0x300), 0x43);
+    assert_eq!(c8.pc(), 0x202);
+}
+
+#[test]
+fn breakpoints() {
+    let mut c8 = machine(&COUNTER);
+    let mut script = Script::new(
+        r#"
+        let hits = 0;
+        breakpoint(0x204, || {
+            hits += 1;
+            if hits == 15 {
+                stop();
+            }
+        });
+        "#,
+    )
+    .unwrap();
+    script.attach(&mut c8).unwrap();
+    while !script.stopped() && script.frames() < 10 {
+        script.run_frame(&mut c8, 10).unwrap();
+    }
+    // The 15th hit stops the script before the jump of the 4th frame
+    assert!(script.stopped());
+    assert_eq!(script.frames(), 3);
+    assert_eq!(c8.pc(), 0x204);
+    assert_eq!(c8.register(0), 15);
+}
+
+#[test]
+fn frames_and_keys() {
+    let mut c8 = machine(&KEY);
+    let mut script = Script::new(
+        r#"
+        on_frame(|| {
+            if frames() == 3 {
+                press(7);
+            }
+        });
+        "#,
+    )
+    .unwrap();
+    script.attach(&mut c8).unwrap();
+    for _ in 0..3 {
+        script.run_frame(&mut c8, 10).unwrap();
+    }
+    assert_eq!(c8.pc(), 0x200);
+    script.run_frame(&mut c8, 10).unwrap();
+    assert_eq!(c8.register(1), 7);
+    assert_eq!(c8.pc(), 0x202);
+    assert_eq!(script.frames(), 4);
+}
+
+#[test]
+fn screenshots() {
+    let file = env::temp_dir().join("chip8-script-screenshot.pbm");
+    let mut c8 = machine(&COUNTER);
+    let source = format!("screenshot({:?});", file.to_str().unwrap());
+    let mut script = Script::new(&source).unwrap();
+    script.screenshots(Palette::DEFAULT, 2);
+    script.attach(&mut c8).unwrap();
+    let image = fs::read(&file).unwrap();
+    fs::remove_file(&file).ok();
+    assert!(image.starts_with(b"P1\n128 64\n"));
+}
+
+#[test]
+fn errors() {
+    assert!(Script::new("let = 1;").is_err());
+
+    let mut c8 = machine(&COUNTER);
+    let mut script = Script::new("set_reg(16, 0);").unwrap();
+    match script.attach(&mut c8) {
+        Err(ErrScript::Script(message)) => {
+            assert!(message.contains("no register V16"), "{}", message)
+        }
+        result => panic!("{:?}", result),
+    }
+
+    let mut c8 = machine(&[0xFF, 0xFF]);
+    let mut script = Script::new("breakpoint(0x300, || ());").unwrap();
+    script.attach(&mut c8).unwrap();
+    match script.run_frame(&mut c8, 10) {
+        Err(ErrScript::Fault(_)) => (),
+        result => panic!("{:?}", result),
+    }
+}
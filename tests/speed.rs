@@ -0,0 +1,58 @@
+use chip8::interpreter::chip8::Chip8;
+use std::fs;
+use std::time::{Duration, Instant};
+
+// FRAMES is how long each engine runs every ROM for, about ten minutes of
+// play at 60 Hz.
+const FRAMES: usize = 36_000;
+
+// INSTRUCTIONS is the number of instructions run in a frame, high enough
+// for the engine to matter more than counting the timers down.
+const INSTRUCTIONS: usize = 100;
+
+// Setup sets up a machine to run on one of the engines.
+type Setup = fn(&mut Chip8);
+
+// timed runs a ROM on a machine set up by the given function and returns
+// how long it took and the state of the display at the end.
+fn timed(rom: &[u8], setup: Setup) -> (Duration, u64) {
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(rom).unwrap();
+    c8.seed(1);
+    setup(&mut c8);
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        c8.run_frame(INSTRUCTIONS).unwrap();
+    }
+    (start.elapsed(), c8.framebuffer_hash())
+}
+
+// engines compares the interpreter without and with the decode cache and the
+// block engine, which must all end up with the same display. Run with
+// cargo test --release --test speed -- --ignored --nocapture
+#[test]
+#[ignore]
+fn engines() {
+    let engines: [(&str, Setup); 3] = [
+        ("uncached", Chip8::disable_cache),
+        ("cached", |_| ()),
+        ("blocks", Chip8::enable_blocks),
+    ];
+    for rom in &["roms/PONG.bin", "roms/TETRIS.bin"] {
+        let bytes = fs::read(rom).unwrap();
+        let mut hashes = Vec::new();
+        for (name, setup) in &engines {
+            let (elapsed, hash) = timed(&bytes, *setup);
+            let rate = (FRAMES * INSTRUCTIONS) as f64 / elapsed.as_secs_f64();
+            println!(
+                "{:<16} {:<8} {:>8.1} ms {:>8.1} M instructions/s",
+                rom,
+                name,
+                elapsed.as_secs_f64() * 1000.0,
+                rate / 1e6
+            );
+            hashes.push(hash);
+        }
+        assert!(hashes.iter().all(|h| *h == hashes[0]), "{}", rom);
+    }
+}
@@ -0,0 +1,55 @@
+use chip8::interpreter::chip8::state::{ErrState, STATE_SIZE};
+use chip8::interpreter::chip8::Chip8;
+
+// run runs frames and returns the hash of the display after them.
+fn run(c8: &mut Chip8, frames: usize) -> u64 {
+    for _ in 0..frames {
+        c8.run_frame(10).unwrap();
+    }
+    c8.framebuffer_hash()
+}
+
+#[test]
+fn round_trip() {
+    let mut c8 = Chip8::new();
+    c8.load_rom("roms/PONG.bin").unwrap();
+    c8.seed(1);
+    c8.set_key(1, true);
+    run(&mut c8, 100);
+    let mut state = vec![0; STATE_SIZE];
+    c8.save_state(&mut state).unwrap();
+
+    // The generator is not saved, so both machines are seeded again
+    let mut copy = Chip8::new();
+    copy.load_state(&state).unwrap();
+    c8.seed(2);
+    copy.seed(2);
+    assert_eq!(copy.pc(), c8.pc());
+    assert_eq!(copy.quirks, c8.quirks);
+    assert_eq!(copy.rom(), c8.rom());
+    assert_eq!(run(&mut copy, 200), run(&mut c8, 200));
+    assert!(copy.differences(&c8).is_empty());
+}
+
+#[test]
+fn invalid_states() {
+    let mut c8 = Chip8::new();
+    let mut state = vec![0; STATE_SIZE];
+    assert!(c8.save_state(&mut state[..10]).is_err());
+    c8.save_state(&mut state).unwrap();
+
+    assert!(c8.load_state(&state[..STATE_SIZE - 1]).is_err());
+    let mut wrong = state.clone();
+    wrong[0] = b'X';
+    assert_eq!(
+        c8.load_state(&wrong),
+        Err(ErrState("not a state of this version"))
+    );
+
+    // A state with an overflowing stack leaves the machine as it was
+    c8.store(0x300, 0xAB);
+    let mut wrong = state.clone();
+    wrong[4 + 0x1000 + 16 + 4] = 13;
+    assert!(c8.load_state(&wrong).is_err());
+    assert_eq!(c8.peek(0x300), 0xAB);
+}
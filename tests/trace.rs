@@ -0,0 +1,357 @@
+use chip8::interpreter::chip8::Chip8;
+use chip8::interpreter::instruction::Instruction;
+use chip8::interpreter::trace::{self, Filter, Format, Record, Tracer};
+use chip8::interpreter::trace::{I_CHANGED, MAGIC, RECORD_SIZE, VERSION};
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// Output is an output whose contents can be read after the tracer is done.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// steps returns the first eight steps of the program
+//
+//   0x200  LD V0, 0x00
+//   0x202  LD I, 0x300
+//   0x204  ADD V0, 0x01
+//   0x206  JP 0x204
+//
+// as its tracer sees them.
+fn steps() -> Vec<(u16, Instruction, [u8; 16], u16, u32)> {
+    let mut steps = vec![
+        (0x200, Instruction::I6XNN(0, 0), [0; 16], 0, 0),
+        (0x202, Instruction::IANNN(0x300), [0; 16], 0x300, I_CHANGED),
+    ];
+    for n in 1..4 {
+        let mut v = [0; 16];
+        v[0] = n;
+        steps.push((0x204, Instruction::I7XNN(0, 1), v, 0x300, 1));
+        steps.push((0x206, Instruction::I1NNN(0x204), v, 0x300, 0));
+    }
+    steps
+}
+
+// records returns the steps as records.
+fn records() -> Vec<Record> {
+    steps()
+        .into_iter()
+        .enumerate()
+        .map(|(step, (pc, instr, v, i, changed))| Record {
+            step: step as u64,
+            pc,
+            opcode: instr.encode(),
+            v,
+            i,
+            changed,
+        })
+        .collect()
+}
+
+// trace records every step with a tracer, and returns what it wrote.
+fn trace(format: Format, filter: Filter) -> Vec<u8> {
+    let out = Output::default();
+    let mut tracer = Tracer::new(Box::new(out.clone()), format, filter)
+        .expect("writing to memory");
+    for (pc, instr, v, i, changed) in steps() {
+        tracer.record(pc, &instr, v, i, changed);
+    }
+    tracer.flush().unwrap();
+    let written = out.0.borrow().clone();
+    written
+}
+
+#[test]
+fn formats() {
+    let mut binary = MAGIC.to_vec();
+    binary.push(VERSION);
+    for record in records() {
+        binary.extend_from_slice(&record.to_bytes());
+    }
+    assert_eq!(trace(Format::Binary, Filter::default()), binary);
+
+    let json: String = records().iter().map(|r| r.to_json() + "\n").collect();
+    let written = trace(Format::JsonLines, Filter::default());
+    assert_eq!(String::from_utf8(written).unwrap(), json);
+}
+
+// A machine traces every step it runs.
+#[test]
+fn machine() {
+    let out = Output::default();
+    let tracer =
+        Tracer::new(Box::new(out.clone()), Format::Binary, Filter::default())
+            .unwrap();
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&[0x60, 0x00, 0xA3, 0x00, 0x70, 0x01, 0x12, 0x04])
+        .unwrap();
+    c8.set_tracer(Some(tracer));
+    c8.run_frame(8).unwrap();
+    c8.set_tracer(None);
+    assert_eq!(*out.0.borrow(), trace(Format::Binary, Filter::default()));
+}
+
+#[test]
+fn round_trip() {
+    for (name, format) in &[
+        ("chip8-trace.bin", Format::Binary),
+        ("chip8-trace.jsonl", Format::JsonLines),
+    ] {
+        let file = env::temp_dir().join(name);
+        let file = file.to_str().unwrap();
+        let mut tracer =
+            Tracer::create(file, *format, Filter::default()).unwrap();
+        for (pc, instr, v, i, changed) in steps() {
+            tracer.record(pc, &instr, v, i, changed);
+        }
+        tracer.flush().unwrap();
+        assert_eq!(trace::read(file).unwrap(), records());
+        fs::remove_file(file).ok();
+    }
+}
+
+#[test]
+fn filters() {
+    let header = MAGIC.len() + 1;
+    let traced = |filter| -> Vec<u64> {
+        trace(Format::Binary, filter)[header..]
+            .chunks(RECORD_SIZE)
+            .map(|b| {
+                let mut step = [0; 8];
+                step.copy_from_slice(&b[..8]);
+                u64::from_le_bytes(step)
+            })
+            .collect()
+    };
+
+    let range = Filter {
+        range: Some((0x204, 0x205)),
+        kinds: None,
+    };
+    assert_eq!(traced(range), [2, 4, 6]);
+
+    let jumps = Filter {
+        range: None,
+        kinds: Some(vec![Instruction::I1NNN(0).id()]),
+    };
+    assert_eq!(traced(jumps), [3, 5, 7]);
+}
+
+#[test]
+fn encodings() {
+    let mut v = [0; 16];
+    v[0xA] = 0xFF;
+    let record = Record {
+        step: 1 << 40,
+        pc: 0x2fe,
+        opcode: 0x8AB4,
+        v,
+        i: 0xfff,
+        changed: 1 << 0xA | 1 << 0xF | I_CHANGED,
+    };
+    let bytes = record.to_bytes();
+    assert_eq!(bytes.len(), RECORD_SIZE);
+    assert_eq!(bytes[0..8], (1u64 << 40).to_le_bytes());
+    assert_eq!(bytes[8..10], [0xfe, 0x02]);
+    assert_eq!(bytes[10..12], [0xB4, 0x8A]);
+    assert_eq!(bytes[12..16], record.changed.to_le_bytes());
+    assert_eq!(bytes[16..32], v);
+    assert_eq!(bytes[32..34], [0xff, 0x0f]);
+    assert_eq!(Record::from_bytes(&bytes), record);
+
+    let json = record.to_json();
+    assert!(json.contains("\"instr\":\"ADD VA, VB\""), "{}", json);
+    assert!(json.ends_with("\"delta\":{\"VA\":255,\"VF\":0,\"I\":4095}}"));
+    assert_eq!(Record::from_json(&json), Some(record));
+
+    // Opcodes that do not decode are kept
+    let record = Record {
+        opcode: 0xFFFF,
+        changed: 0,
+        ..record
+    };
+    assert_eq!(Record::from_json(&record.to_json()), Some(record));
+
+    assert_eq!(Record::from_json("{}"), None);
+    assert_eq!(Record::from_json("{\"step\":1,\"pc\":x}"), None);
+}
+
+#[test]
+fn parse_filters() {
+    assert_eq!(Filter::parse_range("0x200-0x2ff"), Ok((0x200, 0x2ff)));
+    assert_eq!(Filter::parse_range("512"), Ok((0x200, 0x200)));
+    for bad in &["", "0x300-0x200", "0x200-", "x"] {
+        assert!(Filter::parse_range(bad).is_err(), "{}", bad);
+    }
+
+    let draw = Instruction::IDXYN(0, 0, 0).id();
+    assert_eq!(Filter::parse_kinds("dxyn"), Ok(vec![draw]));
+    let skips = Filter::parse_kinds("SE").unwrap();
+    assert_eq!(skips.len(), 2);
+    assert!(skips.contains(&Instruction::I3XNN(0, 0).id()));
+    assert!(skips.contains(&Instruction::I5XY0(0, 0).id()));
+    assert!(Filter::parse_kinds("DXYN,nope").is_err());
+}
+
+// The step that faults is traced, with the registers as it left them.
+#[test]
+fn faults() {
+    let file = env::temp_dir().join("chip8-trace-fault.jsonl");
+    let file = file.to_str().unwrap();
+    let rom = [0x60, 0x05, 0x00, 0xEE];
+    let mut c8 = Chip8::new();
+    c8.load_rom_bytes(&rom).unwrap();
+    let tracer =
+        Tracer::create(file, Format::JsonLines, Filter::default()).unwrap();
+    c8.set_tracer(Some(tracer));
+    assert!(c8.run_frame(10).is_err());
+    c8.take_tracer().unwrap().flush().unwrap();
+    let records = trace::read(file).unwrap();
+    fs::remove_file(file).ok();
+    let pcs: Vec<u16> = records.iter().map(|r| r.pc).collect();
+    assert_eq!(pcs, [0x200, 0x202]);
+    assert_eq!((records[1].opcode, records[1].v[0]), (0x00EE, 5));
+}
+
+// Broken is an output that fails every write.
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("broken"))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Tracing stops at the first error, which is kept for the caller.
+#[test]
+fn write_errors() {
+    let mut tracer =
+        Tracer::new(Box::new(Broken), Format::JsonLines, Filter::default())
+            .unwrap();
+    for (pc, instr, v, i, changed) in steps() {
+        tracer.record(pc, &instr, v, i, changed);
+    }
+    assert_eq!(tracer.error().unwrap().to_string(), "broken");
+    assert!(tracer.flush().is_err());
+    assert!(tracer.error().is_none());
+}
+
+#[test]
+fn bad_files() {
+    let file = env::temp_dir().join("chip8-trace-bad");
+    let file = file.to_str().unwrap();
+    let mut truncated = MAGIC.to_vec();
+    truncated.push(VERSION);
+    truncated.extend_from_slice(&[0; RECORD_SIZE - 1]);
+    let versioned = [&MAGIC[..], &[VERSION + 1]].concat();
+    let json = b"{\"step\":0}\n".to_vec();
+    for data in &[truncated, versioned, json] {
+        fs::write(file, data).unwrap();
+        assert!(trace::read(file).is_err());
+    }
+    fs::remove_file(file).ok();
+}
+
+// record returns a record of a step, with V0 set.
+fn record(step: u64, pc: u16, v0: u8) -> Record {
+    let mut v = [0; 16];
+    v[0] = v0;
+    Record {
+        step,
+        pc,
+        opcode: 0x7001,
+        v,
+        i: 0,
+        changed: 1,
+    }
+}
+
+// counting returns a trace of n steps, with V0 counting them.
+fn counting(n: usize) -> Vec<Record> {
+    (0..n)
+        .map(|s| record(s as u64, 0x200 + 2 * s as u16, s as u8))
+        .collect()
+}
+
+#[test]
+fn identical_traces() {
+    assert!(trace::diff(&counting(10), &counting(10)).is_none());
+    assert!(trace::diff(&[], &[]).is_none());
+
+    // Step numbers are not compared
+    let renumbered: Vec<Record> = counting(10)
+        .into_iter()
+        .map(|r| Record {
+            step: r.step + 100,
+            ..r
+        })
+        .collect();
+    assert!(trace::diff(&counting(10), &renumbered).is_none());
+}
+
+#[test]
+fn register_divergence() {
+    let left = counting(10);
+    let mut right = counting(10);
+    right[6].v[0] = 0x42;
+    let divergence = trace::diff(&left, &right).unwrap();
+    assert_eq!(divergence.index, 6);
+    assert_eq!(divergence.last, Some(left[5]));
+    assert_eq!(
+        (divergence.left, divergence.right),
+        (Some(left[6]), Some(right[6]))
+    );
+    let text = divergence.to_string();
+    assert!(text.contains("V0    0x06  0x42 <"), "{}", text);
+    assert!(text.contains("V1    0x00  0x00\n"), "{}", text);
+
+    // A trace that ends first diverges where it ends
+    let divergence = trace::diff(&left, &left[..4]).unwrap();
+    assert_eq!(divergence.index, 4);
+    assert_eq!(divergence.right, None);
+    assert!(divergence.to_string().contains("(end of trace)"));
+}
+
+#[test]
+fn extra_steps_within_the_window() {
+    let trace = counting(100);
+
+    // The first steps are missing from one trace
+    for &skipped in &[1, 63] {
+        assert!(trace::diff(&trace, &trace[skipped..]).is_none());
+        assert!(trace::diff(&trace[skipped..], &trace).is_none());
+    }
+
+    // A step is missing from the middle
+    let mut skipped = trace.clone();
+    skipped.remove(50);
+    let divergence = trace::diff(&trace, &skipped).unwrap();
+    assert_eq!(divergence.index, 50);
+    assert_eq!(divergence.last, Some(trace[49]));
+    assert_eq!(divergence.right, Some(trace[51]));
+}
+
+#[test]
+fn extra_steps_beyond_the_window() {
+    let trace = counting(100);
+    let divergence = trace::diff(&trace, &trace[64..]).unwrap();
+    assert_eq!(divergence.index, 0);
+    assert_eq!(divergence.last, None);
+    assert_eq!(divergence.left, Some(trace[0]));
+    assert_eq!(divergence.right, Some(trace[64]));
+}
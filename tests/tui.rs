@@ -0,0 +1,39 @@
+use chip8::screenshot::Image;
+use chip8::tui::{render, Glyphs};
+
+// IMAGE is a 4x4 image with its top left and bottom right pixels on, and a
+// full column on the right.
+#[rustfmt::skip]
+const IMAGE: [u8; 16] = [
+    1, 0, 0, 1,
+    0, 0, 0, 1,
+    0, 0, 0, 1,
+    0, 0, 1, 1,
+];
+
+#[test]
+fn half_blocks() {
+    let image = Image::new(&IMAGE, 4, 4);
+    assert_eq!(
+        render(image, Glyphs::HalfBlock),
+        vec!["\u{2580}  \u{2588}", "  \u{2584}\u{2588}"]
+    );
+}
+
+#[test]
+fn braille() {
+    let image = Image::new(&IMAGE, 4, 4);
+    // The left cell has its top left dot, the right cell its right column
+    // and bottom left dot
+    assert_eq!(render(image, Glyphs::Braille), vec!["\u{2801}\u{28f8}"]);
+}
+
+#[test]
+fn odd_sizes() {
+    let image = Image::new(&[1, 1, 1], 3, 1);
+    assert_eq!(
+        render(image, Glyphs::HalfBlock),
+        vec!["\u{2580}\u{2580}\u{2580}"]
+    );
+    assert_eq!(render(image, Glyphs::Braille), vec!["\u{2809}\u{2801}"]);
+}